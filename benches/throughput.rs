@@ -0,0 +1,313 @@
+//! Throughput of every filter, as the share of one core a real-time 48 kHz stream takes.
+//!
+//! Each filter is measured rendering interleaved audio sample by sample through
+//! `Box<dyn Filter>`, with `Filter::process_block` on planar buffers, and for f32 stereo through
+//! `MultiChannelFilter::render_frame`, which is what the audio callback runs. The filters with SIMD
+//! versions are also run on 8 channels, scalar and through a `LaneBank` of each vector width.
+//! Pass a substring to only run matching filters:
+//!
+//! ```text
+//! cargo bench --bench throughput -- Lattice
+//! ```
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use filters::simd::{f32x4, f32x8, f64x2, f64x4};
+use filters::{
+    BiQuadLanes, BiQuadTopology, Filter, FilterParams, FixedFormat, FixedPointParams,
+    ImportedDesign, LaneBank, LaneFilter, Lanes, MultiChannelFilter, NoiseGen, Sample,
+    SelectedFilter, StateVariableLanes,
+};
+
+const SAMPLE_RATE: f32 = 48000.0;
+/// Audio processed per measurement.
+const SECONDS: usize = 2;
+const FRAMES: usize = SAMPLE_RATE as usize * SECONDS;
+const BLOCK: usize = 256;
+/// Measurements per case; the fastest is reported.
+const RUNS: usize = 5;
+/// Channels for comparing SIMD with scalar processing.
+const SIMD_CHANNELS: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Mode {
+    Sample,
+    Block,
+    Frame,
+    Simd,
+}
+
+struct Case {
+    name: String,
+    params: FilterParams,
+    imported: Option<ImportedDesign>,
+}
+
+fn cases() -> Vec<Case> {
+    let params = FilterParams {
+        cutoff_hz: 1000.0,
+        resonance_q: 2.0,
+        ..FilterParams::default()
+    };
+    let case = |name: &str, params: FilterParams| Case {
+        name: name.to_string(),
+        params,
+        imported: None,
+    };
+
+    let mut cases = vec![
+        case(
+            "FirLowPass",
+            FilterParams {
+                filter: SelectedFilter::FirLowPass,
+                ..params
+            },
+        ),
+        case(
+            "FirHighPass",
+            FilterParams {
+                filter: SelectedFilter::FirHighPass,
+                ..params
+            },
+        ),
+        case(
+            "StateVariable",
+            FilterParams {
+                filter: SelectedFilter::StateVariable,
+                ..params
+            },
+        ),
+        case(
+            "StateVariableTPT",
+            FilterParams {
+                filter: SelectedFilter::StateVariableTPT,
+                ..params
+            },
+        ),
+    ];
+    for topology in [
+        BiQuadTopology::DirectFormI,
+        BiQuadTopology::DirectFormII,
+        BiQuadTopology::TransposedDirectFormII,
+        BiQuadTopology::Lattice,
+    ] {
+        cases.push(case(
+            &format!("BiQuad {:?}", topology),
+            FilterParams {
+                filter: SelectedFilter::BiQuad,
+                topology,
+                ..params
+            },
+        ));
+    }
+    for format in [FixedFormat::Q15, FixedFormat::Q31] {
+        let fixed = FixedPointParams {
+            format,
+            coefficient_bits: 16,
+        };
+        for filter in [
+            SelectedFilter::FixedBiQuad,
+            SelectedFilter::FixedStateVariable,
+        ] {
+            cases.push(case(
+                &format!("{:?} {:?}", filter, format),
+                FilterParams {
+                    filter,
+                    fixed,
+                    ..params
+                },
+            ));
+        }
+    }
+
+    let imported = FilterParams {
+        filter: SelectedFilter::Imported,
+        ..params
+    };
+    let sos = "0.00482434 0.00964869 0.00482434 1 -1.04859958 0.29614036\n\
+               1 2 1 1 -1.32091343 0.63273879";
+    cases.push(Case {
+        name: "Imported SOS, 2 sections".to_string(),
+        params: imported,
+        imported: Some(ImportedDesign::parse(sos).unwrap()),
+    });
+    let taps = vec!["0.015625"; 64].join(" ");
+    cases.push(Case {
+        name: "Imported FIR, 64 taps".to_string(),
+        params: imported,
+        imported: Some(ImportedDesign::parse(&taps).unwrap()),
+    });
+    cases
+}
+
+fn noise<T: Sample>(len: usize) -> Vec<T> {
+    let mut noise = NoiseGen::new();
+    (0..len)
+        .map(|_| T::cast(noise.next_value() as f64 * 0.5))
+        .collect()
+}
+
+/// Runs `process` on a fresh copy of `input` `RUNS` times and returns the fastest time.
+fn fastest<B: Clone>(input: &B, mut process: impl FnMut(&mut B)) -> Duration {
+    (0..RUNS)
+        .map(|_| {
+            let mut buffer = input.clone();
+            let start = Instant::now();
+            process(&mut buffer);
+            let elapsed = start.elapsed();
+            black_box(buffer);
+            elapsed
+        })
+        .min()
+        .unwrap()
+}
+
+fn measure<T: Sample>(case: &Case, channels: usize, mode: Mode) -> Duration {
+    let mut filters: Vec<Box<dyn Filter<T>>> = (0..channels)
+        .map(|_| {
+            case.params
+                .create_filter_as::<T>(SAMPLE_RATE, case.imported.as_ref())
+        })
+        .collect();
+
+    match mode {
+        Mode::Sample => fastest(&noise::<T>(FRAMES * channels), |buffer| {
+            for frame in buffer.chunks_mut(channels) {
+                for (sample, filter) in frame.iter_mut().zip(filters.iter_mut()) {
+                    *sample = filter.render(*sample);
+                }
+            }
+        }),
+        Mode::Block => {
+            let planar: Vec<Vec<T>> = (0..channels).map(|_| noise::<T>(FRAMES)).collect();
+            fastest(&planar, |buffer| {
+                for (channel, filter) in buffer.iter_mut().zip(filters.iter_mut()) {
+                    for block in channel.chunks_mut(BLOCK) {
+                        filter.process_block(block);
+                    }
+                }
+            })
+        }
+        Mode::Frame => {
+            let mut filter = MultiChannelFilter::new(SAMPLE_RATE, channels, case.params);
+            filter.set_imported(case.imported.clone().map(std::sync::Arc::new));
+            fastest(&noise::<f32>(FRAMES * channels), |buffer| {
+                for frame in buffer.chunks_mut(channels) {
+                    filter.render_frame(frame);
+                }
+            })
+        }
+        Mode::Simd => unreachable!("SIMD filters are measured by `measure_lanes`"),
+    }
+}
+
+fn measure_lanes<L: Lanes, F: LaneFilter<L>>(case: &Case, channels: usize) -> Duration {
+    let mut bank = LaneBank::<L, F>::new(SAMPLE_RATE, channels);
+    bank.update_all(case.params.cutoff_hz, case.params.resonance_q);
+    fastest(&noise::<L::Scalar>(FRAMES * channels), |buffer| {
+        for block in buffer.chunks_mut(BLOCK * channels) {
+            bank.process_interleaved(block);
+        }
+    })
+}
+
+type LaneMeasurement = fn(&Case, usize) -> Duration;
+
+/// Runs the SIMD version of the case's filter, if it has one.
+fn simd(case: &Case) {
+    let lanes: [(&str, LaneMeasurement); 4] = match case.params.filter {
+        SelectedFilter::BiQuad if case.params.topology == BiQuadTopology::DirectFormI => [
+            ("f32x4", measure_lanes::<f32x4, BiQuadLanes<f32x4>>),
+            ("f32x8", measure_lanes::<f32x8, BiQuadLanes<f32x8>>),
+            ("f64x2", measure_lanes::<f64x2, BiQuadLanes<f64x2>>),
+            ("f64x4", measure_lanes::<f64x4, BiQuadLanes<f64x4>>),
+        ],
+        SelectedFilter::StateVariable => [
+            ("f32x4", measure_lanes::<f32x4, StateVariableLanes<f32x4>>),
+            ("f32x8", measure_lanes::<f32x8, StateVariableLanes<f32x8>>),
+            ("f64x2", measure_lanes::<f64x2, StateVariableLanes<f64x2>>),
+            ("f64x4", measure_lanes::<f64x4, StateVariableLanes<f64x4>>),
+        ],
+        _ => return,
+    };
+
+    let channels = SIMD_CHANNELS;
+    // The scalar baseline is block processing, the fastest scalar mode.
+    let f32_block = measure::<f32>(case, channels, Mode::Block);
+    report(case, "f32", channels, Mode::Block, f32_block);
+    let f64_block = measure::<f64>(case, channels, Mode::Block);
+    report(case, "f64", channels, Mode::Block, f64_block);
+    for (precision, measure) in lanes {
+        report(
+            case,
+            precision,
+            channels,
+            Mode::Simd,
+            measure(case, channels),
+        );
+    }
+}
+
+fn report(case: &Case, precision: &str, channels: usize, mode: Mode, elapsed: Duration) {
+    let samples = (FRAMES * channels) as f64;
+    let seconds = elapsed.as_secs_f64();
+    let layout = match channels {
+        1 => "mono".to_string(),
+        2 => "stereo".to_string(),
+        channels => format!("{} ch", channels),
+    };
+    println!(
+        "{:<30} {:>5} {:>7} {:>6} {:>10.1} {:>9.3}%",
+        case.name,
+        precision,
+        layout,
+        format!("{:?}", mode).to_lowercase(),
+        samples / seconds / 1e6,
+        seconds / SECONDS as f64 * 100.0
+    );
+}
+
+fn main() {
+    // `cargo bench` passes `--bench`; anything else is a filter on the case name.
+    let filter = std::env::args().skip(1).find(|arg| !arg.starts_with("--"));
+
+    println!(
+        "{:<30} {:>5} {:>7} {:>6} {:>10} {:>10}",
+        "filter", "type", "layout", "mode", "Msample/s", "48 kHz CPU"
+    );
+    for case in cases() {
+        if filter
+            .as_ref()
+            .is_some_and(|filter| !case.name.contains(filter.as_str()))
+        {
+            continue;
+        }
+        for channels in [1, 2] {
+            for mode in [Mode::Sample, Mode::Block] {
+                report(
+                    &case,
+                    "f32",
+                    channels,
+                    mode,
+                    measure::<f32>(&case, channels, mode),
+                );
+                report(
+                    &case,
+                    "f64",
+                    channels,
+                    mode,
+                    measure::<f64>(&case, channels, mode),
+                );
+            }
+        }
+        report(
+            &case,
+            "f32",
+            2,
+            Mode::Frame,
+            measure::<f32>(&case, 2, Mode::Frame),
+        );
+        simd(&case);
+    }
+}
@@ -0,0 +1,538 @@
+//! The CLAP interface: the entry point, the plugin factory, and the plugin with its params, state
+//! and audio-ports extensions.
+//!
+//! The host calls in from its main thread and its audio thread. Parameter values live in
+//! `SharedParams`, so reading, setting, saving and loading them never blocks the audio thread.
+//! The filter itself is in a `Mutex<FilterPlugin>` that the audio thread only `try_lock`s; the
+//! main thread takes it in `activate` and `deactivate`, which CLAP doesn't call while
+//! processing. No callback panics on a poisoned lock, since unwinding out of `extern "C"` aborts
+//! the host.
+
+use std::ffi::{c_char, c_void, CStr};
+use std::ptr;
+use std::sync::{Mutex, MutexGuard, PoisonError, TryLockError};
+
+use clap_sys::entry::clap_plugin_entry;
+use clap_sys::events::{
+    clap_event_header, clap_event_param_value, clap_input_events, clap_output_events,
+    CLAP_CORE_EVENT_SPACE_ID, CLAP_EVENT_PARAM_VALUE,
+};
+use clap_sys::ext::audio_ports::{
+    clap_audio_port_info, clap_plugin_audio_ports, CLAP_AUDIO_PORT_IS_MAIN, CLAP_EXT_AUDIO_PORTS,
+    CLAP_PORT_STEREO,
+};
+use clap_sys::ext::params::{
+    clap_host_params, clap_param_info, clap_plugin_params, CLAP_EXT_PARAMS,
+    CLAP_PARAM_IS_AUTOMATABLE, CLAP_PARAM_IS_ENUM, CLAP_PARAM_IS_STEPPED, CLAP_PARAM_RESCAN_VALUES,
+};
+use clap_sys::ext::state::{clap_plugin_state, CLAP_EXT_STATE};
+use clap_sys::factory::plugin_factory::{clap_plugin_factory, CLAP_PLUGIN_FACTORY_ID};
+use clap_sys::host::clap_host;
+use clap_sys::id::clap_id;
+use clap_sys::plugin::{clap_plugin, clap_plugin_descriptor};
+use clap_sys::plugin_features::{
+    CLAP_PLUGIN_FEATURE_AUDIO_EFFECT, CLAP_PLUGIN_FEATURE_FILTER, CLAP_PLUGIN_FEATURE_STEREO,
+};
+use clap_sys::process::{
+    clap_process, clap_process_status, CLAP_PROCESS_CONTINUE, CLAP_PROCESS_ERROR,
+};
+use clap_sys::stream::{clap_istream, clap_ostream};
+use clap_sys::version::CLAP_VERSION;
+
+use crate::params::{self, ParamId, PluginParams, SharedParams};
+use crate::{load_state, save_state, FilterPlugin, CHANNELS};
+
+const PLUGIN_ID: &CStr = c"audio-filters.filter";
+
+/// A null-terminated list of C strings, which isn't `Sync` on its own.
+struct Features([*const c_char; 4]);
+
+unsafe impl Sync for Features {}
+
+static FEATURES: Features = Features([
+    CLAP_PLUGIN_FEATURE_AUDIO_EFFECT.as_ptr(),
+    CLAP_PLUGIN_FEATURE_FILTER.as_ptr(),
+    CLAP_PLUGIN_FEATURE_STEREO.as_ptr(),
+    ptr::null(),
+]);
+
+static DESCRIPTOR: clap_plugin_descriptor = clap_plugin_descriptor {
+    clap_version: CLAP_VERSION,
+    id: PLUGIN_ID.as_ptr(),
+    name: c"Filter".as_ptr(),
+    vendor: c"audio_filters".as_ptr(),
+    url: c"".as_ptr(),
+    manual_url: c"".as_ptr(),
+    support_url: c"".as_ptr(),
+    version: c"0.1.0".as_ptr(),
+    description: c"The audio_filters playground's filters, with cutoff, resonance and stereo mode"
+        .as_ptr(),
+    features: FEATURES.0.as_ptr(),
+};
+
+/// What `clap_plugin::plugin_data` points to.
+struct Instance {
+    plugin: clap_plugin,
+    host: *const clap_host,
+    params: SharedParams,
+    state: Mutex<FilterPlugin>,
+}
+
+impl Instance {
+    /// For the main thread, when the audio thread isn't processing.
+    fn lock(&self) -> MutexGuard<'_, FilterPlugin> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// For the audio thread, which mustn't wait. `None` if another thread holds the lock.
+    fn try_lock(&self) -> Option<MutexGuard<'_, FilterPlugin>> {
+        match self.state.try_lock() {
+            Ok(state) => Some(state),
+            Err(TryLockError::Poisoned(poisoned)) => Some(poisoned.into_inner()),
+            Err(TryLockError::WouldBlock) => None,
+        }
+    }
+}
+
+/// # Safety
+///
+/// `plugin` must have been returned by `create_plugin` and not destroyed.
+unsafe fn instance<'a>(plugin: *const clap_plugin) -> &'a Instance {
+    &*((*plugin).plugin_data as *const Instance)
+}
+
+/// Copies `text` into a C buffer of `capacity` bytes, truncated and null-terminated.
+///
+/// # Safety
+///
+/// `out` must point to `capacity` writable bytes.
+unsafe fn write_c_string(text: &str, out: *mut c_char, capacity: usize) {
+    if capacity == 0 {
+        return;
+    }
+    let len = text.len().min(capacity - 1);
+    ptr::copy_nonoverlapping(text.as_ptr() as *const c_char, out, len);
+    *out.add(len) = 0;
+}
+
+/// Applies a parameter change and returns the parameter it set. Other events are ignored.
+///
+/// # Safety
+///
+/// `header` must point to a complete event of the type it declares.
+unsafe fn apply_event(params: &SharedParams, header: *const clap_event_header) -> Option<ParamId> {
+    let header = &*header;
+    if header.space_id != CLAP_CORE_EVENT_SPACE_ID || header.type_ != CLAP_EVENT_PARAM_VALUE {
+        return None;
+    }
+    let event = &*(header as *const clap_event_header as *const clap_event_param_value);
+    let id = ParamId::from_id(event.param_id)?;
+    params.set(id, event.value);
+    Some(id)
+}
+
+/// # Safety
+///
+/// `events` must be NULL or a valid event list.
+unsafe fn apply_events(params: &SharedParams, events: *const clap_input_events) {
+    let Some(list) = events.as_ref() else {
+        return;
+    };
+    let (Some(size), Some(get)) = (list.size, list.get) else {
+        return;
+    };
+    for index in 0..size(events) {
+        apply_event(params, get(events, index));
+    }
+}
+
+unsafe extern "C" fn init(_plugin: *const clap_plugin) -> bool {
+    true
+}
+
+unsafe extern "C" fn destroy(plugin: *const clap_plugin) {
+    drop(Box::from_raw((*plugin).plugin_data as *mut Instance));
+}
+
+unsafe extern "C" fn activate(
+    plugin: *const clap_plugin,
+    sample_rate: f64,
+    _min_frames_count: u32,
+    _max_frames_count: u32,
+) -> bool {
+    let instance = instance(plugin);
+    let mut state = instance.lock();
+    state.set_params(instance.params.load());
+    state.activate(sample_rate as f32);
+    true
+}
+
+unsafe extern "C" fn deactivate(plugin: *const clap_plugin) {
+    instance(plugin).lock().deactivate();
+}
+
+unsafe extern "C" fn start_processing(_plugin: *const clap_plugin) -> bool {
+    true
+}
+
+unsafe extern "C" fn stop_processing(_plugin: *const clap_plugin) {}
+
+unsafe extern "C" fn reset(plugin: *const clap_plugin) {
+    if let Some(mut state) = instance(plugin).try_lock() {
+        state.reset();
+    }
+}
+
+/// Filters the main port in place, splitting the block at each parameter change so automation
+/// is sample-accurate. If the filter is locked by another thread the block passes through
+/// unfiltered; its parameter changes still apply from the next block.
+unsafe extern "C" fn process(
+    plugin: *const clap_plugin,
+    process: *const clap_process,
+) -> clap_process_status {
+    let process = &*process;
+    let instance = instance(plugin);
+    let frames = process.frames_count as usize;
+
+    if process.audio_outputs_count == 0 || process.audio_outputs.is_null() {
+        apply_events(&instance.params, process.in_events);
+        return CLAP_PROCESS_CONTINUE;
+    }
+    let output = &*process.audio_outputs;
+    if output.data32.is_null() {
+        return CLAP_PROCESS_ERROR;
+    }
+    let input = if process.audio_inputs_count > 0 && !process.audio_inputs.is_null() {
+        Some(&*process.audio_inputs)
+    } else {
+        None
+    };
+
+    // Hosts may process in place or give separate buffers; the output ends up holding the input
+    // either way, and is filtered in place.
+    let channels = (output.channel_count as usize).min(CHANNELS);
+    let mut outputs = [ptr::null_mut::<f32>(); CHANNELS];
+    for (channel, out) in outputs.iter_mut().enumerate().take(channels) {
+        *out = *output.data32.add(channel);
+        let source = match input {
+            Some(input) if channel < input.channel_count as usize && !input.data32.is_null() => {
+                *input.data32.add(channel)
+            }
+            _ => ptr::null_mut(),
+        };
+        if source.is_null() {
+            ptr::write_bytes(*out, 0, frames);
+        } else if source != *out {
+            ptr::copy_nonoverlapping(source, *out, frames);
+        }
+    }
+
+    let Some(mut state) = instance.try_lock() else {
+        apply_events(&instance.params, process.in_events);
+        return CLAP_PROCESS_CONTINUE;
+    };
+    // Picks up changes the main thread made since the last block.
+    state.set_params(instance.params.load());
+
+    let render = |state: &mut FilterPlugin, start: usize, end: usize| {
+        if end > start {
+            let mut block: [&mut [f32]; CHANNELS] = Default::default();
+            for (slice, out) in block.iter_mut().zip(outputs).take(channels) {
+                *slice = std::slice::from_raw_parts_mut(out.add(start), end - start);
+            }
+            state.process(&mut block[..channels]);
+        }
+    };
+
+    let mut start = 0;
+    if let Some(events) = process.in_events.as_ref() {
+        if let (Some(size), Some(get)) = (events.size, events.get) {
+            for index in 0..size(process.in_events) {
+                let header = get(process.in_events, index);
+                let time = ((*header).time as usize).min(frames);
+                render(&mut state, start, time);
+                start = start.max(time);
+                if let Some(id) = apply_event(&instance.params, header) {
+                    state.set_param(id, instance.params.get(id));
+                }
+            }
+        }
+    }
+    render(&mut state, start, frames);
+    CLAP_PROCESS_CONTINUE
+}
+
+unsafe extern "C" fn get_extension(
+    _plugin: *const clap_plugin,
+    id: *const c_char,
+) -> *const c_void {
+    let id = CStr::from_ptr(id);
+    if id == CLAP_EXT_PARAMS {
+        &PARAMS as *const clap_plugin_params as *const c_void
+    } else if id == CLAP_EXT_STATE {
+        &STATE as *const clap_plugin_state as *const c_void
+    } else if id == CLAP_EXT_AUDIO_PORTS {
+        &AUDIO_PORTS as *const clap_plugin_audio_ports as *const c_void
+    } else {
+        ptr::null()
+    }
+}
+
+unsafe extern "C" fn on_main_thread(_plugin: *const clap_plugin) {}
+
+static PARAMS: clap_plugin_params = clap_plugin_params {
+    count: Some(params_count),
+    get_info: Some(params_get_info),
+    get_value: Some(params_get_value),
+    value_to_text: Some(params_value_to_text),
+    text_to_value: Some(params_text_to_value),
+    flush: Some(params_flush),
+};
+
+unsafe extern "C" fn params_count(_plugin: *const clap_plugin) -> u32 {
+    ParamId::ALL.len() as u32
+}
+
+unsafe extern "C" fn params_get_info(
+    _plugin: *const clap_plugin,
+    index: u32,
+    info: *mut clap_param_info,
+) -> bool {
+    let Some(&id) = ParamId::ALL.get(index as usize) else {
+        return false;
+    };
+    let info = &mut *info;
+    let (min, max) = id.range();
+    info.id = id as clap_id;
+    info.flags = CLAP_PARAM_IS_AUTOMATABLE;
+    if id.is_choice() {
+        info.flags |= CLAP_PARAM_IS_STEPPED | CLAP_PARAM_IS_ENUM;
+    }
+    info.cookie = ptr::null_mut();
+    write_c_string(id.name(), info.name.as_mut_ptr(), info.name.len());
+    write_c_string("", info.module.as_mut_ptr(), info.module.len());
+    info.min_value = min;
+    info.max_value = max;
+    info.default_value = id.default_value();
+    true
+}
+
+unsafe extern "C" fn params_get_value(
+    plugin: *const clap_plugin,
+    id: clap_id,
+    value: *mut f64,
+) -> bool {
+    let Some(id) = ParamId::from_id(id) else {
+        return false;
+    };
+    *value = instance(plugin).params.get(id);
+    true
+}
+
+unsafe extern "C" fn params_value_to_text(
+    _plugin: *const clap_plugin,
+    id: clap_id,
+    value: f64,
+    out: *mut c_char,
+    capacity: u32,
+) -> bool {
+    let Some(id) = ParamId::from_id(id) else {
+        return false;
+    };
+    write_c_string(&params::value_to_text(id, value), out, capacity as usize);
+    true
+}
+
+unsafe extern "C" fn params_text_to_value(
+    _plugin: *const clap_plugin,
+    id: clap_id,
+    text: *const c_char,
+    value: *mut f64,
+) -> bool {
+    let Some(id) = ParamId::from_id(id) else {
+        return false;
+    };
+    let Ok(text) = CStr::from_ptr(text).to_str() else {
+        return false;
+    };
+    match params::text_to_value(id, text) {
+        Some(parsed) => {
+            *value = parsed;
+            true
+        }
+        None => false,
+    }
+}
+
+/// Parameter changes while the plugin isn't processing.
+unsafe extern "C" fn params_flush(
+    plugin: *const clap_plugin,
+    in_events: *const clap_input_events,
+    _out_events: *const clap_output_events,
+) {
+    apply_events(&instance(plugin).params, in_events);
+}
+
+static STATE: clap_plugin_state = clap_plugin_state {
+    save: Some(state_save),
+    load: Some(state_load),
+};
+
+unsafe extern "C" fn state_save(plugin: *const clap_plugin, stream: *const clap_ostream) -> bool {
+    let Ok(json) = save_state(&instance(plugin).params.load()) else {
+        return false;
+    };
+    let Some(write) = (*stream).write else {
+        return false;
+    };
+    let mut bytes = json.as_bytes();
+    while !bytes.is_empty() {
+        let written = write(stream, bytes.as_ptr() as *const c_void, bytes.len() as u64);
+        if written <= 0 {
+            return false;
+        }
+        bytes = &bytes[written as usize..];
+    }
+    true
+}
+
+unsafe extern "C" fn state_load(plugin: *const clap_plugin, stream: *const clap_istream) -> bool {
+    let Some(read) = (*stream).read else {
+        return false;
+    };
+    let mut json = Vec::new();
+    let mut chunk = [0u8; 4096];
+    loop {
+        let count = read(
+            stream,
+            chunk.as_mut_ptr() as *mut c_void,
+            chunk.len() as u64,
+        );
+        match count {
+            0 => break,
+            count if count < 0 => return false,
+            count => json.extend_from_slice(&chunk[..count as usize]),
+        }
+    }
+    let Ok(json) = String::from_utf8(json) else {
+        return false;
+    };
+
+    let Ok(params) = load_state(&json) else {
+        return false;
+    };
+    let instance = instance(plugin);
+    instance.params.store(&params);
+    let host = &*instance.host;
+    if let Some(get_extension) = host.get_extension {
+        let host_params = get_extension(host, CLAP_EXT_PARAMS.as_ptr()) as *const clap_host_params;
+        if let Some(rescan) = host_params.as_ref().and_then(|params| params.rescan) {
+            rescan(host, CLAP_PARAM_RESCAN_VALUES);
+        }
+    }
+    true
+}
+
+static AUDIO_PORTS: clap_plugin_audio_ports = clap_plugin_audio_ports {
+    count: Some(audio_ports_count),
+    get: Some(audio_ports_get),
+};
+
+unsafe extern "C" fn audio_ports_count(_plugin: *const clap_plugin, _is_input: bool) -> u32 {
+    1
+}
+
+unsafe extern "C" fn audio_ports_get(
+    _plugin: *const clap_plugin,
+    index: u32,
+    _is_input: bool,
+    info: *mut clap_audio_port_info,
+) -> bool {
+    if index != 0 {
+        return false;
+    }
+    let info = &mut *info;
+    info.id = 0;
+    write_c_string("Main", info.name.as_mut_ptr(), info.name.len());
+    info.flags = CLAP_AUDIO_PORT_IS_MAIN;
+    info.channel_count = CHANNELS as u32;
+    info.port_type = CLAP_PORT_STEREO.as_ptr();
+    info.in_place_pair = 0;
+    true
+}
+
+static FACTORY: clap_plugin_factory = clap_plugin_factory {
+    get_plugin_count: Some(factory_get_plugin_count),
+    get_plugin_descriptor: Some(factory_get_plugin_descriptor),
+    create_plugin: Some(factory_create_plugin),
+};
+
+unsafe extern "C" fn factory_get_plugin_count(_factory: *const clap_plugin_factory) -> u32 {
+    1
+}
+
+unsafe extern "C" fn factory_get_plugin_descriptor(
+    _factory: *const clap_plugin_factory,
+    index: u32,
+) -> *const clap_plugin_descriptor {
+    if index == 0 {
+        &DESCRIPTOR
+    } else {
+        ptr::null()
+    }
+}
+
+unsafe extern "C" fn factory_create_plugin(
+    _factory: *const clap_plugin_factory,
+    host: *const clap_host,
+    plugin_id: *const c_char,
+) -> *const clap_plugin {
+    if plugin_id.is_null() || CStr::from_ptr(plugin_id) != PLUGIN_ID {
+        return ptr::null();
+    }
+    let instance = Box::into_raw(Box::new(Instance {
+        plugin: clap_plugin {
+            desc: &DESCRIPTOR,
+            plugin_data: ptr::null_mut(),
+            init: Some(init),
+            destroy: Some(destroy),
+            activate: Some(activate),
+            deactivate: Some(deactivate),
+            start_processing: Some(start_processing),
+            stop_processing: Some(stop_processing),
+            reset: Some(reset),
+            process: Some(process),
+            get_extension: Some(get_extension),
+            on_main_thread: Some(on_main_thread),
+        },
+        host,
+        params: SharedParams::new(&PluginParams::default()),
+        state: Mutex::new(FilterPlugin::default()),
+    }));
+    (*instance).plugin.plugin_data = instance as *mut c_void;
+    &(*instance).plugin
+}
+
+unsafe extern "C" fn entry_init(_plugin_path: *const c_char) -> bool {
+    true
+}
+
+unsafe extern "C" fn entry_deinit() {}
+
+unsafe extern "C" fn entry_get_factory(factory_id: *const c_char) -> *const c_void {
+    if CStr::from_ptr(factory_id) == CLAP_PLUGIN_FACTORY_ID {
+        &FACTORY as *const clap_plugin_factory as *const c_void
+    } else {
+        ptr::null()
+    }
+}
+
+/// The symbol CLAP hosts look up in the plugin library.
+#[allow(non_upper_case_globals)]
+#[no_mangle]
+pub static clap_entry: clap_plugin_entry = clap_plugin_entry {
+    clap_version: CLAP_VERSION,
+    init: Some(entry_init),
+    deinit: Some(entry_deinit),
+    get_factory: Some(entry_get_factory),
+};
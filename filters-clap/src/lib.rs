@@ -0,0 +1,148 @@
+//! The filter engine as a CLAP audio effect, for running the playground's filters inside a DAW.
+//!
+//! `FilterPlugin` is the plugin without the host interface: the parameters and the
+//! `MultiChannelFilter` that runs them. `save_state` and `load_state` convert the parameters to
+//! and from a saved state. `entry` exposes it all to hosts through `clap_entry`. The
+//! plugin has no editor, so hosts show their generic parameter controls.
+
+#![warn(clippy::all, rust_2018_idioms)]
+
+mod entry;
+pub mod params;
+
+pub use entry::clap_entry;
+
+use filters::MultiChannelFilter;
+
+use params::{ParamId, PluginParams};
+
+/// Version written to saved states. Bump it when the format changes in a way old readers would
+/// misinterpret.
+pub const STATE_VERSION: u32 = 1;
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SavedState {
+    version: u32,
+    params: PluginParams,
+}
+
+/// Channels of the plugin's input and output.
+pub const CHANNELS: usize = 2;
+
+pub struct FilterPlugin {
+    params: PluginParams,
+    /// Created on activation, when the sample rate is known.
+    filter: Option<MultiChannelFilter>,
+    /// Output gain reached at the end of the last block. Changes are ramped over a block to
+    /// avoid zipper noise.
+    gain: f32,
+}
+
+impl Default for FilterPlugin {
+    fn default() -> Self {
+        let params = PluginParams::default();
+        Self {
+            params,
+            filter: None,
+            gain: params.output_gain(),
+        }
+    }
+}
+
+impl FilterPlugin {
+    pub fn params(&self) -> &PluginParams {
+        &self.params
+    }
+
+    pub fn set_param(&mut self, id: ParamId, value: f64) {
+        self.params.set(id, value);
+    }
+
+    pub fn set_params(&mut self, params: PluginParams) {
+        self.params = params;
+    }
+
+    pub fn activate(&mut self, sample_rate: f32) {
+        let mut filter = MultiChannelFilter::new(
+            sample_rate,
+            CHANNELS,
+            self.params.filter_params(sample_rate),
+        );
+        filter.set_stereo_mode(self.params.stereo_mode);
+        self.filter = Some(filter);
+        self.gain = self.params.output_gain();
+    }
+
+    pub fn deactivate(&mut self) {
+        self.filter = None;
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.filter.is_some()
+    }
+
+    /// Clears the filter state, e.g. when the host jumps to another position.
+    pub fn reset(&mut self) {
+        if let Some(filter) = &mut self.filter {
+            filter.reset();
+        }
+        self.gain = self.params.output_gain();
+    }
+
+    /// Filters planar channels in place with the current parameters. Channels past the first two
+    /// are left as they are. Does nothing until the plugin is activated.
+    pub fn process(&mut self, channels: &mut [&mut [f32]]) {
+        let Some(filter) = &mut self.filter else {
+            return;
+        };
+        filter.set_stereo_mode(self.params.stereo_mode);
+        filter.set_all_params(self.params.filter_params(filter.sample_rate()));
+
+        let count = channels.len().min(CHANNELS);
+        let channels = &mut channels[..count];
+        let frames = channels.iter().map(|c| c.len()).min().unwrap_or(0);
+        let target = self.params.output_gain();
+        let step = (target - self.gain) / frames.max(1) as f32;
+        let mut buffer = [0.0; CHANNELS];
+        for n in 0..frames {
+            let frame = &mut buffer[..channels.len()];
+            for (sample, channel) in frame.iter_mut().zip(channels.iter()) {
+                *sample = channel[n];
+            }
+            filter.render_frame(frame);
+            self.gain += step;
+            for (sample, channel) in frame.iter().zip(channels.iter_mut()) {
+                channel[n] = sample * self.gain;
+            }
+        }
+        if frames > 0 {
+            self.gain = target;
+        }
+    }
+}
+
+/// The saved state for `params`, as JSON.
+pub fn save_state(params: &PluginParams) -> Result<String, String> {
+    let state = SavedState {
+        version: STATE_VERSION,
+        params: *params,
+    };
+    serde_json::to_string(&state).map_err(|err| err.to_string())
+}
+
+/// Reads parameters saved by `save_state`.
+pub fn load_state(json: &str) -> Result<PluginParams, String> {
+    let state: SavedState = serde_json::from_str(json).map_err(|err| err.to_string())?;
+    if state.version > STATE_VERSION {
+        return Err(format!(
+            "unsupported state version {} (this build reads up to {})",
+            state.version, STATE_VERSION
+        ));
+    }
+    // Clamps values from hand-edited or corrupted states into range.
+    let mut params = PluginParams::default();
+    for id in ParamId::ALL {
+        params.set(id, state.params.get(id));
+    }
+    Ok(params)
+}
@@ -0,0 +1,237 @@
+//! The plugin's parameters: what the host automates and what is saved with a session.
+//!
+//! Parameter values are in the units shown to the user (Hz, dB), with the choice parameters as an
+//! index into their list. The IDs are part of saved host sessions and must never change.
+
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use filters::registry::{CUTOFF, MAX_CUTOFF_RATIO, RESONANCE};
+use filters::{FilterParams, SelectedFilter, StereoMode};
+
+/// Filters the plugin offers. `Imported` needs a design file, which a plugin parameter can't
+/// carry.
+pub const FILTERS: [SelectedFilter; 7] = [
+    SelectedFilter::StateVariable,
+    SelectedFilter::StateVariableTPT,
+    SelectedFilter::BiQuad,
+    SelectedFilter::FirLowPass,
+    SelectedFilter::FirHighPass,
+    SelectedFilter::FixedBiQuad,
+    SelectedFilter::FixedStateVariable,
+];
+
+pub const STEREO_MODES: [StereoMode; 2] = [StereoMode::LeftRight, StereoMode::MidSide];
+
+/// Lowest and highest cutoff, as in the app. The cutoff is also kept below Nyquist.
+pub const MIN_CUTOFF_HZ: f64 = CUTOFF.min as f64;
+pub const MAX_CUTOFF_HZ: f64 = CUTOFF.max as f64;
+pub const MIN_Q: f64 = RESONANCE.min as f64;
+pub const MAX_Q: f64 = RESONANCE.max as f64;
+pub const MIN_GAIN_DB: f64 = -24.0;
+pub const MAX_GAIN_DB: f64 = 24.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParamId {
+    Filter = 0,
+    Cutoff = 1,
+    Resonance = 2,
+    StereoMode = 3,
+    OutputGain = 4,
+}
+
+impl ParamId {
+    pub const ALL: [ParamId; 5] = [
+        ParamId::Filter,
+        ParamId::Cutoff,
+        ParamId::Resonance,
+        ParamId::StereoMode,
+        ParamId::OutputGain,
+    ];
+
+    pub fn from_id(id: u32) -> Option<ParamId> {
+        ParamId::ALL.into_iter().find(|param| *param as u32 == id)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ParamId::Filter => "Filter type",
+            ParamId::Cutoff => "Cutoff",
+            ParamId::Resonance => "Resonance",
+            ParamId::StereoMode => "Stereo mode",
+            ParamId::OutputGain => "Output gain",
+        }
+    }
+
+    /// Smallest and largest value.
+    pub fn range(self) -> (f64, f64) {
+        match self {
+            ParamId::Filter => (0.0, (FILTERS.len() - 1) as f64),
+            ParamId::Cutoff => (MIN_CUTOFF_HZ, MAX_CUTOFF_HZ),
+            ParamId::Resonance => (MIN_Q, MAX_Q),
+            ParamId::StereoMode => (0.0, (STEREO_MODES.len() - 1) as f64),
+            ParamId::OutputGain => (MIN_GAIN_DB, MAX_GAIN_DB),
+        }
+    }
+
+    pub fn default_value(self) -> f64 {
+        PluginParams::default().get(self)
+    }
+
+    /// Whether the parameter picks from a list rather than being continuous.
+    pub fn is_choice(self) -> bool {
+        matches!(self, ParamId::Filter | ParamId::StereoMode)
+    }
+}
+
+/// Current value of every parameter. This is also the saved state, as JSON.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PluginParams {
+    pub filter: SelectedFilter,
+    pub cutoff_hz: f32,
+    pub resonance_q: f32,
+    pub stereo_mode: StereoMode,
+    pub output_gain_db: f32,
+}
+
+impl Default for PluginParams {
+    fn default() -> Self {
+        Self {
+            filter: FILTERS[0],
+            cutoff_hz: CUTOFF.default,
+            resonance_q: RESONANCE.default,
+            stereo_mode: StereoMode::LeftRight,
+            output_gain_db: 0.0,
+        }
+    }
+}
+
+impl PluginParams {
+    pub fn get(&self, id: ParamId) -> f64 {
+        match id {
+            ParamId::Filter => FILTERS
+                .iter()
+                .position(|filter| *filter == self.filter)
+                .unwrap_or(0) as f64,
+            ParamId::Cutoff => self.cutoff_hz as f64,
+            ParamId::Resonance => self.resonance_q as f64,
+            ParamId::StereoMode => STEREO_MODES
+                .iter()
+                .position(|mode| *mode == self.stereo_mode)
+                .unwrap_or(0) as f64,
+            ParamId::OutputGain => self.output_gain_db as f64,
+        }
+    }
+
+    /// Sets a parameter, clamped to its range. Choices are rounded to the nearest entry.
+    pub fn set(&mut self, id: ParamId, value: f64) {
+        let (min, max) = id.range();
+        let value = if value.is_nan() {
+            id.default_value()
+        } else {
+            value.clamp(min, max)
+        };
+        match id {
+            ParamId::Filter => self.filter = FILTERS[value.round() as usize],
+            ParamId::Cutoff => self.cutoff_hz = value as f32,
+            ParamId::Resonance => self.resonance_q = value as f32,
+            ParamId::StereoMode => self.stereo_mode = STEREO_MODES[value.round() as usize],
+            ParamId::OutputGain => self.output_gain_db = value as f32,
+        }
+    }
+
+    /// The parameters of the filter on each channel, with the cutoff kept below Nyquist.
+    pub fn filter_params(&self, sample_rate: f32) -> FilterParams {
+        FilterParams {
+            filter: self.filter,
+            cutoff_hz: self.cutoff_hz.min(sample_rate * MAX_CUTOFF_RATIO),
+            resonance_q: self.resonance_q,
+            ..FilterParams::default()
+        }
+    }
+
+    pub fn output_gain(&self) -> f32 {
+        10f32.powf(self.output_gain_db / 20.0)
+    }
+}
+
+/// Parameter values the host's threads share without locking: the main thread reads them, sets
+/// them from `params_flush` and saves or loads them, while the audio thread applies automation.
+/// Values are stored as `f64` bits, one per `ParamId`.
+pub struct SharedParams([AtomicU64; ParamId::ALL.len()]);
+
+impl SharedParams {
+    pub fn new(params: &PluginParams) -> Self {
+        Self(ParamId::ALL.map(|id| AtomicU64::new(params.get(id).to_bits())))
+    }
+
+    pub fn get(&self, id: ParamId) -> f64 {
+        f64::from_bits(self.0[id as usize].load(Ordering::Relaxed))
+    }
+
+    /// Sets a parameter, clamped and rounded like `PluginParams::set`.
+    pub fn set(&self, id: ParamId, value: f64) {
+        let mut params = PluginParams::default();
+        params.set(id, value);
+        self.0[id as usize].store(params.get(id).to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn load(&self) -> PluginParams {
+        let mut params = PluginParams::default();
+        for id in ParamId::ALL {
+            params.set(id, self.get(id));
+        }
+        params
+    }
+
+    pub fn store(&self, params: &PluginParams) {
+        for id in ParamId::ALL {
+            self.0[id as usize].store(params.get(id).to_bits(), Ordering::Relaxed);
+        }
+    }
+}
+
+fn stereo_mode_name(mode: StereoMode) -> &'static str {
+    match mode {
+        StereoMode::LeftRight => "Left/Right",
+        StereoMode::MidSide => "Mid/Side",
+    }
+}
+
+/// How the host displays a value.
+pub fn value_to_text(id: ParamId, value: f64) -> String {
+    let mut params = PluginParams::default();
+    params.set(id, value);
+    match id {
+        ParamId::Filter => params.filter.name().to_string(),
+        ParamId::Cutoff => format!("{:.0} Hz", params.cutoff_hz),
+        ParamId::Resonance => format!("{:.2}", params.resonance_q),
+        ParamId::StereoMode => stereo_mode_name(params.stereo_mode).to_string(),
+        ParamId::OutputGain => format!("{:+.1} dB", params.output_gain_db),
+    }
+}
+
+/// Parses text typed into the host, accepting what `value_to_text` writes, with or without the
+/// unit.
+pub fn text_to_value(id: ParamId, text: &str) -> Option<f64> {
+    let text = text.trim();
+    match id {
+        ParamId::Filter => FILTERS
+            .iter()
+            .position(|filter| filter.name().eq_ignore_ascii_case(text))
+            .map(|index| index as f64),
+        ParamId::StereoMode => STEREO_MODES
+            .iter()
+            .position(|mode| stereo_mode_name(*mode).eq_ignore_ascii_case(text))
+            .map(|index| index as f64),
+        ParamId::Cutoff => parse_number(text, "Hz"),
+        ParamId::Resonance => parse_number(text, ""),
+        ParamId::OutputGain => parse_number(text, "dB"),
+    }
+}
+
+fn parse_number(text: &str, unit: &str) -> Option<f64> {
+    let number = text.strip_suffix(unit).unwrap_or(text).trim();
+    number.parse().ok().filter(|value: &f64| value.is_finite())
+}
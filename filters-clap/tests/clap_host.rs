@@ -0,0 +1,393 @@
+//! Drives the plugin through `clap_entry` the way a host does: creates it from the factory,
+//! reads its parameters, automates them from process events, and saves and restores its state.
+
+use std::ffi::{c_char, c_void, CStr};
+use std::ptr;
+
+use clap_sys::audio_buffer::clap_audio_buffer;
+use clap_sys::events::{
+    clap_event_header, clap_event_param_value, clap_input_events, CLAP_CORE_EVENT_SPACE_ID,
+    CLAP_EVENT_PARAM_VALUE,
+};
+use clap_sys::ext::params::{clap_param_info, clap_plugin_params, CLAP_EXT_PARAMS};
+use clap_sys::ext::state::{clap_plugin_state, CLAP_EXT_STATE};
+use clap_sys::factory::plugin_factory::{clap_plugin_factory, CLAP_PLUGIN_FACTORY_ID};
+use clap_sys::host::clap_host;
+use clap_sys::plugin::clap_plugin;
+use clap_sys::process::{clap_process, CLAP_PROCESS_CONTINUE};
+use clap_sys::stream::{clap_istream, clap_ostream};
+use clap_sys::version::CLAP_VERSION;
+
+use filters::{Filter, StateVariableFilter};
+use filters_clap::clap_entry;
+use filters_clap::params::ParamId;
+
+const SAMPLE_RATE: f64 = 48000.0;
+
+unsafe extern "C" fn host_get_extension(
+    _host: *const clap_host,
+    _id: *const c_char,
+) -> *const c_void {
+    ptr::null()
+}
+
+unsafe extern "C" fn host_request(_host: *const clap_host) {}
+
+static HOST: clap_host = clap_host {
+    clap_version: CLAP_VERSION,
+    host_data: ptr::null_mut(),
+    name: c"test host".as_ptr(),
+    vendor: c"".as_ptr(),
+    url: c"".as_ptr(),
+    version: c"1".as_ptr(),
+    get_extension: Some(host_get_extension),
+    request_restart: Some(host_request),
+    request_process: Some(host_request),
+    request_callback: Some(host_request),
+};
+
+/// A plugin instance, destroyed on drop.
+struct Plugin(*const clap_plugin);
+
+impl Plugin {
+    fn new() -> Self {
+        unsafe {
+            assert!((clap_entry.init.unwrap())(c"".as_ptr()));
+            let factory = (clap_entry.get_factory.unwrap())(CLAP_PLUGIN_FACTORY_ID.as_ptr())
+                as *const clap_plugin_factory;
+            assert!(!factory.is_null());
+            assert_eq!(((*factory).get_plugin_count.unwrap())(factory), 1);
+            let descriptor = ((*factory).get_plugin_descriptor.unwrap())(factory, 0);
+            let plugin = ((*factory).create_plugin.unwrap())(factory, &HOST, (*descriptor).id);
+            assert!(!plugin.is_null());
+            assert!(((*plugin).init.unwrap())(plugin));
+            Plugin(plugin)
+        }
+    }
+
+    fn extension<T>(&self, id: &CStr) -> &T {
+        unsafe {
+            let extension = ((*self.0).get_extension.unwrap())(self.0, id.as_ptr()) as *const T;
+            extension.as_ref().expect("extension is provided")
+        }
+    }
+
+    fn params(&self) -> &clap_plugin_params {
+        self.extension(CLAP_EXT_PARAMS)
+    }
+
+    fn value(&self, id: ParamId) -> f64 {
+        let mut value = 0.0;
+        unsafe {
+            assert!((self.params().get_value.unwrap())(
+                self.0, id as u32, &mut value
+            ));
+        }
+        value
+    }
+
+    fn activate(&self) {
+        unsafe {
+            assert!(((*self.0).activate.unwrap())(self.0, SAMPLE_RATE, 1, 4096));
+            assert!(((*self.0).start_processing.unwrap())(self.0));
+        }
+    }
+
+    /// Processes stereo `left` and `right` in place with parameter events.
+    fn process(&self, left: &mut [f32], right: &mut [f32], events: &[clap_event_param_value]) {
+        let in_events = clap_input_events {
+            ctx: &events as *const &[clap_event_param_value] as *mut c_void,
+            size: Some(events_size),
+            get: Some(events_get),
+        };
+        let mut channels = [left.as_mut_ptr(), right.as_mut_ptr()];
+        let buffer = clap_audio_buffer {
+            data32: channels.as_mut_ptr(),
+            data64: ptr::null_mut(),
+            channel_count: 2,
+            latency: 0,
+            constant_mask: 0,
+        };
+        let mut output = buffer;
+        let process = clap_process {
+            steady_time: -1,
+            frames_count: left.len() as u32,
+            transport: ptr::null(),
+            audio_inputs: &buffer,
+            audio_outputs: &mut output,
+            audio_inputs_count: 1,
+            audio_outputs_count: 1,
+            in_events: &in_events,
+            out_events: ptr::null(),
+        };
+        unsafe {
+            assert_eq!(
+                ((*self.0).process.unwrap())(self.0, &process),
+                CLAP_PROCESS_CONTINUE
+            );
+        }
+    }
+}
+
+impl Drop for Plugin {
+    fn drop(&mut self) {
+        unsafe { ((*self.0).destroy.unwrap())(self.0) }
+    }
+}
+
+unsafe extern "C" fn events_size(list: *const clap_input_events) -> u32 {
+    let events = *((*list).ctx as *const &[clap_event_param_value]);
+    events.len() as u32
+}
+
+unsafe extern "C" fn events_get(
+    list: *const clap_input_events,
+    index: u32,
+) -> *const clap_event_header {
+    let events = *((*list).ctx as *const &[clap_event_param_value]);
+    &events[index as usize].header
+}
+
+fn param_event(time: u32, id: ParamId, value: f64) -> clap_event_param_value {
+    clap_event_param_value {
+        header: clap_event_header {
+            size: std::mem::size_of::<clap_event_param_value>() as u32,
+            time,
+            space_id: CLAP_CORE_EVENT_SPACE_ID,
+            type_: CLAP_EVENT_PARAM_VALUE,
+            flags: 0,
+        },
+        param_id: id as u32,
+        cookie: ptr::null_mut(),
+        note_id: -1,
+        port_index: -1,
+        channel: -1,
+        key: -1,
+        value,
+    }
+}
+
+fn impulse(len: usize) -> Vec<f32> {
+    (0..len).map(|n| if n == 0 { 1.0 } else { 0.0 }).collect()
+}
+
+#[test]
+fn parameters_are_described() {
+    let plugin = Plugin::new();
+    let params = plugin.params();
+    unsafe {
+        let count = (params.count.unwrap())(plugin.0);
+        assert_eq!(count as usize, ParamId::ALL.len());
+        for index in 0..count {
+            let mut info: clap_param_info = std::mem::zeroed();
+            assert!((params.get_info.unwrap())(plugin.0, index, &mut info));
+            let id = ParamId::from_id(info.id).unwrap();
+            let name = CStr::from_ptr(info.name.as_ptr()).to_str().unwrap();
+            assert_eq!(name, id.name());
+            assert!(info.min_value <= info.default_value && info.default_value <= info.max_value);
+            assert_eq!(plugin.value(id), info.default_value);
+        }
+
+        let mut text = [0 as c_char; 64];
+        let cutoff = ParamId::Cutoff as u32;
+        assert!((params.value_to_text.unwrap())(
+            plugin.0,
+            cutoff,
+            440.0,
+            text.as_mut_ptr(),
+            64
+        ));
+        assert_eq!(CStr::from_ptr(text.as_ptr()).to_str().unwrap(), "440 Hz");
+        let mut value = 0.0;
+        assert!((params.text_to_value.unwrap())(
+            plugin.0,
+            cutoff,
+            text.as_ptr(),
+            &mut value
+        ));
+        assert_eq!(value, 440.0);
+    }
+}
+
+/// The default filter is a `StateVariableFilter` on each channel; automation applies from the
+/// sample the event is at.
+#[test]
+fn process_runs_the_filter_with_sample_accurate_automation() {
+    let plugin = Plugin::new();
+    plugin.activate();
+
+    let (mut left, mut right) = (impulse(512), impulse(512));
+    plugin.process(
+        &mut left,
+        &mut right,
+        &[param_event(256, ParamId::Cutoff, 4000.0)],
+    );
+
+    let mut reference = StateVariableFilter::<f32>::new(SAMPLE_RATE as f32);
+    reference.update_coefficients(1000.0, 0.707);
+    let mut expected = impulse(512);
+    reference.process_block(&mut expected[..256]);
+    reference.update_coefficients(4000.0, 0.707);
+    reference.process_block(&mut expected[256..]);
+    assert_eq!(left, expected);
+    assert_eq!(right, expected);
+    assert_eq!(plugin.value(ParamId::Cutoff), 4000.0);
+}
+
+#[test]
+fn output_gain_scales_the_output() {
+    let plugin = Plugin::new();
+    plugin.activate();
+    let events = [
+        param_event(0, ParamId::Filter, 3.0),
+        param_event(0, ParamId::OutputGain, -6.0),
+    ];
+    // The gain ramps over the first block, then holds.
+    let (mut left, mut right) = (vec![1.0; 64], vec![1.0; 64]);
+    plugin.process(&mut left, &mut right, &events);
+    let (mut left, mut right) = (vec![1.0; 64], vec![1.0; 64]);
+    plugin.process(&mut left, &mut right, &[]);
+    // The third filter is `FirLowPass`, x[n] + x[n-1], with a DC gain of 2.
+    let gain = 10f32.powf(-6.0 / 20.0);
+    for sample in left.iter().chain(right.iter()) {
+        assert!((sample - 2.0 * gain).abs() < 1e-6, "{}", sample);
+    }
+}
+
+struct Stream {
+    data: Vec<u8>,
+    position: usize,
+}
+
+unsafe extern "C" fn stream_write(
+    stream: *const clap_ostream,
+    buffer: *const c_void,
+    size: u64,
+) -> i64 {
+    let stream = &mut *((*stream).ctx as *mut Stream);
+    // A few bytes at a time, as hosts may do.
+    let size = (size as usize).min(7);
+    stream
+        .data
+        .extend_from_slice(std::slice::from_raw_parts(buffer as *const u8, size));
+    size as i64
+}
+
+unsafe extern "C" fn stream_read(
+    stream: *const clap_istream,
+    buffer: *mut c_void,
+    size: u64,
+) -> i64 {
+    let stream = &mut *((*stream).ctx as *mut Stream);
+    let size = (size as usize)
+        .min(stream.data.len() - stream.position)
+        .min(7);
+    ptr::copy_nonoverlapping(
+        stream.data.as_ptr().add(stream.position),
+        buffer as *mut u8,
+        size,
+    );
+    stream.position += size;
+    size as i64
+}
+
+#[test]
+fn state_is_saved_and_restored() {
+    let source = Plugin::new();
+    source.activate();
+    let (mut left, mut right) = (vec![0.0; 16], vec![0.0; 16]);
+    source.process(
+        &mut left,
+        &mut right,
+        &[
+            param_event(0, ParamId::Filter, 2.0),
+            param_event(0, ParamId::Cutoff, 250.0),
+            param_event(0, ParamId::Resonance, 4.0),
+            param_event(0, ParamId::StereoMode, 1.0),
+            param_event(0, ParamId::OutputGain, 3.5),
+        ],
+    );
+
+    let mut stream = Stream {
+        data: Vec::new(),
+        position: 0,
+    };
+    let state: &clap_plugin_state = source.extension(CLAP_EXT_STATE);
+    let ostream = clap_ostream {
+        ctx: &mut stream as *mut Stream as *mut c_void,
+        write: Some(stream_write),
+    };
+    assert!(unsafe { (state.save.unwrap())(source.0, &ostream) });
+
+    let restored = Plugin::new();
+    let state: &clap_plugin_state = restored.extension(CLAP_EXT_STATE);
+    let istream = clap_istream {
+        ctx: &mut stream as *mut Stream as *mut c_void,
+        read: Some(stream_read),
+    };
+    assert!(unsafe { (state.load.unwrap())(restored.0, &istream) });
+    for id in ParamId::ALL {
+        assert_eq!(restored.value(id), source.value(id), "{:?}", id);
+    }
+
+    let garbage = Plugin::new();
+    let mut stream = Stream {
+        data: b"{\"version\": 1, \"params\": ".to_vec(),
+        position: 0,
+    };
+    let istream = clap_istream {
+        ctx: &mut stream as *mut Stream as *mut c_void,
+        read: Some(stream_read),
+    };
+    let state: &clap_plugin_state = garbage.extension(CLAP_EXT_STATE);
+    assert!(!unsafe { (state.load.unwrap())(garbage.0, &istream) });
+    assert_eq!(garbage.value(ParamId::Cutoff), 1000.0);
+}
+
+/// The host changes parameters and saves the state from its main thread while the audio thread
+/// processes. The changes reach the next block, and neither thread blocks the other.
+#[test]
+fn main_thread_changes_apply_while_processing() {
+    let plugin = Plugin::new();
+    plugin.activate();
+
+    let flush = plugin.params().flush.unwrap();
+    let events = [param_event(0, ParamId::Filter, 3.0)];
+    let in_events = clap_input_events {
+        ctx: &events.as_slice() as *const &[clap_event_param_value] as *mut c_void,
+        size: Some(events_size),
+        get: Some(events_get),
+    };
+    unsafe { flush(plugin.0, &in_events, ptr::null()) };
+    // The third filter is `FirLowPass`, with a DC gain of 2.
+    let (mut left, mut right) = (vec![1.0; 64], vec![1.0; 64]);
+    plugin.process(&mut left, &mut right, &[]);
+    assert_eq!(left[63], 2.0);
+
+    let raw = plugin.0 as usize;
+    let main_thread = std::thread::spawn(move || {
+        let plugin = raw as *const clap_plugin;
+        let state: &clap_plugin_state = unsafe {
+            &*(((*plugin).get_extension.unwrap())(plugin, CLAP_EXT_STATE.as_ptr())
+                as *const clap_plugin_state)
+        };
+        for _ in 0..1000 {
+            let mut stream = Stream {
+                data: Vec::new(),
+                position: 0,
+            };
+            let ostream = clap_ostream {
+                ctx: &mut stream as *mut Stream as *mut c_void,
+                write: Some(stream_write),
+            };
+            assert!(unsafe { (state.save.unwrap())(plugin, &ostream) });
+        }
+    });
+    for _ in 0..1000 {
+        let (mut left, mut right) = (vec![1.0; 64], vec![1.0; 64]);
+        plugin.process(&mut left, &mut right, &[]);
+        assert_eq!(left[63], 2.0);
+    }
+    main_thread.join().unwrap();
+    assert_eq!(plugin.value(ParamId::Filter), 3.0);
+}
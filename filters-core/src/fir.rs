@@ -0,0 +1,51 @@
+//! FIR filters with arbitrary taps.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::{Coefficients, Filter, Sample};
+
+/// A direct-form FIR filter with arbitrary taps. Like `SosFilter`, the design is fixed, so cutoff
+/// and resonance changes are ignored.
+pub struct FirFilter<T = f32> {
+    taps: Vec<T>,
+    // Circular buffer of past inputs, `history[position]` being the newest.
+    history: Vec<T>,
+    position: usize,
+}
+
+impl<T: Sample> FirFilter<T> {
+    pub fn new(taps: Vec<T>) -> Self {
+        Self {
+            history: vec![T::zero(); taps.len().max(1)],
+            taps,
+            position: 0,
+        }
+    }
+}
+
+impl<T: Sample> Filter<T> for FirFilter<T> {
+    fn reset(&mut self) {
+        self.history.fill(T::zero());
+        self.position = 0;
+    }
+
+    fn update_coefficients(&mut self, _cutoff_freq: f32, _resonance: f32) {}
+
+    fn coefficients(&self) -> Coefficients<T> {
+        Coefficients::Fir(self.taps.clone())
+    }
+
+    fn render(&mut self, input_sample: T) -> T {
+        let len = self.history.len();
+        self.position = (self.position + 1) % len;
+        self.history[self.position] = input_sample;
+
+        self.taps
+            .iter()
+            .enumerate()
+            .fold(T::zero(), |sum, (k, tap)| {
+                sum + *tap * self.history[(self.position + len - k) % len]
+            })
+    }
+}
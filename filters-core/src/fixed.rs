@@ -0,0 +1,346 @@
+//! Fixed-point filters for targets without an FPU.
+//!
+//! Samples are Q15 or Q31: signed 16 or 32 bit integers scaled so that full scale is ±1.
+//! Coefficients are stored with two integer bits (a range of [-2, 2), enough for any stable
+//! biquad) and a configurable word length, so the effect of shorter coefficient words can be
+//! heard and measured. Products are summed in a 64-bit accumulator that saturates instead of
+//! wrapping, and results saturate to the sample word, like the MAC units of fixed-point DSPs. The
+//! state variable filter's states need headroom, so they get guard bits and a wider accumulator.
+//!
+//! The filters implement `Filter` for the float types so they can run in the audio path and be
+//! analyzed like any other filter; conversion to and from fixed point happens at their input and
+//! output.
+
+use alloc::boxed::Box;
+use num_traits::Float;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    BiQuadCoefficients, BiQuadFilter, Coefficients, Filter, NoiseGen, Sample, StateVariableFilter,
+};
+
+/// Word length of the samples.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum FixedFormat {
+    #[default]
+    Q15,
+    Q31,
+}
+
+impl FixedFormat {
+    /// Fractional bits of a sample.
+    pub fn frac_bits(self) -> u32 {
+        match self {
+            FixedFormat::Q15 => 15,
+            FixedFormat::Q31 => 31,
+        }
+    }
+
+    fn min(self) -> i64 {
+        -(1 << self.frac_bits())
+    }
+
+    fn max(self) -> i64 {
+        (1 << self.frac_bits()) - 1
+    }
+
+    fn saturate(self, value: i64) -> i64 {
+        value.clamp(self.min(), self.max())
+    }
+
+    fn quantize<T: Sample>(self, value: T) -> i64 {
+        let scaled = Float::round(value.as_f64() * (1u64 << self.frac_bits()) as f64);
+        // Float to int casts saturate, and NaN becomes 0.
+        self.saturate(scaled as i64)
+    }
+
+    fn dequantize<T: Sample>(self, value: i64) -> T {
+        T::cast(value as f64 / (1u64 << self.frac_bits()) as f64)
+    }
+}
+
+/// Settings shared by the fixed-point filters.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
+pub struct FixedPointParams {
+    pub format: FixedFormat,
+    /// Word length of the coefficients, including the sign and integer bits.
+    pub coefficient_bits: u32,
+}
+
+impl Default for FixedPointParams {
+    fn default() -> Self {
+        Self {
+            format: FixedFormat::Q15,
+            coefficient_bits: 16,
+        }
+    }
+}
+
+/// Smallest and largest supported coefficient word lengths.
+pub const MIN_COEFFICIENT_BITS: u32 = 8;
+pub const MAX_COEFFICIENT_BITS: u32 = 32;
+
+// Sign bit plus one integer bit.
+const COEFFICIENT_INTEGER_BITS: u32 = 2;
+
+/// A coefficient quantized to `bits` with `COEFFICIENT_INTEGER_BITS` integer bits.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct FixedCoefficient {
+    value: i64,
+}
+
+impl FixedCoefficient {
+    fn quantize(value: f64, bits: u32) -> Self {
+        let frac_bits = frac_bits(bits);
+        let bits = bits.clamp(MIN_COEFFICIENT_BITS, MAX_COEFFICIENT_BITS);
+        let limit = 1i64 << (bits - 1);
+        let scaled = Float::round(value * (1u64 << frac_bits) as f64) as i64;
+        Self {
+            value: scaled.clamp(-limit, limit - 1),
+        }
+    }
+
+    fn to_float<T: Sample>(self, bits: u32) -> T {
+        T::cast(self.value as f64 / (1u64 << frac_bits(bits)) as f64)
+    }
+}
+
+fn frac_bits(coefficient_bits: u32) -> u32 {
+    coefficient_bits.clamp(MIN_COEFFICIENT_BITS, MAX_COEFFICIENT_BITS) - COEFFICIENT_INTEGER_BITS
+}
+
+/// Multiply-accumulate with a saturating accumulator.
+fn mac(accumulator: i64, sample: i64, coefficient: FixedCoefficient) -> i64 {
+    accumulator.saturating_add(sample.saturating_mul(coefficient.value))
+}
+
+/// Drops the coefficient's fractional bits from an accumulator, rounding to nearest.
+fn scale_down(accumulator: i64, coefficient_bits: u32) -> i64 {
+    let shift = frac_bits(coefficient_bits);
+    accumulator.saturating_add(1 << (shift - 1)) >> shift
+}
+
+/// A direct form I biquad in fixed point, with the same design as `BiQuadFilter`. DF I is the
+/// usual choice in fixed point since its only sum is in the accumulator, so intermediate values
+/// can't overflow.
+pub struct FixedBiQuadFilter {
+    design: BiQuadFilter<f64>,
+    params: FixedPointParams,
+    b0: FixedCoefficient,
+    b1: FixedCoefficient,
+    b2: FixedCoefficient,
+    a1: FixedCoefficient,
+    a2: FixedCoefficient,
+    x1: i64,
+    x2: i64,
+    y1: i64,
+    y2: i64,
+}
+
+impl FixedBiQuadFilter {
+    pub fn new(sample_rate: f32, params: FixedPointParams) -> Self {
+        let mut filter = Self {
+            design: BiQuadFilter::new(sample_rate),
+            params,
+            b0: FixedCoefficient { value: 0 },
+            b1: FixedCoefficient { value: 0 },
+            b2: FixedCoefficient { value: 0 },
+            a1: FixedCoefficient { value: 0 },
+            a2: FixedCoefficient { value: 0 },
+            x1: 0,
+            x2: 0,
+            y1: 0,
+            y2: 0,
+        };
+        filter.quantize();
+        filter
+    }
+
+    fn quantize(&mut self) {
+        let Coefficients::BiQuad(c) = self.design.coefficients() else {
+            unreachable!("a biquad has biquad coefficients");
+        };
+        let bits = self.params.coefficient_bits;
+        self.b0 = FixedCoefficient::quantize(c.b0, bits);
+        self.b1 = FixedCoefficient::quantize(c.b1, bits);
+        self.b2 = FixedCoefficient::quantize(c.b2, bits);
+        self.a1 = FixedCoefficient::quantize(c.a1, bits);
+        self.a2 = FixedCoefficient::quantize(c.a2, bits);
+    }
+}
+
+impl<T: Sample> Filter<T> for FixedBiQuadFilter {
+    fn reset(&mut self) {
+        self.x1 = 0;
+        self.x2 = 0;
+        self.y1 = 0;
+        self.y2 = 0;
+    }
+
+    fn update_coefficients(&mut self, cutoff_freq: f32, resonance: f32) {
+        self.design.update_coefficients(cutoff_freq, resonance);
+        self.quantize();
+    }
+
+    fn coefficients(&self) -> Coefficients<T> {
+        let bits = self.params.coefficient_bits;
+        Coefficients::BiQuad(BiQuadCoefficients {
+            b0: self.b0.to_float(bits),
+            b1: self.b1.to_float(bits),
+            b2: self.b2.to_float(bits),
+            a1: self.a1.to_float(bits),
+            a2: self.a2.to_float(bits),
+        })
+    }
+
+    fn render(&mut self, input_sample: T) -> T {
+        let format = self.params.format;
+        let x = format.quantize(input_sample);
+
+        let mut accumulator = mac(0, x, self.b0);
+        accumulator = mac(accumulator, self.x1, self.b1);
+        accumulator = mac(accumulator, self.x2, self.b2);
+        accumulator = mac(accumulator, -self.y1, self.a1);
+        accumulator = mac(accumulator, -self.y2, self.a2);
+        let y = format.saturate(scale_down(accumulator, self.params.coefficient_bits));
+
+        self.x2 = self.x1;
+        self.x1 = x;
+        self.y2 = self.y1;
+        self.y1 = y;
+
+        format.dequantize(y)
+    }
+}
+
+// Integer bits the state variable filter's states have above the sample word. Its band-pass state
+// reaches about Q times the input, so 6 bits cover the highest Q without clipping.
+const STATE_GUARD_BITS: u32 = 6;
+
+/// Multiply-accumulate for states with guard bits, whose products don't fit in 64 bits.
+fn mac_wide(accumulator: i128, state: i64, coefficient: FixedCoefficient) -> i128 {
+    accumulator + state as i128 * coefficient.value as i128
+}
+
+/// `scale_down` for `mac_wide` accumulators.
+fn scale_down_wide(accumulator: i128, coefficient_bits: u32) -> i64 {
+    let shift = frac_bits(coefficient_bits);
+    let scaled = (accumulator + (1 << (shift - 1))) >> shift;
+    scaled.clamp(i64::MIN as i128, i64::MAX as i128) as i64
+}
+
+/// The low-pass output of `StateVariableFilter` in fixed point, with the same design. Its
+/// coefficients are all in [0, 1]. The states have `STATE_GUARD_BITS` of headroom above the
+/// sample word and a wider accumulator, so only the output saturates to the sample word.
+pub struct FixedStateVariableFilter {
+    design: StateVariableFilter<f64>,
+    params: FixedPointParams,
+    a1: FixedCoefficient,
+    a2: FixedCoefficient,
+    a3: FixedCoefficient,
+    ic1eq: i64,
+    ic2eq: i64,
+}
+
+impl FixedStateVariableFilter {
+    pub fn new(sample_rate: f32, params: FixedPointParams) -> Self {
+        let mut filter = Self {
+            design: StateVariableFilter::new(sample_rate),
+            params,
+            a1: FixedCoefficient { value: 0 },
+            a2: FixedCoefficient { value: 0 },
+            a3: FixedCoefficient { value: 0 },
+            ic1eq: 0,
+            ic2eq: 0,
+        };
+        filter.quantize();
+        filter
+    }
+
+    fn quantize(&mut self) {
+        let Coefficients::StateVariable { a1, a2, a3, .. } = self.design.coefficients() else {
+            unreachable!("a state variable filter has state variable coefficients");
+        };
+        let bits = self.params.coefficient_bits;
+        self.a1 = FixedCoefficient::quantize(a1, bits);
+        self.a2 = FixedCoefficient::quantize(a2, bits);
+        self.a3 = FixedCoefficient::quantize(a3, bits);
+    }
+
+    fn saturate_state(&self, value: i64) -> i64 {
+        let limit = 1i64 << (self.params.format.frac_bits() + STATE_GUARD_BITS);
+        value.clamp(-limit, limit - 1)
+    }
+}
+
+impl<T: Sample> Filter<T> for FixedStateVariableFilter {
+    fn reset(&mut self) {
+        self.ic1eq = 0;
+        self.ic2eq = 0;
+    }
+
+    fn update_coefficients(&mut self, cutoff: f32, q: f32) {
+        // Designed in double precision, then quantized.
+        self.design.update_coefficients(cutoff, q);
+        self.quantize();
+    }
+
+    fn coefficients(&self) -> Coefficients<T> {
+        let Coefficients::StateVariable { g, k, .. } = self.design.coefficients() else {
+            unreachable!("a state variable filter has state variable coefficients");
+        };
+        let bits = self.params.coefficient_bits;
+        Coefficients::StateVariable {
+            g: T::cast(g),
+            k: T::cast(k),
+            a1: self.a1.to_float(bits),
+            a2: self.a2.to_float(bits),
+            a3: self.a3.to_float(bits),
+        }
+    }
+
+    fn render(&mut self, sample: T) -> T {
+        let format = self.params.format;
+        let bits = self.params.coefficient_bits;
+        let x = format.quantize(sample);
+
+        let v3 = x - self.ic2eq;
+        let v1 = scale_down_wide(
+            mac_wide(mac_wide(0, self.ic1eq, self.a1), v3, self.a2),
+            bits,
+        );
+        let v2 = scale_down_wide(
+            mac_wide(mac_wide(0, self.ic1eq, self.a2), v3, self.a3),
+            bits,
+        );
+        let v1 = self.saturate_state(v1);
+        let v2 = self.saturate_state(self.ic2eq + v2);
+        self.ic1eq = self.saturate_state(2 * v1 - self.ic1eq);
+        self.ic2eq = self.saturate_state(2 * v2 - self.ic2eq);
+
+        format.dequantize(format.saturate(v2))
+    }
+}
+
+/// Level of the error a fixed-point filter adds compared to its float reference, in dB relative to
+/// full scale, measured over a second of white noise at -12 dBFS.
+pub fn noise_floor_db(
+    mut fixed: Box<dyn Filter<f64>>,
+    mut reference: Box<dyn Filter<f64>>,
+    sample_rate: usize,
+) -> f32 {
+    let mut noise = NoiseGen::new();
+    let error_power = (0..sample_rate)
+        .map(|_| {
+            let x = noise.next_value() as f64 * 0.25;
+            let error = fixed.render(x) - reference.render(x);
+            error * error
+        })
+        .sum::<f64>()
+        / sample_rate as f64;
+
+    (10.0 * Float::log10(error_power + 1e-30)) as f32
+}
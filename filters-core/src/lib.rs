@@ -0,0 +1,522 @@
+//! The DSP filters, without the app around them.
+//!
+//! The crate is `no_std` and needs only `alloc`, for the variable-length coefficient lists and
+//! the boxed filters the app builds. Without the default `std` feature, float math comes from
+//! `libm`. The `serde` feature makes the settings types serializable.
+
+#![no_std]
+#![warn(clippy::all, rust_2018_idioms)]
+
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+use alloc::vec;
+use alloc::vec::Vec;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+pub mod fir;
+pub mod fixed;
+pub mod noise;
+pub mod sample;
+pub mod simd;
+pub mod sos;
+
+pub use fir::FirFilter;
+pub use fixed::{FixedBiQuadFilter, FixedFormat, FixedPointParams, FixedStateVariableFilter};
+pub use noise::NoiseGen;
+pub use sample::{PerSample, Sample};
+pub use simd::{BiQuadLanes, LaneBank, LaneFilter, Lanes, StateVariableLanes};
+pub use sos::SosFilter;
+
+/// The highest cutoff as a fraction of the sample rate. Designs past Nyquist are unstable, so
+/// callers taking a cutoff from users clamp it to this.
+pub const MAX_CUTOFF_RATIO: f32 = 0.49;
+
+/// Which output of a multi-output filter is used.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FilterType {
+    LowPass,
+    HighPass,
+    BandPass,
+}
+
+/// A second-order section normalized so that a0 = 1.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BiQuadCoefficients<T = f32> {
+    pub b0: T,
+    pub b1: T,
+    pub b2: T,
+    pub a1: T,
+    pub a2: T,
+}
+
+impl<T: Sample> BiQuadCoefficients<T> {
+    /// Converts to another precision.
+    pub fn cast<U: Sample>(&self) -> BiQuadCoefficients<U> {
+        BiQuadCoefficients {
+            b0: U::cast(self.b0.as_f64()),
+            b1: U::cast(self.b1.as_f64()),
+            b2: U::cast(self.b2.as_f64()),
+            a1: U::cast(self.a1.as_f64()),
+            a2: U::cast(self.a2.as_f64()),
+        }
+    }
+}
+
+/// The coefficients a filter is currently running with, in the form its structure uses them.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Coefficients<T = f32> {
+    BiQuad(BiQuadCoefficients<T>),
+    StateVariable {
+        g: T,
+        k: T,
+        a1: T,
+        a2: T,
+        a3: T,
+    },
+    StateVariableTPT {
+        g: T,
+        h: T,
+        r2: T,
+    },
+    /// Taps of an FIR filter, `taps[n]` weighting x[n - k].
+    Fir(Vec<T>),
+    /// Second-order sections applied one after the other.
+    Cascade(Vec<BiQuadCoefficients<T>>),
+}
+
+/// A filter processing samples of type `T`. Parameters are always given in `f32` since they come
+/// from the UI; coefficients are computed in `T`.
+pub trait Filter<T: Sample = f32>: Send {
+    /// Clears the filter's memory (delay lines, integrator states) as if it had only ever
+    /// processed silence. Coefficients and settings are kept, so rendering continues with the same
+    /// response.
+    fn reset(&mut self);
+    fn render(&mut self, input_sample: T) -> T;
+    /// Filters a block in place. Through a `dyn Filter` this costs one virtual call per block
+    /// instead of one per sample, and lets `render` be inlined into the loop.
+    fn process_block(&mut self, block: &mut [T]) {
+        for sample in block.iter_mut() {
+            *sample = self.render(*sample);
+        }
+    }
+    fn update_coefficients(&mut self, cutoff_freq: f32, reso: f32);
+    fn coefficients(&self) -> Coefficients<T>;
+}
+
+/// Structure a `BiQuadFilter` is realized with. They share a transfer function but differ in
+/// rounding noise, coefficient sensitivity and how they behave when coefficients change while
+/// running.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum BiQuadTopology {
+    /// Separate input and output delay lines. Four states, no internal overflow.
+    #[default]
+    DirectFormI,
+    /// Poles first, then zeros, sharing one delay line. Internal gain can be high.
+    DirectFormII,
+    /// The transposed form, with two states. Usually the best choice in floating point.
+    TransposedDirectFormII,
+    /// Normalized Gray-Markel lattice with a ladder for the zeros. Insensitive to coefficient
+    /// quantization and well behaved under modulation, but only defined for stable poles.
+    Lattice,
+}
+
+/// Reflection and ladder coefficients of a normalized lattice, derived from a biquad's direct
+/// form coefficients.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct LatticeCoefficients<T> {
+    k1: T,
+    k2: T,
+    c1: T,
+    c2: T,
+    v0: T,
+    v1: T,
+    v2: T,
+}
+
+impl<T: Sample> LatticeCoefficients<T> {
+    fn new(c: &BiQuadCoefficients<T>) -> Self {
+        let one = T::one();
+        let k2 = c.a2;
+        let k1 = c.a1 / (one + c.a2);
+        let c1 = (one - k1 * k1).sqrt();
+        let c2 = (one - k2 * k2).sqrt();
+
+        // Ladder taps of the unnormalized lattice, rescaled for the gain of the normalized
+        // sections in front of each tap.
+        let v2 = c.b2;
+        let v1 = c.b1 - c.b2 * c.a1;
+        let v0 = c.b0 - v1 * k1 - c.b2 * c.a2;
+        Self {
+            k1,
+            k2,
+            c1,
+            c2,
+            v0: v0 / (c1 * c2),
+            v1: v1 / c2,
+            v2,
+        }
+    }
+}
+
+pub struct BiQuadFilter<T = f32> {
+    sample_rate: T,
+    topology: BiQuadTopology,
+    b0: T,
+    b1: T,
+    b2: T,
+    a0: T,
+    a1: T,
+    a2: T,
+    // Divided by a0 once per coefficient update rather than on every sample.
+    normalized: BiQuadCoefficients<T>,
+    lattice: LatticeCoefficients<T>,
+    // Direct form I history
+    x1: T,
+    x2: T,
+    y1: T,
+    y2: T,
+    // State of the two-state topologies
+    s1: T,
+    s2: T,
+}
+
+impl<T: Sample> BiQuadFilter<T> {
+    pub fn new(sample_rate: f32) -> Self {
+        Self::with_topology(sample_rate, BiQuadTopology::default())
+    }
+
+    pub fn with_topology(sample_rate: f32, topology: BiQuadTopology) -> Self {
+        let (zero, one) = (T::zero(), T::one());
+        let normalized = BiQuadCoefficients {
+            b0: one,
+            b1: zero,
+            b2: zero,
+            a1: zero,
+            a2: zero,
+        };
+        Self {
+            sample_rate: T::cast(sample_rate as f64),
+            topology,
+            b0: one,
+            a0: one,
+            a1: zero,
+            a2: zero,
+            b1: zero,
+            b2: zero,
+            normalized,
+            lattice: LatticeCoefficients::new(&normalized),
+            x1: zero,
+            x2: zero,
+            y1: zero,
+            y2: zero,
+            s1: zero,
+            s2: zero,
+        }
+    }
+
+    pub fn topology(&self) -> BiQuadTopology {
+        self.topology
+    }
+
+    /// Switches structure. The state is cleared since it means something else in the new one.
+    pub fn set_topology(&mut self, topology: BiQuadTopology) {
+        self.topology = topology;
+        self.reset();
+    }
+
+    fn normalize(&mut self) {
+        self.normalized = BiQuadCoefficients {
+            b0: self.b0 / self.a0,
+            b1: self.b1 / self.a0,
+            b2: self.b2 / self.a0,
+            a1: self.a1 / self.a0,
+            a2: self.a2 / self.a0,
+        };
+        self.lattice = LatticeCoefficients::new(&self.normalized);
+    }
+}
+
+impl<T: Sample> Filter<T> for BiQuadFilter<T> {
+    fn reset(&mut self) {
+        self.x1 = T::zero();
+        self.x2 = T::zero();
+        self.y1 = T::zero();
+        self.y2 = T::zero();
+        self.s1 = T::zero();
+        self.s2 = T::zero();
+    }
+
+    fn update_coefficients(&mut self, cutoff_frequency: f32, resonance: f32) {
+        let (one, two) = (T::one(), T::cast(2.0));
+        let w0 = two * T::PI() * T::cast(cutoff_frequency as f64) / self.sample_rate;
+        let alpha = w0.sin() / (two * T::cast(resonance as f64));
+
+        // Low Pass
+        self.b0 = (one - w0.cos()) / two;
+        self.b1 = one - w0.cos();
+        self.b2 = (one - w0.cos()) / two;
+        self.a0 = one + alpha;
+        self.a1 = -two * w0.cos();
+        self.a2 = one - alpha;
+        self.normalize();
+    }
+
+    fn coefficients(&self) -> Coefficients<T> {
+        Coefficients::BiQuad(self.normalized)
+    }
+
+    fn render(&mut self, input_sample: T) -> T {
+        let c = &self.normalized;
+
+        match self.topology {
+            BiQuadTopology::DirectFormI => {
+                let yn = c.b0 * input_sample + c.b1 * self.x1 + c.b2 * self.x2
+                    - c.a1 * self.y1
+                    - c.a2 * self.y2;
+
+                self.x2 = self.x1;
+                self.x1 = input_sample;
+
+                self.y2 = self.y1;
+                self.y1 = yn.flush_denormal();
+
+                yn
+            }
+            BiQuadTopology::DirectFormII => {
+                let w = input_sample - c.a1 * self.s1 - c.a2 * self.s2;
+                let yn = c.b0 * w + c.b1 * self.s1 + c.b2 * self.s2;
+                self.s2 = self.s1;
+                self.s1 = w.flush_denormal();
+                yn
+            }
+            BiQuadTopology::TransposedDirectFormII => {
+                let yn = c.b0 * input_sample + self.s1;
+                self.s1 = (c.b1 * input_sample - c.a1 * yn + self.s2).flush_denormal();
+                self.s2 = (c.b2 * input_sample - c.a2 * yn).flush_denormal();
+                yn
+            }
+            BiQuadTopology::Lattice => {
+                // s1 and s2 hold the backward signals g0 and g1 from the previous sample.
+                let l = &self.lattice;
+                let f1 = l.c2 * input_sample - l.k2 * self.s2;
+                let f0 = l.c1 * f1 - l.k1 * self.s1;
+                let g1 = l.k1 * f1 + l.c1 * self.s1;
+                let g2 = l.k2 * input_sample + l.c2 * self.s2;
+                self.s2 = g1.flush_denormal();
+                self.s1 = f0.flush_denormal();
+                l.v0 * f0 + l.v1 * g1 + l.v2 * g2
+            }
+        }
+    }
+}
+
+pub struct FirLowPassFilter<T = f32> {
+    s1: T,
+    s2: T,
+}
+
+impl<T: Sample> FirLowPassFilter<T> {
+    pub fn new(_sample_rate: f32) -> Self {
+        Self {
+            s1: T::zero(),
+            s2: T::zero(),
+        }
+    }
+}
+
+impl<T: Sample> Filter<T> for FirLowPassFilter<T> {
+    fn reset(&mut self) {
+        self.s1 = T::zero();
+        self.s2 = T::zero();
+    }
+
+    fn update_coefficients(&mut self, _cutoff_freq: f32, _resonance: f32) {}
+
+    fn coefficients(&self) -> Coefficients<T> {
+        Coefficients::Fir(vec![T::one(), T::one()])
+    }
+
+    // Difference Equation: y[n] = a0.x[n] + a1.x[n-1]
+    fn render(&mut self, input_sample: T) -> T {
+        let y = self.s1 + self.s2 + input_sample;
+
+        // self.s2 = self.s1; // Playing with additional state delays
+        self.s1 = input_sample;
+
+        y
+    }
+}
+
+pub struct FirHighPassFilter<T = f32> {
+    s1: T,
+    s2: T,
+}
+
+impl<T: Sample> FirHighPassFilter<T> {
+    pub fn new(_sample_rate: f32) -> Self {
+        Self {
+            s1: T::zero(),
+            s2: T::zero(),
+        }
+    }
+}
+
+impl<T: Sample> Filter<T> for FirHighPassFilter<T> {
+    fn reset(&mut self) {
+        self.s1 = T::zero();
+        self.s2 = T::zero();
+    }
+
+    fn update_coefficients(&mut self, _cutoff_freq: f32, _resonance: f32) {}
+
+    fn coefficients(&self) -> Coefficients<T> {
+        Coefficients::Fir(vec![T::one(), -T::one()])
+    }
+
+    // Difference Equation: y[n] = a0.x[n] - a1.x[n-1]
+    fn render(&mut self, input_sample: T) -> T {
+        let y = input_sample - self.s1 - self.s2;
+
+        // self.s2 = self.s1; // Playing with additional state delays
+        self.s1 = input_sample;
+
+        y
+    }
+}
+
+// JUCE implementation of juce_StateVariableTPTFilter
+pub struct StateVariableTPTFilter<T = f32> {
+    pub sample_rate: T,
+    filter_type: FilterType,
+    // Coefficients
+    g: T,
+    h: T,
+    r2: T,
+    // Intermediate State ?
+    s1: T,
+    s2: T,
+}
+
+impl<T: Sample> StateVariableTPTFilter<T> {
+    pub fn new(sample_rate: f32) -> Self {
+        Self::with_filter_type(sample_rate, FilterType::BandPass)
+    }
+
+    /// A filter rendering `filter_type` instead of the band-pass output. The app and its
+    /// coefficient analysis always use band-pass.
+    pub fn with_filter_type(sample_rate: f32, filter_type: FilterType) -> Self {
+        Self {
+            sample_rate: T::cast(sample_rate as f64),
+            filter_type,
+            g: T::zero(),
+            h: T::zero(),
+            r2: T::zero(),
+            s1: T::zero(),
+            s2: T::zero(),
+        }
+    }
+}
+
+impl<T: Sample> Filter<T> for StateVariableTPTFilter<T> {
+    fn reset(&mut self) {
+        self.s1 = T::zero();
+        self.s2 = T::zero();
+    }
+
+    fn update_coefficients(&mut self, cutoff_freq: f32, resonance: f32) {
+        let one = T::one();
+        self.g = (T::PI() * T::cast(cutoff_freq as f64) / self.sample_rate).tan();
+        self.r2 = one / T::cast(resonance as f64);
+        self.h = one / (one + self.r2 * self.g + self.g * self.g);
+    }
+
+    fn coefficients(&self) -> Coefficients<T> {
+        Coefficients::StateVariableTPT {
+            g: self.g,
+            h: self.h,
+            r2: self.r2,
+        }
+    }
+
+    fn render(&mut self, input_sample: T) -> T {
+        let y_high_pass = self.h * (input_sample - self.s1 * (self.g + self.r2) - self.s2);
+        let y_band_pass = y_high_pass * self.g + self.s1;
+        self.s1 = (y_high_pass * self.g + y_band_pass).flush_denormal();
+
+        let y_low_pass = y_band_pass * self.g + self.s2;
+        self.s2 = (y_band_pass * self.g + y_low_pass).flush_denormal();
+
+        match self.filter_type {
+            FilterType::LowPass => y_low_pass,
+            FilterType::BandPass => y_band_pass,
+            FilterType::HighPass => y_high_pass,
+        }
+    }
+}
+
+pub struct StateVariableFilter<T = f32> {
+    pub sample_rate: T,
+    g: T, // cutoff freq
+    k: T, // resonance
+    a1: T,
+    a2: T,
+    a3: T,
+    ic1eq: T,
+    ic2eq: T,
+}
+
+impl<T: Sample> StateVariableFilter<T> {
+    pub fn new(sample_rate: f32) -> Self {
+        Self {
+            sample_rate: T::cast(sample_rate as f64),
+            g: T::zero(),
+            k: T::zero(),
+            a1: T::zero(),
+            a2: T::zero(),
+            a3: T::zero(),
+            ic1eq: T::zero(),
+            ic2eq: T::zero(),
+        }
+    }
+}
+
+impl<T: Sample> Filter<T> for StateVariableFilter<T> {
+    fn reset(&mut self) {
+        self.ic1eq = T::zero();
+        self.ic2eq = T::zero();
+    }
+
+    fn update_coefficients(&mut self, cutoff: f32, q: f32) {
+        let one = T::one();
+        self.g = (T::PI() * T::cast(cutoff as f64) / self.sample_rate).tan();
+        self.k = one / T::cast(q as f64);
+        self.a1 = one / (one + self.g * (self.g + self.k));
+        self.a2 = self.g * self.a1;
+        self.a3 = self.g * self.a2;
+    }
+
+    fn coefficients(&self) -> Coefficients<T> {
+        Coefficients::StateVariable {
+            g: self.g,
+            k: self.k,
+            a1: self.a1,
+            a2: self.a2,
+            a3: self.a3,
+        }
+    }
+
+    fn render(&mut self, sample: T) -> T {
+        let two = T::cast(2.0);
+        // v1..v3 are voltages at different nodes
+        let v3 = sample - self.ic2eq;
+        let v1 = self.a1 * self.ic1eq + self.a2 * v3;
+        let v2 = self.ic2eq + self.a2 * self.ic1eq + self.a3 * v3; // lp
+        self.ic1eq = (two * v1 - self.ic1eq).flush_denormal(); // state of capacitors
+        self.ic2eq = (two * v2 - self.ic2eq).flush_denormal(); // state of capacitors
+        v2
+    }
+}
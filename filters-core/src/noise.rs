@@ -0,0 +1,40 @@
+//! A cheap deterministic white noise generator, for test signals and measurements.
+
+/// Uniform white noise in [-1, 1) from a linear congruential generator. The same seed always
+/// gives the same sequence.
+pub struct NoiseGen {
+    initial_seed: u32,
+    noise_seed: u32,
+}
+
+impl Default for NoiseGen {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NoiseGen {
+    pub fn new() -> Self {
+        Self::with_seed(22222)
+    }
+
+    pub fn with_seed(noise_seed: u32) -> Self {
+        Self {
+            initial_seed: noise_seed,
+            noise_seed,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.noise_seed = self.initial_seed;
+    }
+
+    pub fn next_value(&mut self) -> f32 {
+        self.noise_seed = self
+            .noise_seed
+            .wrapping_mul(196314165)
+            .wrapping_add(907633515);
+        let tmp = ((self.noise_seed >> 7) as i32) - 16777216;
+        tmp as f32 / 16777216.0f32
+    }
+}
@@ -0,0 +1,64 @@
+//! The floating point types filters can run at.
+
+use core::fmt::Debug;
+use num_traits::{Float, FloatConst};
+
+/// A sample and coefficient type. `f32` is what the audio path uses; `f64` keeps low-frequency and
+/// high-Q designs precise where single precision rounding starts to show.
+pub trait Sample: Float + FloatConst + Debug + Send + Sync + 'static {
+    /// Converts a constant or parameter. Rounds when `Self` is `f32`.
+    fn cast(value: f64) -> Self;
+
+    fn as_f64(self) -> f64;
+
+    /// Replaces subnormal values with zero. Filter states decaying towards silence end up
+    /// subnormal, which many CPUs process at a fraction of the normal speed.
+    fn flush_denormal(self) -> Self {
+        if self.abs() < Self::min_positive_value() {
+            Self::zero()
+        } else {
+            self
+        }
+    }
+
+    /// Picks the `f32` or `f64` member of `per_sample`, e.g. the constructor for this precision
+    /// from a table holding one of each.
+    fn select<P: PerSample>(per_sample: &P) -> P::Output<Self>;
+}
+
+/// Something with one member per `Sample` type, chosen by `Sample::select` without downcasting.
+pub trait PerSample {
+    type Output<T: Sample>;
+
+    fn for_f32(&self) -> Self::Output<f32>;
+
+    fn for_f64(&self) -> Self::Output<f64>;
+}
+
+impl Sample for f32 {
+    fn cast(value: f64) -> Self {
+        value as f32
+    }
+
+    fn select<P: PerSample>(per_sample: &P) -> P::Output<Self> {
+        per_sample.for_f32()
+    }
+
+    fn as_f64(self) -> f64 {
+        self as f64
+    }
+}
+
+impl Sample for f64 {
+    fn cast(value: f64) -> Self {
+        value
+    }
+
+    fn select<P: PerSample>(per_sample: &P) -> P::Output<Self> {
+        per_sample.for_f64()
+    }
+
+    fn as_f64(self) -> f64 {
+        self
+    }
+}
@@ -0,0 +1,318 @@
+//! Several filters processed at once, one per SIMD lane.
+//!
+//! `BiQuadLanes` and `StateVariableLanes` run 2, 4 or 8 independent instances of
+//! `BiQuadFilter` (direct form I) and `StateVariableFilter`, with their own settings per lane.
+//! They perform the same operations in the same order as the scalar filters, without fused
+//! multiply-adds, so each lane's output is bit-identical to the scalar filter's.
+//!
+//! Lanes can be channels of a multichannel stream (see `LaneBank`) or bands of a parallel filter
+//! bank, in which case every lane gets the same input: `render(L::splat(x))`.
+//!
+//! The vector types come from the `wide` crate, which uses SSE/AVX or NEON where the target has
+//! them and plain arrays where it doesn't.
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::ops::{Add, Mul, Sub};
+
+use wide::CmpLt;
+pub use wide::{f32x4, f32x8, f64x2, f64x4};
+
+use crate::{BiQuadFilter, Coefficients, Filter, Sample, StateVariableFilter};
+
+/// A SIMD vector of samples.
+pub trait Lanes:
+    Copy + Add<Output = Self> + Sub<Output = Self> + Mul<Output = Self> + Send + 'static
+{
+    type Scalar: Sample;
+    const LANES: usize;
+
+    fn zero() -> Self;
+    fn splat(value: Self::Scalar) -> Self;
+    /// Loads `values` into the first lanes and zeroes the rest.
+    fn load(values: &[Self::Scalar]) -> Self;
+    /// Writes the first `out.len()` lanes.
+    fn store(self, out: &mut [Self::Scalar]);
+    fn with_lane(self, lane: usize, value: Self::Scalar) -> Self;
+    /// `Sample::flush_denormal` on every lane.
+    fn flush_denormal(self) -> Self;
+}
+
+macro_rules! impl_lanes {
+    ($vector:ty, $scalar:ty, $lanes:expr) => {
+        impl Lanes for $vector {
+            type Scalar = $scalar;
+            const LANES: usize = $lanes;
+
+            fn zero() -> Self {
+                <$vector>::ZERO
+            }
+
+            fn splat(value: $scalar) -> Self {
+                <$vector>::splat(value)
+            }
+
+            fn load(values: &[$scalar]) -> Self {
+                let mut lanes = [0.0; $lanes];
+                lanes[..values.len()].copy_from_slice(values);
+                <$vector>::new(lanes)
+            }
+
+            fn store(self, out: &mut [$scalar]) {
+                let len = out.len();
+                out.copy_from_slice(&self.as_array_ref()[..len]);
+            }
+
+            fn with_lane(self, lane: usize, value: $scalar) -> Self {
+                let mut lanes = self.to_array();
+                lanes[lane] = value;
+                <$vector>::new(lanes)
+            }
+
+            fn flush_denormal(self) -> Self {
+                let denormal = self.abs().cmp_lt(<$vector>::splat(<$scalar>::MIN_POSITIVE));
+                denormal.blend(<$vector>::ZERO, self)
+            }
+        }
+    };
+}
+
+impl_lanes!(f32x4, f32, 4);
+impl_lanes!(f32x8, f32, 8);
+impl_lanes!(f64x2, f64, 2);
+impl_lanes!(f64x4, f64, 4);
+
+/// A filter running one independent instance per lane.
+pub trait LaneFilter<L: Lanes>: Send + Sized {
+    /// All lanes with the coefficients a new scalar filter has.
+    fn new(sample_rate: f32) -> Self;
+    /// The scalar filter a lane is equivalent to, used for channels that don't fill a vector.
+    fn scalar(sample_rate: f32) -> Box<dyn Filter<L::Scalar>>;
+    /// Tunes one lane. Coefficients are computed by the scalar filter, so they match it exactly.
+    fn update_lane(&mut self, lane: usize, cutoff_freq: f32, resonance: f32);
+    fn reset(&mut self);
+    fn render(&mut self, input: L) -> L;
+}
+
+/// `BiQuadFilter` in direct form I, one per lane.
+pub struct BiQuadLanes<L: Lanes> {
+    sample_rate: f32,
+    b0: L,
+    b1: L,
+    b2: L,
+    a1: L,
+    a2: L,
+    x1: L,
+    x2: L,
+    y1: L,
+    y2: L,
+}
+
+impl<L: Lanes> BiQuadLanes<L> {
+    fn set_lane(&mut self, lane: usize, filter: &BiQuadFilter<L::Scalar>) {
+        let Coefficients::BiQuad(c) = filter.coefficients() else {
+            unreachable!("a biquad has biquad coefficients");
+        };
+        self.b0 = self.b0.with_lane(lane, c.b0);
+        self.b1 = self.b1.with_lane(lane, c.b1);
+        self.b2 = self.b2.with_lane(lane, c.b2);
+        self.a1 = self.a1.with_lane(lane, c.a1);
+        self.a2 = self.a2.with_lane(lane, c.a2);
+    }
+}
+
+impl<L: Lanes> LaneFilter<L> for BiQuadLanes<L> {
+    fn new(sample_rate: f32) -> Self {
+        let zero = L::zero();
+        let mut lanes = Self {
+            sample_rate,
+            b0: zero,
+            b1: zero,
+            b2: zero,
+            a1: zero,
+            a2: zero,
+            x1: zero,
+            x2: zero,
+            y1: zero,
+            y2: zero,
+        };
+        let filter = BiQuadFilter::new(sample_rate);
+        for lane in 0..L::LANES {
+            lanes.set_lane(lane, &filter);
+        }
+        lanes
+    }
+
+    fn scalar(sample_rate: f32) -> Box<dyn Filter<L::Scalar>> {
+        Box::new(BiQuadFilter::new(sample_rate))
+    }
+
+    fn update_lane(&mut self, lane: usize, cutoff_freq: f32, resonance: f32) {
+        let mut filter = BiQuadFilter::new(self.sample_rate);
+        filter.update_coefficients(cutoff_freq, resonance);
+        self.set_lane(lane, &filter);
+    }
+
+    fn reset(&mut self) {
+        let zero = L::zero();
+        self.x1 = zero;
+        self.x2 = zero;
+        self.y1 = zero;
+        self.y2 = zero;
+    }
+
+    fn render(&mut self, input: L) -> L {
+        let yn = self.b0 * input + self.b1 * self.x1 + self.b2 * self.x2
+            - self.a1 * self.y1
+            - self.a2 * self.y2;
+
+        self.x2 = self.x1;
+        self.x1 = input;
+
+        self.y2 = self.y1;
+        self.y1 = yn.flush_denormal();
+
+        yn
+    }
+}
+
+/// `StateVariableFilter`, one per lane.
+pub struct StateVariableLanes<L: Lanes> {
+    sample_rate: f32,
+    a1: L,
+    a2: L,
+    a3: L,
+    ic1eq: L,
+    ic2eq: L,
+}
+
+impl<L: Lanes> StateVariableLanes<L> {
+    fn set_lane(&mut self, lane: usize, filter: &StateVariableFilter<L::Scalar>) {
+        let Coefficients::StateVariable { a1, a2, a3, .. } = filter.coefficients() else {
+            unreachable!("a state variable filter has state variable coefficients");
+        };
+        self.a1 = self.a1.with_lane(lane, a1);
+        self.a2 = self.a2.with_lane(lane, a2);
+        self.a3 = self.a3.with_lane(lane, a3);
+    }
+}
+
+impl<L: Lanes> LaneFilter<L> for StateVariableLanes<L> {
+    fn new(sample_rate: f32) -> Self {
+        let zero = L::zero();
+        let mut lanes = Self {
+            sample_rate,
+            a1: zero,
+            a2: zero,
+            a3: zero,
+            ic1eq: zero,
+            ic2eq: zero,
+        };
+        let filter = StateVariableFilter::new(sample_rate);
+        for lane in 0..L::LANES {
+            lanes.set_lane(lane, &filter);
+        }
+        lanes
+    }
+
+    fn scalar(sample_rate: f32) -> Box<dyn Filter<L::Scalar>> {
+        Box::new(StateVariableFilter::new(sample_rate))
+    }
+
+    fn update_lane(&mut self, lane: usize, cutoff_freq: f32, resonance: f32) {
+        let mut filter = StateVariableFilter::new(self.sample_rate);
+        filter.update_coefficients(cutoff_freq, resonance);
+        self.set_lane(lane, &filter);
+    }
+
+    fn reset(&mut self) {
+        let zero = L::zero();
+        self.ic1eq = zero;
+        self.ic2eq = zero;
+    }
+
+    fn render(&mut self, input: L) -> L {
+        let two = L::splat(<L::Scalar as Sample>::cast(2.0));
+        let v3 = input - self.ic2eq;
+        let v1 = self.a1 * self.ic1eq + self.a2 * v3;
+        let v2 = self.ic2eq + self.a2 * self.ic1eq + self.a3 * v3;
+        self.ic1eq = (two * v1 - self.ic1eq).flush_denormal();
+        self.ic2eq = (two * v2 - self.ic2eq).flush_denormal();
+        v2
+    }
+}
+
+/// Filters interleaved audio with any number of channels, `L::LANES` channels at a time. Channels
+/// left over after the last full vector run through the equivalent scalar filter. A bank with no
+/// channels leaves buffers untouched.
+pub struct LaneBank<L: Lanes, F: LaneFilter<L>> {
+    channels: usize,
+    groups: Vec<F>,
+    remainder: Vec<Box<dyn Filter<L::Scalar>>>,
+}
+
+impl<L: Lanes, F: LaneFilter<L>> LaneBank<L, F> {
+    pub fn new(sample_rate: f32, channels: usize) -> Self {
+        Self {
+            channels,
+            groups: (0..channels / L::LANES)
+                .map(|_| F::new(sample_rate))
+                .collect(),
+            remainder: (0..channels % L::LANES)
+                .map(|_| F::scalar(sample_rate))
+                .collect(),
+        }
+    }
+
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    /// Panics if `channel` is not below `channels()`.
+    pub fn update_channel(&mut self, channel: usize, cutoff_freq: f32, resonance: f32) {
+        assert!(
+            channel < self.channels,
+            "channel {} out of range for a bank of {} channels",
+            channel,
+            self.channels
+        );
+        let group = channel / L::LANES;
+        match self.groups.get_mut(group) {
+            Some(lanes) => lanes.update_lane(channel % L::LANES, cutoff_freq, resonance),
+            None => self.remainder[channel - self.groups.len() * L::LANES]
+                .update_coefficients(cutoff_freq, resonance),
+        }
+    }
+
+    pub fn update_all(&mut self, cutoff_freq: f32, resonance: f32) {
+        for channel in 0..self.channels {
+            self.update_channel(channel, cutoff_freq, resonance);
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.groups.iter_mut().for_each(F::reset);
+        self.remainder.iter_mut().for_each(|filter| filter.reset());
+    }
+
+    /// Filters whole frames of `channels()` samples in place.
+    pub fn process_interleaved(&mut self, buffer: &mut [L::Scalar]) {
+        if self.channels == 0 {
+            return;
+        }
+        let vectorized = self.groups.len() * L::LANES;
+        for frame in buffer.chunks_exact_mut(self.channels) {
+            let (vector_part, scalar_part) = frame.split_at_mut(vectorized);
+            for (lanes, samples) in self
+                .groups
+                .iter_mut()
+                .zip(vector_part.chunks_exact_mut(L::LANES))
+            {
+                lanes.render(L::load(samples)).store(samples);
+            }
+            for (filter, sample) in self.remainder.iter_mut().zip(scalar_part.iter_mut()) {
+                *sample = filter.render(*sample);
+            }
+        }
+    }
+}
@@ -0,0 +1,46 @@
+//! Second-order sections in cascade, the usual form for higher-order IIR designs.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::{BiQuadCoefficients, Coefficients, Filter, Sample};
+
+/// A cascade of second-order sections, each realized in transposed direct form II. The design is
+/// fixed, so cutoff and resonance changes are ignored. With no sections it passes audio through.
+pub struct SosFilter<T = f32> {
+    sections: Vec<BiQuadCoefficients<T>>,
+    // z1, z2 per section
+    state: Vec<[T; 2]>,
+}
+
+impl<T: Sample> SosFilter<T> {
+    pub fn new(sections: Vec<BiQuadCoefficients<T>>) -> Self {
+        Self {
+            state: vec![[T::zero(); 2]; sections.len()],
+            sections,
+        }
+    }
+}
+
+impl<T: Sample> Filter<T> for SosFilter<T> {
+    fn reset(&mut self) {
+        self.state.fill([T::zero(); 2]);
+    }
+
+    fn update_coefficients(&mut self, _cutoff_freq: f32, _resonance: f32) {}
+
+    fn coefficients(&self) -> Coefficients<T> {
+        Coefficients::Cascade(self.sections.clone())
+    }
+
+    fn render(&mut self, input_sample: T) -> T {
+        let mut x = input_sample;
+        for (c, z) in self.sections.iter().zip(self.state.iter_mut()) {
+            let y = c.b0 * x + z[0];
+            z[0] = (c.b1 * x - c.a1 * y + z[1]).flush_denormal();
+            z[1] = (c.b2 * x - c.a2 * y).flush_denormal();
+            x = y;
+        }
+        x
+    }
+}
@@ -0,0 +1,212 @@
+//! The SIMD filters against the scalar filters they vectorize. They do the same arithmetic, so
+//! the outputs must be bit-identical, not just close. Values are compared as bit patterns so
+//! signed zeros and NaNs count too.
+
+use filters_core::simd::{f32x4, f32x8, f64x2, f64x4};
+use filters_core::{
+    BiQuadFilter, BiQuadLanes, Filter, LaneBank, LaneFilter, Lanes, NoiseGen, Sample,
+    StateVariableFilter, StateVariableLanes,
+};
+
+const SAMPLE_RATE: f32 = 48000.0;
+const LEN: usize = 4096;
+
+/// Different settings per lane, including extreme ones.
+fn settings(lane: usize) -> (f32, f32) {
+    const SETTINGS: [(f32, f32); 8] = [
+        (1000.0, 0.707),
+        (20.0, 0.1),
+        (23000.0, 30.0),
+        (440.0, 5.0),
+        (5000.0, 0.5),
+        (80.0, 12.0),
+        (12000.0, 1.0),
+        (250.0, 2.0),
+    ];
+    SETTINGS[lane % SETTINGS.len()]
+}
+
+fn noise<T: Sample>(seed: u32, len: usize) -> Vec<T> {
+    let mut noise = NoiseGen::with_seed(seed);
+    (0..len)
+        .map(|_| T::cast(noise.next_value() as f64))
+        .collect()
+}
+
+/// Runs independent noise through every lane and through one scalar filter per lane.
+fn check_lanes<L, F>(scalar: impl Fn() -> Box<dyn Filter<L::Scalar>>)
+where
+    L: Lanes,
+    F: LaneFilter<L>,
+{
+    let mut lanes = F::new(SAMPLE_RATE);
+    let mut scalars: Vec<Box<dyn Filter<L::Scalar>>> = (0..L::LANES).map(|_| scalar()).collect();
+    for (lane, filter) in scalars.iter_mut().enumerate() {
+        let (cutoff, q) = settings(lane);
+        lanes.update_lane(lane, cutoff, q);
+        filter.update_coefficients(cutoff, q);
+    }
+
+    let inputs: Vec<Vec<L::Scalar>> = (0..L::LANES).map(|lane| noise(lane as u32, LEN)).collect();
+    let mut frame = vec![<L::Scalar as Sample>::cast(0.0); L::LANES];
+    let mut output = vec![<L::Scalar as Sample>::cast(0.0); L::LANES];
+
+    for round in 0..2 {
+        for n in 0..LEN {
+            for (sample, input) in frame.iter_mut().zip(inputs.iter()) {
+                *sample = input[n];
+            }
+            lanes.render(L::load(&frame)).store(&mut output);
+            for (lane, filter) in scalars.iter_mut().enumerate() {
+                let expected = filter.render(frame[lane]);
+                assert_eq!(
+                    output[lane].as_f64().to_bits(),
+                    expected.as_f64().to_bits(),
+                    "lane {} of {}, sample {}, round {}: {:?} != {:?}",
+                    lane,
+                    std::any::type_name::<L>(),
+                    n,
+                    round,
+                    output[lane],
+                    expected
+                );
+            }
+        }
+        // The second round checks that reset clears all lanes like the scalar filters.
+        lanes.reset();
+        scalars.iter_mut().for_each(|filter| filter.reset());
+    }
+}
+
+fn biquad<T: Sample>() -> Box<dyn Filter<T>> {
+    Box::new(BiQuadFilter::new(SAMPLE_RATE))
+}
+
+fn state_variable<T: Sample>() -> Box<dyn Filter<T>> {
+    Box::new(StateVariableFilter::new(SAMPLE_RATE))
+}
+
+#[test]
+fn biquad_lanes_match_scalar_biquads() {
+    check_lanes::<f32x4, BiQuadLanes<f32x4>>(biquad);
+    check_lanes::<f32x8, BiQuadLanes<f32x8>>(biquad);
+    check_lanes::<f64x2, BiQuadLanes<f64x2>>(biquad);
+    check_lanes::<f64x4, BiQuadLanes<f64x4>>(biquad);
+}
+
+#[test]
+fn state_variable_lanes_match_scalar_filters() {
+    check_lanes::<f32x4, StateVariableLanes<f32x4>>(state_variable);
+    check_lanes::<f32x8, StateVariableLanes<f32x8>>(state_variable);
+    check_lanes::<f64x2, StateVariableLanes<f64x2>>(state_variable);
+    check_lanes::<f64x4, StateVariableLanes<f64x4>>(state_variable);
+}
+
+/// Untuned lanes behave like freshly built scalar filters.
+#[test]
+fn new_lanes_match_new_scalar_filters() {
+    let input = noise::<f32>(7, LEN);
+
+    let mut lanes = BiQuadLanes::<f32x4>::new(SAMPLE_RATE);
+    let mut scalar = BiQuadFilter::<f32>::new(SAMPLE_RATE);
+    for x in &input {
+        let mut output = [0.0; 4];
+        lanes.render(f32x4::splat(*x)).store(&mut output);
+        assert_eq!(output, [scalar.render(*x); 4]);
+    }
+
+    let mut lanes = StateVariableLanes::<f32x4>::new(SAMPLE_RATE);
+    let mut scalar = StateVariableFilter::<f32>::new(SAMPLE_RATE);
+    for x in &input {
+        let mut output = [0.0; 4];
+        lanes.render(f32x4::splat(*x)).store(&mut output);
+        assert_eq!(output, [scalar.render(*x); 4]);
+    }
+}
+
+/// A parallel bank: one input, a different band per lane.
+#[test]
+fn parallel_bank_matches_scalar_filters() {
+    let mut bank = StateVariableLanes::<f32x8>::new(SAMPLE_RATE);
+    let mut bands: Vec<StateVariableFilter> = (0..8)
+        .map(|band| {
+            let mut filter = StateVariableFilter::new(SAMPLE_RATE);
+            let cutoff = 50.0 * 2.0f32.powi(band);
+            filter.update_coefficients(cutoff, 4.0);
+            bank.update_lane(band as usize, cutoff, 4.0);
+            filter
+        })
+        .collect();
+
+    for x in noise::<f32>(3, LEN) {
+        let mut output = [0.0; 8];
+        bank.render(f32x8::splat(x)).store(&mut output);
+        for (band, filter) in bands.iter_mut().enumerate() {
+            assert_eq!(output[band], filter.render(x), "band {}", band);
+        }
+    }
+}
+
+fn check_bank<L, F>(scalar: impl Fn() -> Box<dyn Filter<L::Scalar>>)
+where
+    L: Lanes,
+    F: LaneFilter<L>,
+{
+    // Fewer channels than lanes, exact multiples and leftovers.
+    for channels in 1..=2 * L::LANES + 1 {
+        let mut bank = LaneBank::<L, F>::new(SAMPLE_RATE, channels);
+        let mut scalars: Vec<Box<dyn Filter<L::Scalar>>> =
+            (0..channels).map(|_| scalar()).collect();
+        for (channel, filter) in scalars.iter_mut().enumerate() {
+            let (cutoff, q) = settings(channel);
+            bank.update_channel(channel, cutoff, q);
+            filter.update_coefficients(cutoff, q);
+        }
+
+        let input = noise::<L::Scalar>(channels as u32, 512 * channels);
+        let mut output = input.clone();
+        // Odd buffer sizes, as long as they hold whole frames.
+        for buffer in output.chunks_mut(channels * 37) {
+            bank.process_interleaved(buffer);
+        }
+
+        for (n, (frame, expected)) in output
+            .chunks(channels)
+            .zip(input.chunks(channels))
+            .enumerate()
+        {
+            for channel in 0..channels {
+                let expected = scalars[channel].render(expected[channel]);
+                assert_eq!(
+                    frame[channel].as_f64().to_bits(),
+                    expected.as_f64().to_bits(),
+                    "{} channels with {}, channel {}, frame {}",
+                    channels,
+                    std::any::type_name::<L>(),
+                    channel,
+                    n
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn lane_bank_without_channels_does_nothing() {
+    let mut bank = LaneBank::<f32x4, BiQuadLanes<f32x4>>::new(SAMPLE_RATE, 0);
+    assert_eq!(bank.channels(), 0);
+    bank.update_all(1000.0, 0.707);
+    bank.reset();
+    let mut buffer = [0.5f32; 16];
+    bank.process_interleaved(&mut buffer);
+    bank.process_interleaved(&mut []);
+    assert_eq!(buffer, [0.5; 16]);
+}
+
+#[test]
+fn lane_bank_matches_scalar_filters_for_any_channel_count() {
+    check_bank::<f32x4, BiQuadLanes<f32x4>>(biquad);
+    check_bank::<f32x8, StateVariableLanes<f32x8>>(state_variable);
+    check_bank::<f64x2, StateVariableLanes<f64x2>>(state_variable);
+    check_bank::<f64x4, BiQuadLanes<f64x4>>(biquad);
+}
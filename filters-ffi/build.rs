@@ -0,0 +1,44 @@
+//! Generates `filters.h` from the `extern "C"` functions in `src/lib.rs` into `OUT_DIR`, then
+//! compiles the C test program against it.
+//!
+//! `include/filters.h` is a checked-in copy so C and C++ projects can use the header without
+//! running cargo. The build never writes it on its own: run
+//! `FILTERS_FFI_UPDATE_HEADER=1 cargo build -p filters-ffi` after changing the API to refresh it.
+//! `tests/c_api.rs` fails while the copy is stale.
+
+use std::path::PathBuf;
+
+fn main() {
+    println!("cargo:rerun-if-changed=src/lib.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+    println!("cargo:rerun-if-changed=tests/c/test_filters.c");
+    println!("cargo:rerun-if-env-changed=FILTERS_FFI_UPDATE_HEADER");
+
+    let crate_dir = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap());
+    let out_dir = PathBuf::from(std::env::var("OUT_DIR").unwrap());
+    let include_dir = out_dir.join("include");
+
+    let config = cbindgen::Config::from_file(crate_dir.join("cbindgen.toml"))
+        .expect("cbindgen.toml is valid");
+    let bindings = cbindgen::Builder::new()
+        .with_config(config)
+        .with_src(crate_dir.join("src/lib.rs"))
+        .generate()
+        .expect("the C header can be generated");
+    // Only writes when the header changed, so it doesn't trigger rebuilds.
+    bindings.write_to_file(include_dir.join("filters.h"));
+    if std::env::var_os("FILTERS_FFI_UPDATE_HEADER").is_some() {
+        bindings.write_to_file(crate_dir.join("include/filters.h"));
+    }
+
+    // Only `tests/c_api.rs` links the program, so it isn't part of the libraries. Cargo is told
+    // where it is, not to link it.
+    cc::Build::new()
+        .file(crate_dir.join("tests/c/test_filters.c"))
+        .include(&include_dir)
+        .std("c99")
+        .warnings_into_errors(true)
+        .cargo_metadata(false)
+        .compile("filters_c_test");
+    println!("cargo:rustc-link-search=native={}", out_dir.display());
+}
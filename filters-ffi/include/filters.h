@@ -0,0 +1,240 @@
+#ifndef FILTERS_H
+#define FILTERS_H
+
+/* Generated by cbindgen from filters-ffi/src/lib.rs; do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Structure of a biquad. See `BiQuadTopology`.
+ */
+typedef enum FiltersTopology {
+  FILTERS_TOPOLOGY_DIRECT_FORM_I = 0,
+  FILTERS_TOPOLOGY_DIRECT_FORM_II = 1,
+  FILTERS_TOPOLOGY_TRANSPOSED_DIRECT_FORM_II = 2,
+  FILTERS_TOPOLOGY_LATTICE = 3,
+} FiltersTopology;
+
+/**
+ * Output of the TPT state variable filter.
+ */
+typedef enum FiltersOutput {
+  FILTERS_OUTPUT_LOW_PASS = 0,
+  FILTERS_OUTPUT_HIGH_PASS = 1,
+  FILTERS_OUTPUT_BAND_PASS = 2,
+} FiltersOutput;
+
+/**
+ * Sample word length of the fixed-point filters.
+ */
+typedef enum FiltersFixedFormat {
+  FILTERS_FIXED_FORMAT_Q15 = 0,
+  FILTERS_FIXED_FORMAT_Q31 = 1,
+} FiltersFixedFormat;
+
+/**
+ * A filter of any type, processing `float` samples.
+ */
+typedef struct FiltersFilter FiltersFilter;
+
+/**
+ * A second-order section normalized so that a0 = 1:
+ * y[n] = b0 x[n] + b1 x[n-1] + b2 x[n-2] - a1 y[n-1] - a2 y[n-2].
+ */
+typedef struct FiltersBiQuadCoefficients {
+  double b0;
+  double b1;
+  double b2;
+  double a1;
+  double a2;
+} FiltersBiQuadCoefficients;
+
+/**
+ * Coefficients of the state variable filter, in Andrew Simper's notation: g = tan(pi fc / fs),
+ * k = 1 / Q, a1 = 1 / (1 + g (g + k)), a2 = g a1 and a3 = g a2.
+ */
+typedef struct FiltersStateVariableCoefficients {
+  double g;
+  double k;
+  double a1;
+  double a2;
+  double a3;
+} FiltersStateVariableCoefficients;
+
+/**
+ * Coefficients of the TPT state variable filter: the integrator gain g, the damping r2 = 1 / Q
+ * and h = 1 / (1 + r2 g + g²), which resolves the zero-delay feedback loop.
+ */
+typedef struct FiltersStateVariableTPTCoefficients {
+  double g;
+  double h;
+  double r2;
+} FiltersStateVariableTPTCoefficients;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Creates an RBJ low-pass biquad with the `FiltersTopology` given in `topology`. Returns NULL if
+ * the sample rate isn't positive or `topology` isn't a `FiltersTopology`.
+ */
+struct FiltersFilter *filters_biquad_create(float sample_rate, uint32_t topology);
+
+/**
+ * Creates Andrew Simper's low-pass state variable filter. Returns NULL if the sample rate isn't
+ * positive.
+ */
+struct FiltersFilter *filters_state_variable_create(float sample_rate);
+
+/**
+ * Creates a topology-preserving transform state variable filter with the `FiltersOutput` given
+ * in `output`. Returns NULL if the sample rate isn't positive or `output` isn't a
+ * `FiltersOutput`.
+ */
+struct FiltersFilter *filters_state_variable_tpt_create(float sample_rate, uint32_t output);
+
+/**
+ * Creates the two-tap FIR low-pass, y[n] = x[n] + x[n-1]. It has no parameters.
+ */
+struct FiltersFilter *filters_fir_lowpass_create(void);
+
+/**
+ * Creates the two-tap FIR high-pass, y[n] = x[n] - x[n-1]. It has no parameters.
+ */
+struct FiltersFilter *filters_fir_highpass_create(void);
+
+/**
+ * Creates a fixed-point direct form I biquad with the same design as `filters_biquad_create`.
+ * `format` is a `FiltersFixedFormat` and `coefficient_bits` is clamped to 8..=32. Returns NULL
+ * if the sample rate isn't positive or `format` isn't a `FiltersFixedFormat`.
+ */
+struct FiltersFilter *filters_fixed_biquad_create(float sample_rate,
+                                                  uint32_t format,
+                                                  uint32_t coefficient_bits);
+
+/**
+ * Creates a fixed-point state variable filter with the same design as
+ * `filters_state_variable_create`. `format` is a `FiltersFixedFormat` and `coefficient_bits` is
+ * clamped to 8..=32. Returns NULL if the sample rate isn't positive or `format` isn't a
+ * `FiltersFixedFormat`.
+ */
+struct FiltersFilter *filters_fixed_state_variable_create(float sample_rate,
+                                                          uint32_t format,
+                                                          uint32_t coefficient_bits);
+
+/**
+ * Creates a cascade of `count` second-order sections, run in transposed direct form II. The
+ * sections are copied. Returns NULL if `sections` is NULL and `count` isn't 0.
+ *
+ * # Safety
+ *
+ * `sections` must point to `count` readable sections.
+ */
+struct FiltersFilter *filters_sos_create(const struct FiltersBiQuadCoefficients *sections,
+                                         size_t count);
+
+/**
+ * Creates an FIR filter from `count` taps, `taps[k]` weighting x[n - k]. The taps are copied.
+ * Returns NULL if `taps` is NULL and `count` isn't 0.
+ *
+ * # Safety
+ *
+ * `taps` must point to `count` readable values.
+ */
+struct FiltersFilter *filters_fir_create(const double *taps, size_t count);
+
+/**
+ * Frees a filter. NULL is ignored.
+ *
+ * # Safety
+ *
+ * `filter` must be NULL or come from one of the constructors, and not have been destroyed.
+ */
+void filters_destroy(struct FiltersFilter *filter);
+
+/**
+ * Sets the cutoff in Hz and the resonance Q, recomputing the coefficients. The cutoff is clamped
+ * to 0.49 times the sample rate. The state is kept, so this can be called between blocks while
+ * processing. Filters designed elsewhere (SOS and FIR) and the two-tap FIR filters ignore it.
+ * Returns false, leaving the coefficients untouched, if `filter` is NULL or the cutoff or Q isn't
+ * positive and finite.
+ *
+ * # Safety
+ *
+ * `filter` must be NULL or a live handle.
+ */
+bool filters_set_parameters(struct FiltersFilter *filter, float cutoff_hz, float q);
+
+/**
+ * Filters `len` samples in place. NULL handles or blocks are ignored.
+ *
+ * # Safety
+ *
+ * `filter` must be NULL or a live handle, and a non-NULL `block` must point to `len` writable
+ * samples.
+ */
+void filters_process_block(struct FiltersFilter *filter, float *block, size_t len);
+
+/**
+ * Clears the filter's state as if it had only processed silence, keeping its parameters. NULL is
+ * ignored.
+ *
+ * # Safety
+ *
+ * `filter` must be NULL or a live handle.
+ */
+void filters_reset(struct FiltersFilter *filter);
+
+/**
+ * Writes the normalized coefficients of the RBJ low-pass biquad for the given settings to `out`,
+ * with the cutoff clamped like in `filters_set_parameters`. Returns false, leaving `out`
+ * untouched, if `out` is NULL or the sample rate, cutoff or Q isn't positive and finite.
+ *
+ * # Safety
+ *
+ * A non-NULL `out` must be writable.
+ */
+bool filters_design_biquad(float sample_rate,
+                           float cutoff_hz,
+                           float q,
+                           struct FiltersBiQuadCoefficients *out);
+
+/**
+ * Writes the coefficients of the state variable filter for the given settings to `out`, with the
+ * cutoff clamped like in `filters_set_parameters`. Returns false, leaving `out` untouched, if
+ * `out` is NULL or the sample rate, cutoff or Q isn't positive and finite.
+ *
+ * # Safety
+ *
+ * A non-NULL `out` must be writable.
+ */
+bool filters_design_state_variable(float sample_rate,
+                                   float cutoff_hz,
+                                   float q,
+                                   struct FiltersStateVariableCoefficients *out);
+
+/**
+ * Writes the coefficients of the TPT state variable filter for the given settings to `out`.
+ * They are the same for every output, and the cutoff is clamped like in
+ * `filters_set_parameters`. Returns false, leaving `out` untouched, if `out` is NULL or the
+ * sample rate, cutoff or Q isn't positive and finite.
+ *
+ * # Safety
+ *
+ * A non-NULL `out` must be writable.
+ */
+bool filters_design_state_variable_tpt(float sample_rate,
+                                       float cutoff_hz,
+                                       float q,
+                                       struct FiltersStateVariableTPTCoefficients *out);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* FILTERS_H */
@@ -0,0 +1,512 @@
+//! C ABI for the filters in `filters-core`, so C and C++ code can run the same filters as the app.
+//!
+//! Every filter is behind an opaque `FiltersFilter` handle. The handle comes from the
+//! constructor for its filter type, and it is used with the functions shared by all types:
+//! `filters_set_parameters`, `filters_process_block`, `filters_reset` and
+//! `filters_destroy`. Processing is in `float`. The design functions return the coefficients a
+//! filter would run with, in `double`, without creating one.
+//!
+//! Enum arguments are passed as `uint32_t` holding one of the `Filters*` enum values, since an
+//! out-of-range value in a Rust enum would be undefined behavior. Constructors return NULL for
+//! values they don't know.
+//!
+//! Cutoffs are clamped to 0.49 times the sample rate, like in the app and the plugin, since
+//! designs past Nyquist are unstable.
+//!
+//! The build script generates the header from this file; `include/filters.h` is the checked-in
+//! copy (see `build.rs`). A handle is not thread-safe, but separate handles can be used from
+//! separate threads.
+
+#![warn(clippy::all, rust_2018_idioms)]
+
+use std::ptr;
+
+use filters_core::{
+    BiQuadCoefficients, BiQuadFilter, BiQuadTopology, Coefficients, Filter, FilterType, FirFilter,
+    FirHighPassFilter, FirLowPassFilter, FixedBiQuadFilter, FixedFormat, FixedPointParams,
+    FixedStateVariableFilter, SosFilter, StateVariableFilter, StateVariableTPTFilter,
+    MAX_CUTOFF_RATIO,
+};
+
+/// A filter of any type, processing `float` samples.
+pub struct FiltersFilter {
+    inner: Box<dyn Filter<f32>>,
+    /// What cutoffs are clamped against. 0 for filters that ignore their parameters.
+    sample_rate: f32,
+}
+
+/// Structure of a biquad. See `BiQuadTopology`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub enum FiltersTopology {
+    DirectFormI = 0,
+    DirectFormII = 1,
+    TransposedDirectFormII = 2,
+    Lattice = 3,
+}
+
+/// Output of the TPT state variable filter.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub enum FiltersOutput {
+    LowPass = 0,
+    HighPass = 1,
+    BandPass = 2,
+}
+
+/// Sample word length of the fixed-point filters.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub enum FiltersFixedFormat {
+    Q15 = 0,
+    Q31 = 1,
+}
+
+/// A second-order section normalized so that a0 = 1:
+/// y[n] = b0 x[n] + b1 x[n-1] + b2 x[n-2] - a1 y[n-1] - a2 y[n-2].
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FiltersBiQuadCoefficients {
+    pub b0: f64,
+    pub b1: f64,
+    pub b2: f64,
+    pub a1: f64,
+    pub a2: f64,
+}
+
+/// Coefficients of the state variable filter, in Andrew Simper's notation: g = tan(pi fc / fs),
+/// k = 1 / Q, a1 = 1 / (1 + g (g + k)), a2 = g a1 and a3 = g a2.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FiltersStateVariableCoefficients {
+    pub g: f64,
+    pub k: f64,
+    pub a1: f64,
+    pub a2: f64,
+    pub a3: f64,
+}
+
+/// Coefficients of the TPT state variable filter: the integrator gain g, the damping r2 = 1 / Q
+/// and h = 1 / (1 + r2 g + g²), which resolves the zero-delay feedback loop.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FiltersStateVariableTPTCoefficients {
+    pub g: f64,
+    pub h: f64,
+    pub r2: f64,
+}
+
+/// Looks up the value a C caller passed for one of the enums above.
+fn lookup<E: Copy, T: Copy>(value: u32, table: &[(E, T)], to_raw: impl Fn(E) -> u32) -> Option<T> {
+    table
+        .iter()
+        .find(|&&(raw, _)| to_raw(raw) == value)
+        .map(|&(_, converted)| converted)
+}
+
+fn topology(value: u32) -> Option<BiQuadTopology> {
+    lookup(
+        value,
+        &[
+            (FiltersTopology::DirectFormI, BiQuadTopology::DirectFormI),
+            (FiltersTopology::DirectFormII, BiQuadTopology::DirectFormII),
+            (
+                FiltersTopology::TransposedDirectFormII,
+                BiQuadTopology::TransposedDirectFormII,
+            ),
+            (FiltersTopology::Lattice, BiQuadTopology::Lattice),
+        ],
+        |raw| raw as u32,
+    )
+}
+
+fn output(value: u32) -> Option<FilterType> {
+    lookup(
+        value,
+        &[
+            (FiltersOutput::LowPass, FilterType::LowPass),
+            (FiltersOutput::HighPass, FilterType::HighPass),
+            (FiltersOutput::BandPass, FilterType::BandPass),
+        ],
+        |raw| raw as u32,
+    )
+}
+
+fn fixed_format(value: u32) -> Option<FixedFormat> {
+    lookup(
+        value,
+        &[
+            (FiltersFixedFormat::Q15, FixedFormat::Q15),
+            (FiltersFixedFormat::Q31, FixedFormat::Q31),
+        ],
+        |raw| raw as u32,
+    )
+}
+
+impl From<BiQuadCoefficients<f64>> for FiltersBiQuadCoefficients {
+    fn from(c: BiQuadCoefficients<f64>) -> Self {
+        Self {
+            b0: c.b0,
+            b1: c.b1,
+            b2: c.b2,
+            a1: c.a1,
+            a2: c.a2,
+        }
+    }
+}
+
+impl From<FiltersBiQuadCoefficients> for BiQuadCoefficients<f64> {
+    fn from(c: FiltersBiQuadCoefficients) -> Self {
+        Self {
+            b0: c.b0,
+            b1: c.b1,
+            b2: c.b2,
+            a1: c.a1,
+            a2: c.a2,
+        }
+    }
+}
+
+fn valid_sample_rate(sample_rate: f32) -> bool {
+    sample_rate.is_finite() && sample_rate > 0.0
+}
+
+/// Checks a cutoff and Q and clamps the cutoff below Nyquist.
+fn parameters(sample_rate: f32, cutoff_hz: f32, q: f32) -> Option<(f32, f32)> {
+    let valid = |value: f32| value.is_finite() && value > 0.0;
+    (valid(cutoff_hz) && valid(q)).then(|| (cutoff_hz.min(sample_rate * MAX_CUTOFF_RATIO), q))
+}
+
+fn into_handle(filter: impl Filter<f32> + 'static, sample_rate: f32) -> *mut FiltersFilter {
+    Box::into_raw(Box::new(FiltersFilter {
+        inner: Box::new(filter),
+        sample_rate,
+    }))
+}
+
+/// Reads `count` elements from `data`, which may only be NULL if `count` is 0.
+///
+/// # Safety
+///
+/// A non-NULL `data` must point to `count` readable elements.
+unsafe fn slice<'a, T>(data: *const T, count: usize) -> Option<&'a [T]> {
+    if data.is_null() {
+        (count == 0).then_some(&[])
+    } else {
+        Some(std::slice::from_raw_parts(data, count))
+    }
+}
+
+/// Creates an RBJ low-pass biquad with the `FiltersTopology` given in `topology`. Returns NULL if
+/// the sample rate isn't positive or `topology` isn't a `FiltersTopology`.
+#[no_mangle]
+pub extern "C" fn filters_biquad_create(sample_rate: f32, topology: u32) -> *mut FiltersFilter {
+    let Some(topology) = self::topology(topology) else {
+        return ptr::null_mut();
+    };
+    if !valid_sample_rate(sample_rate) {
+        return ptr::null_mut();
+    }
+    into_handle(
+        BiQuadFilter::<f32>::with_topology(sample_rate, topology),
+        sample_rate,
+    )
+}
+
+/// Creates Andrew Simper's low-pass state variable filter. Returns NULL if the sample rate isn't
+/// positive.
+#[no_mangle]
+pub extern "C" fn filters_state_variable_create(sample_rate: f32) -> *mut FiltersFilter {
+    if !valid_sample_rate(sample_rate) {
+        return ptr::null_mut();
+    }
+    into_handle(StateVariableFilter::<f32>::new(sample_rate), sample_rate)
+}
+
+/// Creates a topology-preserving transform state variable filter with the `FiltersOutput` given
+/// in `output`. Returns NULL if the sample rate isn't positive or `output` isn't a
+/// `FiltersOutput`.
+#[no_mangle]
+pub extern "C" fn filters_state_variable_tpt_create(
+    sample_rate: f32,
+    output: u32,
+) -> *mut FiltersFilter {
+    let Some(output) = self::output(output) else {
+        return ptr::null_mut();
+    };
+    if !valid_sample_rate(sample_rate) {
+        return ptr::null_mut();
+    }
+    into_handle(
+        StateVariableTPTFilter::<f32>::with_filter_type(sample_rate, output),
+        sample_rate,
+    )
+}
+
+/// Creates the two-tap FIR low-pass, y[n] = x[n] + x[n-1]. It has no parameters.
+#[no_mangle]
+pub extern "C" fn filters_fir_lowpass_create() -> *mut FiltersFilter {
+    into_handle(FirLowPassFilter::<f32>::new(0.0), 0.0)
+}
+
+/// Creates the two-tap FIR high-pass, y[n] = x[n] - x[n-1]. It has no parameters.
+#[no_mangle]
+pub extern "C" fn filters_fir_highpass_create() -> *mut FiltersFilter {
+    into_handle(FirHighPassFilter::<f32>::new(0.0), 0.0)
+}
+
+/// Creates a fixed-point direct form I biquad with the same design as `filters_biquad_create`.
+/// `format` is a `FiltersFixedFormat` and `coefficient_bits` is clamped to 8..=32. Returns NULL
+/// if the sample rate isn't positive or `format` isn't a `FiltersFixedFormat`.
+#[no_mangle]
+pub extern "C" fn filters_fixed_biquad_create(
+    sample_rate: f32,
+    format: u32,
+    coefficient_bits: u32,
+) -> *mut FiltersFilter {
+    let Some(format) = fixed_format(format) else {
+        return ptr::null_mut();
+    };
+    if !valid_sample_rate(sample_rate) {
+        return ptr::null_mut();
+    }
+    let params = FixedPointParams {
+        format,
+        coefficient_bits,
+    };
+    into_handle(FixedBiQuadFilter::new(sample_rate, params), sample_rate)
+}
+
+/// Creates a fixed-point state variable filter with the same design as
+/// `filters_state_variable_create`. `format` is a `FiltersFixedFormat` and `coefficient_bits` is
+/// clamped to 8..=32. Returns NULL if the sample rate isn't positive or `format` isn't a
+/// `FiltersFixedFormat`.
+#[no_mangle]
+pub extern "C" fn filters_fixed_state_variable_create(
+    sample_rate: f32,
+    format: u32,
+    coefficient_bits: u32,
+) -> *mut FiltersFilter {
+    let Some(format) = fixed_format(format) else {
+        return ptr::null_mut();
+    };
+    if !valid_sample_rate(sample_rate) {
+        return ptr::null_mut();
+    }
+    let params = FixedPointParams {
+        format,
+        coefficient_bits,
+    };
+    into_handle(
+        FixedStateVariableFilter::new(sample_rate, params),
+        sample_rate,
+    )
+}
+
+/// Creates a cascade of `count` second-order sections, run in transposed direct form II. The
+/// sections are copied. Returns NULL if `sections` is NULL and `count` isn't 0.
+///
+/// # Safety
+///
+/// `sections` must point to `count` readable sections.
+#[no_mangle]
+pub unsafe extern "C" fn filters_sos_create(
+    sections: *const FiltersBiQuadCoefficients,
+    count: usize,
+) -> *mut FiltersFilter {
+    let Some(sections) = slice(sections, count) else {
+        return ptr::null_mut();
+    };
+    let sections = sections
+        .iter()
+        .map(|&c| BiQuadCoefficients::<f64>::from(c).cast())
+        .collect();
+    into_handle(SosFilter::<f32>::new(sections), 0.0)
+}
+
+/// Creates an FIR filter from `count` taps, `taps[k]` weighting x[n - k]. The taps are copied.
+/// Returns NULL if `taps` is NULL and `count` isn't 0.
+///
+/// # Safety
+///
+/// `taps` must point to `count` readable values.
+#[no_mangle]
+pub unsafe extern "C" fn filters_fir_create(taps: *const f64, count: usize) -> *mut FiltersFilter {
+    let Some(taps) = slice(taps, count) else {
+        return ptr::null_mut();
+    };
+    into_handle(
+        FirFilter::<f32>::new(taps.iter().map(|&tap| tap as f32).collect()),
+        0.0,
+    )
+}
+
+/// Frees a filter. NULL is ignored.
+///
+/// # Safety
+///
+/// `filter` must be NULL or come from one of the constructors, and not have been destroyed.
+#[no_mangle]
+pub unsafe extern "C" fn filters_destroy(filter: *mut FiltersFilter) {
+    if !filter.is_null() {
+        drop(Box::from_raw(filter));
+    }
+}
+
+/// Sets the cutoff in Hz and the resonance Q, recomputing the coefficients. The cutoff is clamped
+/// to 0.49 times the sample rate. The state is kept, so this can be called between blocks while
+/// processing. Filters designed elsewhere (SOS and FIR) and the two-tap FIR filters ignore it.
+/// Returns false, leaving the coefficients untouched, if `filter` is NULL or the cutoff or Q isn't
+/// positive and finite.
+///
+/// # Safety
+///
+/// `filter` must be NULL or a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn filters_set_parameters(
+    filter: *mut FiltersFilter,
+    cutoff_hz: f32,
+    q: f32,
+) -> bool {
+    let Some(filter) = filter.as_mut() else {
+        return false;
+    };
+    let Some((cutoff_hz, q)) = parameters(filter.sample_rate, cutoff_hz, q) else {
+        return false;
+    };
+    filter.inner.update_coefficients(cutoff_hz, q);
+    true
+}
+
+/// Filters `len` samples in place. NULL handles or blocks are ignored.
+///
+/// # Safety
+///
+/// `filter` must be NULL or a live handle, and a non-NULL `block` must point to `len` writable
+/// samples.
+#[no_mangle]
+pub unsafe extern "C" fn filters_process_block(
+    filter: *mut FiltersFilter,
+    block: *mut f32,
+    len: usize,
+) {
+    let Some(filter) = filter.as_mut() else {
+        return;
+    };
+    if block.is_null() {
+        return;
+    }
+    filter
+        .inner
+        .process_block(std::slice::from_raw_parts_mut(block, len));
+}
+
+/// Clears the filter's state as if it had only processed silence, keeping its parameters. NULL is
+/// ignored.
+///
+/// # Safety
+///
+/// `filter` must be NULL or a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn filters_reset(filter: *mut FiltersFilter) {
+    if let Some(filter) = filter.as_mut() {
+        filter.inner.reset();
+    }
+}
+
+/// Writes the normalized coefficients of the RBJ low-pass biquad for the given settings to `out`,
+/// with the cutoff clamped like in `filters_set_parameters`. Returns false, leaving `out`
+/// untouched, if `out` is NULL or the sample rate, cutoff or Q isn't positive and finite.
+///
+/// # Safety
+///
+/// A non-NULL `out` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn filters_design_biquad(
+    sample_rate: f32,
+    cutoff_hz: f32,
+    q: f32,
+    out: *mut FiltersBiQuadCoefficients,
+) -> bool {
+    let Some(out) = out.as_mut() else {
+        return false;
+    };
+    if !valid_sample_rate(sample_rate) {
+        return false;
+    }
+    let Some((cutoff_hz, q)) = parameters(sample_rate, cutoff_hz, q) else {
+        return false;
+    };
+    let mut design = BiQuadFilter::<f64>::new(sample_rate);
+    design.update_coefficients(cutoff_hz, q);
+    let Coefficients::BiQuad(c) = design.coefficients() else {
+        unreachable!("a biquad has biquad coefficients");
+    };
+    *out = c.into();
+    true
+}
+
+/// Writes the coefficients of the state variable filter for the given settings to `out`, with the
+/// cutoff clamped like in `filters_set_parameters`. Returns false, leaving `out` untouched, if
+/// `out` is NULL or the sample rate, cutoff or Q isn't positive and finite.
+///
+/// # Safety
+///
+/// A non-NULL `out` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn filters_design_state_variable(
+    sample_rate: f32,
+    cutoff_hz: f32,
+    q: f32,
+    out: *mut FiltersStateVariableCoefficients,
+) -> bool {
+    let Some(out) = out.as_mut() else {
+        return false;
+    };
+    if !valid_sample_rate(sample_rate) {
+        return false;
+    }
+    let Some((cutoff_hz, q)) = parameters(sample_rate, cutoff_hz, q) else {
+        return false;
+    };
+    let mut design = StateVariableFilter::<f64>::new(sample_rate);
+    design.update_coefficients(cutoff_hz, q);
+    let Coefficients::StateVariable { g, k, a1, a2, a3 } = design.coefficients() else {
+        unreachable!("a state variable filter has state variable coefficients");
+    };
+    *out = FiltersStateVariableCoefficients { g, k, a1, a2, a3 };
+    true
+}
+
+/// Writes the coefficients of the TPT state variable filter for the given settings to `out`.
+/// They are the same for every output, and the cutoff is clamped like in
+/// `filters_set_parameters`. Returns false, leaving `out` untouched, if `out` is NULL or the
+/// sample rate, cutoff or Q isn't positive and finite.
+///
+/// # Safety
+///
+/// A non-NULL `out` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn filters_design_state_variable_tpt(
+    sample_rate: f32,
+    cutoff_hz: f32,
+    q: f32,
+    out: *mut FiltersStateVariableTPTCoefficients,
+) -> bool {
+    let Some(out) = out.as_mut() else {
+        return false;
+    };
+    if !valid_sample_rate(sample_rate) {
+        return false;
+    }
+    let Some((cutoff_hz, q)) = parameters(sample_rate, cutoff_hz, q) else {
+        return false;
+    };
+    let mut design = StateVariableTPTFilter::<f64>::new(sample_rate);
+    design.update_coefficients(cutoff_hz, q);
+    let Coefficients::StateVariableTPT { g, h, r2 } = design.coefficients() else {
+        unreachable!("a TPT state variable filter has TPT coefficients");
+    };
+    *out = FiltersStateVariableTPTCoefficients { g, h, r2 };
+    true
+}
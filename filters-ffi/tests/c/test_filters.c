@@ -0,0 +1,250 @@
+/* Exercises the C API the way an engine would: create, set parameters, process, reset, destroy.
+ * Compiled by the build script and run by tests/c_api.rs. */
+
+#include <math.h>
+#include <stdio.h>
+
+#include "filters.h"
+
+#define SAMPLE_RATE 48000.0f
+#define BLOCK 256
+#define PI 3.14159265358979323846
+
+static int failures = 0;
+
+#define CHECK(condition, ...)                                                  \
+  do {                                                                         \
+    if (!(condition)) {                                                        \
+      fprintf(stderr, "%s:%d: ", __FILE__, __LINE__);                          \
+      fprintf(stderr, __VA_ARGS__);                                            \
+      fprintf(stderr, "\n");                                                   \
+      failures++;                                                              \
+    }                                                                          \
+  } while (0)
+
+/* Runs a unit step through the filter in blocks and returns the last output. */
+static float step_response(FiltersFilter *filter, int blocks) {
+  float block[BLOCK];
+  for (int b = 0; b < blocks; b++) {
+    for (int i = 0; i < BLOCK; i++) {
+      block[i] = 1.0f;
+    }
+    filters_process_block(filter, block, BLOCK);
+  }
+  return block[BLOCK - 1];
+}
+
+static void impulse_response(FiltersFilter *filter, float *out, int len) {
+  for (int i = 0; i < len; i++) {
+    out[i] = i == 0 ? 1.0f : 0.0f;
+  }
+  filters_process_block(filter, out, (size_t)len);
+}
+
+static void test_biquad_topologies(void) {
+  const FiltersTopology topologies[] = {
+      FILTERS_TOPOLOGY_DIRECT_FORM_I,
+      FILTERS_TOPOLOGY_DIRECT_FORM_II,
+      FILTERS_TOPOLOGY_TRANSPOSED_DIRECT_FORM_II,
+      FILTERS_TOPOLOGY_LATTICE,
+  };
+  for (size_t t = 0; t < sizeof topologies / sizeof topologies[0]; t++) {
+    FiltersFilter *filter = filters_biquad_create(SAMPLE_RATE, topologies[t]);
+    CHECK(filter != NULL, "biquad %zu not created", t);
+    filters_set_parameters(filter, 1000.0f, 0.707f);
+    float dc = step_response(filter, 8);
+    CHECK(fabsf(dc - 1.0f) < 1e-3f, "biquad %zu DC gain %f", t, dc);
+    filters_destroy(filter);
+  }
+}
+
+static void test_state_variable(void) {
+  FiltersFilter *svf = filters_state_variable_create(SAMPLE_RATE);
+  filters_set_parameters(svf, 1000.0f, 0.707f);
+  float dc = step_response(svf, 8);
+  CHECK(fabsf(dc - 1.0f) < 1e-3f, "state variable DC gain %f", dc);
+  filters_destroy(svf);
+
+  FiltersFilter *low = filters_state_variable_tpt_create(SAMPLE_RATE, FILTERS_OUTPUT_LOW_PASS);
+  FiltersFilter *high = filters_state_variable_tpt_create(SAMPLE_RATE, FILTERS_OUTPUT_HIGH_PASS);
+  filters_set_parameters(low, 1000.0f, 0.707f);
+  filters_set_parameters(high, 1000.0f, 0.707f);
+  dc = step_response(low, 8);
+  CHECK(fabsf(dc - 1.0f) < 1e-3f, "TPT low-pass DC gain %f", dc);
+  dc = step_response(high, 8);
+  CHECK(fabsf(dc) < 1e-3f, "TPT high-pass DC gain %f", dc);
+  filters_destroy(low);
+  filters_destroy(high);
+}
+
+/* A cascade of the designed section runs like the biquad it was designed from. */
+static void test_design_and_sos(void) {
+  FiltersBiQuadCoefficients c;
+  CHECK(filters_design_biquad(SAMPLE_RATE, 1000.0f, 2.0f, &c), "biquad design failed");
+  double dc = (c.b0 + c.b1 + c.b2) / (1.0 + c.a1 + c.a2);
+  CHECK(fabs(dc - 1.0) < 1e-9, "designed biquad DC gain %f", dc);
+
+  FiltersFilter *biquad =
+      filters_biquad_create(SAMPLE_RATE, FILTERS_TOPOLOGY_TRANSPOSED_DIRECT_FORM_II);
+  FiltersFilter *sos = filters_sos_create(&c, 1);
+  CHECK(sos != NULL, "SOS not created");
+  filters_set_parameters(biquad, 1000.0f, 2.0f);
+  /* Ignored by filters designed elsewhere. */
+  filters_set_parameters(sos, 5000.0f, 0.5f);
+
+  float expected[BLOCK], actual[BLOCK];
+  impulse_response(biquad, expected, BLOCK);
+  impulse_response(sos, actual, BLOCK);
+  for (int i = 0; i < BLOCK; i++) {
+    CHECK(fabsf(expected[i] - actual[i]) < 1e-5f, "SOS sample %d: %f, biquad %f", i,
+          actual[i], expected[i]);
+  }
+  filters_destroy(biquad);
+  filters_destroy(sos);
+
+  FiltersStateVariableCoefficients svf;
+  CHECK(filters_design_state_variable(SAMPLE_RATE, 1000.0f, 2.0f, &svf),
+        "state variable design failed");
+  double g = tan(PI * 1000.0 / SAMPLE_RATE);
+  CHECK(fabs(svf.g - g) < 1e-9 && fabs(svf.k - 0.5) < 1e-9, "state variable g %f, k %f", svf.g,
+        svf.k);
+
+  FiltersStateVariableTPTCoefficients tpt;
+  CHECK(filters_design_state_variable_tpt(SAMPLE_RATE, 1000.0f, 2.0f, &tpt),
+        "TPT design failed");
+  CHECK(fabs(tpt.g - g) < 1e-9 && fabs(tpt.r2 - 0.5) < 1e-9, "TPT g %f, r2 %f", tpt.g, tpt.r2);
+}
+
+static void test_fir(void) {
+  const double taps[] = {0.5, 0.5};
+  FiltersFilter *fir = filters_fir_create(taps, 2);
+  FiltersFilter *lowpass = filters_fir_lowpass_create();
+  float expected[8], actual[8];
+  impulse_response(lowpass, expected, 8);
+  impulse_response(fir, actual, 8);
+  for (int i = 0; i < 8; i++) {
+    CHECK(actual[i] == 0.5f * expected[i], "FIR sample %d: %f", i, actual[i]);
+  }
+  filters_destroy(fir);
+  filters_destroy(lowpass);
+
+  FiltersFilter *highpass = filters_fir_highpass_create();
+  float dc = step_response(highpass, 1);
+  CHECK(dc == 0.0f, "FIR high-pass DC gain %f", dc);
+  filters_destroy(highpass);
+}
+
+/* Q31 with 32-bit coefficients is within rounding of the float filter. */
+static void test_fixed_point(void) {
+  FiltersFilter *fixed = filters_fixed_biquad_create(SAMPLE_RATE, FILTERS_FIXED_FORMAT_Q31, 32);
+  FiltersFilter *reference = filters_biquad_create(SAMPLE_RATE, FILTERS_TOPOLOGY_DIRECT_FORM_I);
+  filters_set_parameters(fixed, 1000.0f, 0.707f);
+  filters_set_parameters(reference, 1000.0f, 0.707f);
+  float expected[BLOCK], actual[BLOCK];
+  impulse_response(reference, expected, BLOCK);
+  impulse_response(fixed, actual, BLOCK);
+  for (int i = 0; i < BLOCK; i++) {
+    CHECK(fabsf(expected[i] - actual[i]) < 1e-4f, "fixed biquad sample %d: %f, float %f", i,
+          actual[i], expected[i]);
+  }
+  filters_destroy(fixed);
+  filters_destroy(reference);
+
+  FiltersFilter *svf = filters_fixed_state_variable_create(SAMPLE_RATE, FILTERS_FIXED_FORMAT_Q15, 16);
+  CHECK(svf != NULL, "fixed state variable not created");
+  filters_set_parameters(svf, 1000.0f, 0.707f);
+  float block[BLOCK];
+  impulse_response(svf, block, BLOCK);
+  filters_destroy(svf);
+}
+
+/* After a reset the filter renders exactly what a new one would. */
+static void test_reset(void) {
+  FiltersFilter *filter = filters_state_variable_tpt_create(SAMPLE_RATE, FILTERS_OUTPUT_BAND_PASS);
+  filters_set_parameters(filter, 500.0f, 10.0f);
+  float first[BLOCK], second[BLOCK];
+  impulse_response(filter, first, BLOCK);
+  filters_reset(filter);
+  impulse_response(filter, second, BLOCK);
+  for (int i = 0; i < BLOCK; i++) {
+    CHECK(first[i] == second[i], "sample %d after reset: %f, expected %f", i, second[i], first[i]);
+  }
+  filters_destroy(filter);
+}
+
+static void test_invalid_arguments(void) {
+  CHECK(filters_biquad_create(0.0f, FILTERS_TOPOLOGY_DIRECT_FORM_I) == NULL,
+        "biquad created at 0 Hz");
+  CHECK(filters_state_variable_create(-48000.0f) == NULL, "state variable created at -48 kHz");
+  CHECK(filters_state_variable_tpt_create(NAN, FILTERS_OUTPUT_LOW_PASS) == NULL,
+        "TPT created at NaN Hz");
+  CHECK(filters_biquad_create(SAMPLE_RATE, 4) == NULL, "biquad created with topology 4");
+  CHECK(filters_state_variable_tpt_create(SAMPLE_RATE, UINT32_MAX) == NULL,
+        "TPT created with output UINT32_MAX");
+  CHECK(filters_fixed_biquad_create(SAMPLE_RATE, 2, 16) == NULL,
+        "fixed biquad created with format 2");
+  CHECK(filters_fixed_state_variable_create(SAMPLE_RATE, 2, 16) == NULL,
+        "fixed state variable created with format 2");
+  CHECK(filters_sos_create(NULL, 2) == NULL, "SOS created from NULL sections");
+  CHECK(filters_fir_create(NULL, 3) == NULL, "FIR created from NULL taps");
+
+  FiltersBiQuadCoefficients c;
+  CHECK(!filters_design_biquad(SAMPLE_RATE, 1000.0f, 0.707f, NULL), "design into NULL");
+  CHECK(!filters_design_biquad(0.0f, 1000.0f, 0.707f, &c), "design at 0 Hz");
+  CHECK(!filters_design_biquad(SAMPLE_RATE, NAN, 0.707f, &c), "design with a NaN cutoff");
+  CHECK(!filters_design_biquad(SAMPLE_RATE, 1000.0f, 0.0f, &c), "design with Q 0");
+
+  /* Empty designs pass the input through. */
+  FiltersFilter *empty = filters_sos_create(NULL, 0);
+  CHECK(empty != NULL, "empty SOS not created");
+  float dc = step_response(empty, 1);
+  CHECK(dc == 1.0f, "empty SOS output %f", dc);
+  filters_destroy(empty);
+
+  /* NULL handles and blocks are ignored. */
+  float block[4] = {1.0f, 2.0f, 3.0f, 4.0f};
+  filters_process_block(NULL, block, 4);
+  CHECK(!filters_set_parameters(NULL, 1000.0f, 0.707f), "parameters set on NULL");
+  filters_reset(NULL);
+  filters_destroy(NULL);
+  FiltersFilter *filter = filters_fir_lowpass_create();
+  filters_process_block(filter, NULL, 4);
+  filters_destroy(filter);
+}
+
+/* Cutoffs past Nyquist are clamped to 0.49 times the sample rate instead of designing an unstable
+ * filter, and values that aren't positive and finite are rejected. */
+static void test_cutoff_limits(void) {
+  const float sample_rate = 44100.0f;
+  FiltersBiQuadCoefficients clamped, limit;
+  CHECK(filters_design_biquad(sample_rate, 30000.0f, 0.707f, &clamped), "30 kHz design failed");
+  CHECK(filters_design_biquad(sample_rate, 0.49f * sample_rate, 0.707f, &limit),
+        "design at the limit failed");
+  CHECK(clamped.b0 == limit.b0 && clamped.a1 == limit.a1 && clamped.a2 == limit.a2,
+        "30 kHz design not clamped");
+
+  FiltersFilter *filter = filters_biquad_create(sample_rate, FILTERS_TOPOLOGY_DIRECT_FORM_I);
+  CHECK(filters_set_parameters(filter, 30000.0f, 0.707f), "30 kHz cutoff rejected");
+  float block[BLOCK];
+  impulse_response(filter, block, BLOCK);
+  for (int i = 0; i < BLOCK; i++) {
+    CHECK(isfinite(block[i]) && fabsf(block[i]) <= 2.0f, "30 kHz sample %d: %f", i, block[i]);
+  }
+  CHECK(!filters_set_parameters(filter, INFINITY, 0.707f), "infinite cutoff accepted");
+  CHECK(!filters_set_parameters(filter, -1000.0f, 0.707f), "negative cutoff accepted");
+  CHECK(!filters_set_parameters(filter, 1000.0f, NAN), "NaN Q accepted");
+  filters_destroy(filter);
+}
+
+/* Runs every check and returns the number that failed. */
+int filters_c_test(void) {
+  test_biquad_topologies();
+  test_state_variable();
+  test_design_and_sos();
+  test_fir();
+  test_fixed_point();
+  test_reset();
+  test_invalid_arguments();
+  test_cutoff_limits();
+  return failures;
+}
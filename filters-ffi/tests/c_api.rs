@@ -0,0 +1,30 @@
+//! Runs the C program in `tests/c/test_filters.c`, which the build script compiles against the
+//! generated header.
+
+use std::os::raw::c_int;
+
+// Linked for the functions the C program calls, though nothing here uses it from Rust.
+extern crate filters_ffi;
+
+#[link(name = "filters_c_test", kind = "static")]
+extern "C" {
+    fn filters_c_test() -> c_int;
+}
+
+#[test]
+fn c_program_passes() {
+    // The failed checks are printed to stderr.
+    let failures = unsafe { filters_c_test() };
+    assert_eq!(failures, 0, "{} checks failed in the C test", failures);
+}
+
+#[test]
+fn checked_in_header_is_current() {
+    let generated = include_str!(concat!(env!("OUT_DIR"), "/include/filters.h"));
+    let checked_in =
+        std::fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/include/filters.h")).unwrap();
+    assert!(
+        generated == checked_in,
+        "include/filters.h is stale; run `FILTERS_FFI_UPDATE_HEADER=1 cargo build -p filters-ffi`"
+    );
+}
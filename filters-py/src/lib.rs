@@ -0,0 +1,621 @@
+//! Python bindings for the filters, so the implementation the app runs can be checked against
+//! scipy from a notebook.
+//!
+//! The module is `audio_filters`. Its `Filter` class covers every filter type, each built with its
+//! own static method. `Filter.process` filters a writable one-dimensional float64 or float32
+//! buffer in place: a numpy array, an `array.array` or a memoryview. Filters run in double
+//! precision unless built with `precision="float32"`, which is what the app uses. Coefficients,
+//! from `Filter.coefficients` and the `design_*` functions, are dicts in scipy's conventions:
+//! `b` and `a` for transfer functions and `sos` rows of `b0 b1 b2 a0 a1 a2`.
+
+#![warn(clippy::all, rust_2018_idioms)]
+
+use std::sync::{Mutex, PoisonError};
+
+use pyo3::buffer::{Element, PyBuffer};
+use pyo3::exceptions::{PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+use filters::{
+    BiQuadCoefficients, BiQuadFilter, BiQuadTopology, Coefficients, Filter, FilterResponse,
+    FilterType, FirFilter, FirHighPassFilter, FirLowPassFilter, FixedBiQuadFilter, FixedFormat,
+    FixedPointParams, FixedStateVariableFilter, Sample, SosFilter, StateVariableFilter,
+    StateVariableTPTFilter,
+};
+
+const TOPOLOGIES: [(&str, BiQuadTopology); 4] = [
+    ("direct_form_i", BiQuadTopology::DirectFormI),
+    ("direct_form_ii", BiQuadTopology::DirectFormII),
+    (
+        "transposed_direct_form_ii",
+        BiQuadTopology::TransposedDirectFormII,
+    ),
+    ("lattice", BiQuadTopology::Lattice),
+];
+
+const OUTPUTS: [(&str, FilterType); 3] = [
+    ("lowpass", FilterType::LowPass),
+    ("highpass", FilterType::HighPass),
+    ("bandpass", FilterType::BandPass),
+];
+
+const FIXED_FORMATS: [(&str, FixedFormat); 2] =
+    [("q15", FixedFormat::Q15), ("q31", FixedFormat::Q31)];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Precision {
+    Float32,
+    Float64,
+}
+
+const PRECISIONS: [(&str, Precision); 2] = [
+    ("float32", Precision::Float32),
+    ("float64", Precision::Float64),
+];
+
+/// Looks up a string option, listing the valid ones if it isn't one of them.
+fn parse<T: Copy>(what: &str, value: &str, choices: &[(&str, T)]) -> PyResult<T> {
+    choices
+        .iter()
+        .find(|(name, _)| *name == value)
+        .map(|(_, choice)| *choice)
+        .ok_or_else(|| {
+            let names: Vec<&str> = choices.iter().map(|(name, _)| *name).collect();
+            PyValueError::new_err(format!(
+                "unknown {} {:?}, expected one of: {}",
+                what,
+                value,
+                names.join(", ")
+            ))
+        })
+}
+
+fn name_of<T: PartialEq>(value: T, choices: &[(&'static str, T)]) -> &'static str {
+    choices
+        .iter()
+        .find(|(_, choice)| *choice == value)
+        .map_or("", |(name, _)| name)
+}
+
+fn check_sample_rate(sample_rate: f32) -> PyResult<f32> {
+    if sample_rate.is_finite() && sample_rate > 0.0 {
+        Ok(sample_rate)
+    } else {
+        Err(PyValueError::new_err(format!(
+            "sample rate must be positive, got {}",
+            sample_rate
+        )))
+    }
+}
+
+/// How a filter was built, kept so its frequency response can be measured on a fresh copy
+/// without disturbing the one processing.
+#[derive(Debug, Clone)]
+enum Design {
+    BiQuad {
+        sample_rate: f32,
+        topology: BiQuadTopology,
+    },
+    StateVariable {
+        sample_rate: f32,
+    },
+    StateVariableTPT {
+        sample_rate: f32,
+        output: FilterType,
+    },
+    FirLowPass,
+    FirHighPass,
+    FixedBiQuad {
+        sample_rate: f32,
+        params: FixedPointParams,
+    },
+    FixedStateVariable {
+        sample_rate: f32,
+        params: FixedPointParams,
+    },
+    Sos(Vec<BiQuadCoefficients<f64>>),
+    Fir(Vec<f64>),
+}
+
+impl Design {
+    /// The rate the filter was designed for. Filters given as coefficients don't have one.
+    fn sample_rate(&self) -> Option<f32> {
+        match *self {
+            Design::BiQuad { sample_rate, .. }
+            | Design::StateVariable { sample_rate }
+            | Design::StateVariableTPT { sample_rate, .. }
+            | Design::FixedBiQuad { sample_rate, .. }
+            | Design::FixedStateVariable { sample_rate, .. } => Some(sample_rate),
+            Design::FirLowPass | Design::FirHighPass | Design::Sos(_) | Design::Fir(_) => None,
+        }
+    }
+
+    fn build<T: Sample>(&self) -> Box<dyn Filter<T>> {
+        match self {
+            Design::BiQuad {
+                sample_rate,
+                topology,
+            } => Box::new(BiQuadFilter::with_topology(*sample_rate, *topology)),
+            Design::StateVariable { sample_rate } => {
+                Box::new(StateVariableFilter::new(*sample_rate))
+            }
+            Design::StateVariableTPT {
+                sample_rate,
+                output,
+            } => Box::new(StateVariableTPTFilter::with_filter_type(
+                *sample_rate,
+                *output,
+            )),
+            Design::FirLowPass => Box::new(FirLowPassFilter::new(0.0)),
+            Design::FirHighPass => Box::new(FirHighPassFilter::new(0.0)),
+            Design::FixedBiQuad {
+                sample_rate,
+                params,
+            } => Box::new(FixedBiQuadFilter::new(*sample_rate, *params)),
+            Design::FixedStateVariable {
+                sample_rate,
+                params,
+            } => Box::new(FixedStateVariableFilter::new(*sample_rate, *params)),
+            Design::Sos(sections) => Box::new(SosFilter::new(
+                sections.iter().map(|section| section.cast()).collect(),
+            )),
+            Design::Fir(taps) => Box::new(FirFilter::new(
+                taps.iter().map(|&tap| T::cast(tap)).collect(),
+            )),
+        }
+    }
+}
+
+/// The filter processing audio, in the precision it was built with.
+enum Running {
+    Float32(Box<dyn Filter<f32>>),
+    Float64(Box<dyn Filter<f64>>),
+}
+
+impl Running {
+    fn process<U: Sample>(&mut self, block: &mut [U]) {
+        match self {
+            Running::Float32(filter) => process_as(filter.as_mut(), block),
+            Running::Float64(filter) => process_as(filter.as_mut(), block),
+        }
+    }
+
+    fn update_coefficients(&mut self, cutoff_hz: f32, q: f32) {
+        match self {
+            Running::Float32(filter) => filter.update_coefficients(cutoff_hz, q),
+            Running::Float64(filter) => filter.update_coefficients(cutoff_hz, q),
+        }
+    }
+
+    fn reset(&mut self) {
+        match self {
+            Running::Float32(filter) => filter.reset(),
+            Running::Float64(filter) => filter.reset(),
+        }
+    }
+
+    fn coefficients(&self) -> Coefficients<f64> {
+        match self {
+            Running::Float32(filter) => to_f64(filter.coefficients()),
+            Running::Float64(filter) => filter.coefficients(),
+        }
+    }
+}
+
+/// Filters samples of type `U` with a filter running on `T`, rounding to `T` on the way in.
+fn process_as<T: Sample, U: Sample>(filter: &mut dyn Filter<T>, block: &mut [U]) {
+    let mut converted: Vec<T> = block.iter().map(|x| T::cast(x.as_f64())).collect();
+    filter.process_block(&mut converted);
+    for (sample, filtered) in block.iter_mut().zip(converted) {
+        *sample = U::cast(filtered.as_f64());
+    }
+}
+
+fn to_f64<T: Sample>(coefficients: Coefficients<T>) -> Coefficients<f64> {
+    match coefficients {
+        Coefficients::BiQuad(c) => Coefficients::BiQuad(c.cast()),
+        Coefficients::StateVariable { g, k, a1, a2, a3 } => Coefficients::StateVariable {
+            g: g.as_f64(),
+            k: k.as_f64(),
+            a1: a1.as_f64(),
+            a2: a2.as_f64(),
+            a3: a3.as_f64(),
+        },
+        Coefficients::StateVariableTPT { g, h, r2 } => Coefficients::StateVariableTPT {
+            g: g.as_f64(),
+            h: h.as_f64(),
+            r2: r2.as_f64(),
+        },
+        Coefficients::Fir(taps) => Coefficients::Fir(taps.iter().map(|tap| tap.as_f64()).collect()),
+        Coefficients::Cascade(sections) => {
+            Coefficients::Cascade(sections.iter().map(|section| section.cast()).collect())
+        }
+    }
+}
+
+fn coefficients_dict(
+    py: Python<'_>,
+    coefficients: Coefficients<f64>,
+) -> PyResult<Bound<'_, PyDict>> {
+    let dict = PyDict::new(py);
+    match coefficients {
+        Coefficients::BiQuad(c) => {
+            dict.set_item("b", [c.b0, c.b1, c.b2])?;
+            dict.set_item("a", [1.0, c.a1, c.a2])?;
+        }
+        Coefficients::StateVariable { g, k, a1, a2, a3 } => {
+            for (name, value) in [("g", g), ("k", k), ("a1", a1), ("a2", a2), ("a3", a3)] {
+                dict.set_item(name, value)?;
+            }
+        }
+        Coefficients::StateVariableTPT { g, h, r2 } => {
+            for (name, value) in [("g", g), ("h", h), ("r2", r2)] {
+                dict.set_item(name, value)?;
+            }
+        }
+        Coefficients::Fir(taps) => {
+            dict.set_item("b", taps)?;
+            dict.set_item("a", [1.0])?;
+        }
+        Coefficients::Cascade(sections) => {
+            let rows: Vec<[f64; 6]> = sections
+                .iter()
+                .map(|c| [c.b0, c.b1, c.b2, 1.0, c.a1, c.a2])
+                .collect();
+            dict.set_item("sos", rows)?;
+        }
+    }
+    Ok(dict)
+}
+
+/// Any of the filters, processing buffers in place.
+#[pyclass(name = "Filter", module = "audio_filters")]
+pub struct PyFilter {
+    design: Design,
+    precision: Precision,
+    /// The last cutoff and Q set, for building the copy a response is measured on.
+    parameters: Option<(f32, f32)>,
+    // Only for `Sync`: Python holds `&mut self` while using it, so it is never contended.
+    running: Mutex<Running>,
+}
+
+impl PyFilter {
+    fn new(design: Design, precision: &str, parameters: Option<(f32, f32)>) -> PyResult<Self> {
+        let precision = parse("precision", precision, &PRECISIONS)?;
+        let mut running = match precision {
+            Precision::Float32 => Running::Float32(design.build()),
+            Precision::Float64 => Running::Float64(design.build()),
+        };
+        if let Some((cutoff_hz, q)) = parameters {
+            running.update_coefficients(cutoff_hz, q);
+        }
+        Ok(Self {
+            design,
+            precision,
+            parameters,
+            running: Mutex::new(running),
+        })
+    }
+
+    fn running(&mut self) -> &mut Running {
+        self.running
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// A new filter with the same design and parameters, and no state.
+    fn fresh<T: Sample>(&self) -> Box<dyn Filter<T>> {
+        let mut filter = self.design.build();
+        if let Some((cutoff_hz, q)) = self.parameters {
+            filter.update_coefficients(cutoff_hz, q);
+        }
+        filter
+    }
+
+    fn process_buffer<T: Element + Sample>(
+        &mut self,
+        py: Python<'_>,
+        buffer: &PyBuffer<T>,
+    ) -> PyResult<()> {
+        if buffer.dimensions() != 1 {
+            return Err(PyValueError::new_err(format!(
+                "expected a one-dimensional buffer, got {} dimensions",
+                buffer.dimensions()
+            )));
+        }
+        if buffer.readonly() {
+            return Err(PyValueError::new_err(
+                "the buffer is read-only, process a copy",
+            ));
+        }
+        let mut block = buffer.to_vec(py)?;
+        self.running().process(&mut block);
+        buffer.copy_from_slice(py, &block)
+    }
+}
+
+#[pymethods]
+impl PyFilter {
+    /// RBJ low-pass biquad. `topology` is one of "direct_form_i", "direct_form_ii",
+    /// "transposed_direct_form_ii" and "lattice".
+    #[staticmethod]
+    #[pyo3(signature = (sample_rate, cutoff_hz, q, topology = "direct_form_i", precision = "float64"))]
+    fn biquad(
+        sample_rate: f32,
+        cutoff_hz: f32,
+        q: f32,
+        topology: &str,
+        precision: &str,
+    ) -> PyResult<Self> {
+        let design = Design::BiQuad {
+            sample_rate: check_sample_rate(sample_rate)?,
+            topology: parse("topology", topology, &TOPOLOGIES)?,
+        };
+        Self::new(design, precision, Some((cutoff_hz, q)))
+    }
+
+    /// Andrew Simper's low-pass state variable filter.
+    #[staticmethod]
+    #[pyo3(signature = (sample_rate, cutoff_hz, q, precision = "float64"))]
+    fn state_variable(sample_rate: f32, cutoff_hz: f32, q: f32, precision: &str) -> PyResult<Self> {
+        let design = Design::StateVariable {
+            sample_rate: check_sample_rate(sample_rate)?,
+        };
+        Self::new(design, precision, Some((cutoff_hz, q)))
+    }
+
+    /// Topology-preserving transform state variable filter. `output` is one of "lowpass",
+    /// "highpass" and "bandpass".
+    #[staticmethod]
+    #[pyo3(signature = (sample_rate, cutoff_hz, q, output = "lowpass", precision = "float64"))]
+    fn state_variable_tpt(
+        sample_rate: f32,
+        cutoff_hz: f32,
+        q: f32,
+        output: &str,
+        precision: &str,
+    ) -> PyResult<Self> {
+        let design = Design::StateVariableTPT {
+            sample_rate: check_sample_rate(sample_rate)?,
+            output: parse("output", output, &OUTPUTS)?,
+        };
+        Self::new(design, precision, Some((cutoff_hz, q)))
+    }
+
+    /// Two-tap FIR low-pass, y[n] = x[n] + x[n-1].
+    #[staticmethod]
+    #[pyo3(signature = (precision = "float64"))]
+    fn fir_lowpass(precision: &str) -> PyResult<Self> {
+        Self::new(Design::FirLowPass, precision, None)
+    }
+
+    /// Two-tap FIR high-pass, y[n] = x[n] - x[n-1].
+    #[staticmethod]
+    #[pyo3(signature = (precision = "float64"))]
+    fn fir_highpass(precision: &str) -> PyResult<Self> {
+        Self::new(Design::FirHighPass, precision, None)
+    }
+
+    /// Fixed-point direct form I biquad with the design of `Filter.biquad`. `format` is "q15" or
+    /// "q31"; `coefficient_bits` is clamped to 8..=32.
+    #[staticmethod]
+    #[pyo3(signature = (sample_rate, cutoff_hz, q, format = "q15", coefficient_bits = 16, precision = "float64"))]
+    fn fixed_biquad(
+        sample_rate: f32,
+        cutoff_hz: f32,
+        q: f32,
+        format: &str,
+        coefficient_bits: u32,
+        precision: &str,
+    ) -> PyResult<Self> {
+        let design = Design::FixedBiQuad {
+            sample_rate: check_sample_rate(sample_rate)?,
+            params: FixedPointParams {
+                format: parse("format", format, &FIXED_FORMATS)?,
+                coefficient_bits,
+            },
+        };
+        Self::new(design, precision, Some((cutoff_hz, q)))
+    }
+
+    /// Fixed-point state variable filter with the design of `Filter.state_variable`.
+    #[staticmethod]
+    #[pyo3(signature = (sample_rate, cutoff_hz, q, format = "q15", coefficient_bits = 16, precision = "float64"))]
+    fn fixed_state_variable(
+        sample_rate: f32,
+        cutoff_hz: f32,
+        q: f32,
+        format: &str,
+        coefficient_bits: u32,
+        precision: &str,
+    ) -> PyResult<Self> {
+        let design = Design::FixedStateVariable {
+            sample_rate: check_sample_rate(sample_rate)?,
+            params: FixedPointParams {
+                format: parse("format", format, &FIXED_FORMATS)?,
+                coefficient_bits,
+            },
+        };
+        Self::new(design, precision, Some((cutoff_hz, q)))
+    }
+
+    /// Cascade of second-order sections in scipy's `sos` layout, one `b0 b1 b2 a0 a1 a2` row per
+    /// section, run in transposed direct form II.
+    #[staticmethod]
+    #[pyo3(signature = (sections, precision = "float64"))]
+    fn sos(sections: Vec<Vec<f64>>, precision: &str) -> PyResult<Self> {
+        let sections = sections
+            .iter()
+            .enumerate()
+            .map(|(index, row)| match row[..] {
+                [b0, b1, b2, a0, a1, a2] if a0 != 0.0 => Ok(BiQuadCoefficients {
+                    b0: b0 / a0,
+                    b1: b1 / a0,
+                    b2: b2 / a0,
+                    a1: a1 / a0,
+                    a2: a2 / a0,
+                }),
+                [_, _, _, _, _, _] => Err(PyValueError::new_err(format!(
+                    "section {} has a0 = 0",
+                    index
+                ))),
+                _ => Err(PyValueError::new_err(format!(
+                    "section {} has {} coefficients, expected 6",
+                    index,
+                    row.len()
+                ))),
+            })
+            .collect::<PyResult<_>>()?;
+        Self::new(Design::Sos(sections), precision, None)
+    }
+
+    /// FIR filter with `taps[k]` weighting x[n - k].
+    #[staticmethod]
+    #[pyo3(signature = (taps, precision = "float64"))]
+    fn fir(taps: Vec<f64>, precision: &str) -> PyResult<Self> {
+        Self::new(Design::Fir(taps), precision, None)
+    }
+
+    /// The rate the filter was designed for, or None for filters given as coefficients.
+    #[getter]
+    fn sample_rate(&self) -> Option<f32> {
+        self.design.sample_rate()
+    }
+
+    #[getter]
+    fn precision(&self) -> &'static str {
+        name_of(self.precision, &PRECISIONS)
+    }
+
+    /// Sets the cutoff in Hz and the resonance Q, keeping the state. Filters given as
+    /// coefficients and the two-tap FIR filters ignore it.
+    fn set_parameters(&mut self, cutoff_hz: f32, q: f32) {
+        self.parameters = Some((cutoff_hz, q));
+        self.running().update_coefficients(cutoff_hz, q);
+    }
+
+    /// Filters a writable one-dimensional float64 or float32 buffer in place, continuing from
+    /// the state the last call left.
+    fn process(&mut self, py: Python<'_>, samples: &Bound<'_, PyAny>) -> PyResult<()> {
+        if let Ok(buffer) = PyBuffer::<f64>::get(samples) {
+            self.process_buffer(py, &buffer)
+        } else if let Ok(buffer) = PyBuffer::<f32>::get(samples) {
+            self.process_buffer(py, &buffer)
+        } else {
+            Err(PyTypeError::new_err(
+                "expected a buffer of float64 or float32 samples",
+            ))
+        }
+    }
+
+    /// Clears the state as if the filter had only processed silence, keeping its parameters.
+    fn reset(&mut self) {
+        self.running().reset();
+    }
+
+    /// The coefficients the filter is running with, rounded to its precision.
+    fn coefficients<'py>(&mut self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let coefficients = self.running().coefficients();
+        coefficients_dict(py, coefficients)
+    }
+
+    /// Impulse and frequency response over one second, with frequency bins 1 Hz apart, measured
+    /// on a fresh copy of the filter. `sample_rate` is needed for filters given as coefficients
+    /// and must match the others'. Returns a dict of lists: `frequency_hz`, `magnitude_db`,
+    /// `phase_deg`, `group_delay_samples` (NaN where the magnitude is too small) and `impulse`.
+    #[pyo3(signature = (sample_rate = None))]
+    fn frequency_response<'py>(
+        &self,
+        py: Python<'py>,
+        sample_rate: Option<f32>,
+    ) -> PyResult<Bound<'py, PyDict>> {
+        let sample_rate = match (self.design.sample_rate(), sample_rate) {
+            (Some(own), None) => own,
+            (Some(own), Some(given)) if given == own => own,
+            (Some(own), Some(given)) => {
+                return Err(PyValueError::new_err(format!(
+                    "the filter was designed for {} Hz, not {} Hz",
+                    own, given
+                )))
+            }
+            (None, Some(given)) => check_sample_rate(given)?,
+            (None, None) => {
+                return Err(PyValueError::new_err(
+                    "filters given as coefficients need a sample rate",
+                ))
+            }
+        };
+        let length = sample_rate.round() as usize;
+        if length < 2 {
+            return Err(PyValueError::new_err(
+                "sample rate must be at least 2 Hz to measure a response",
+            ));
+        }
+        let response = match self.precision {
+            Precision::Float32 => FilterResponse::compute(self.fresh::<f32>(), length),
+            Precision::Float64 => FilterResponse::compute(self.fresh::<f64>(), length),
+        };
+
+        let bin_hz = sample_rate as f64 / length as f64;
+        let frequency_hz: Vec<f64> = (0..response.magnitude_db.len())
+            .map(|bin| bin as f64 * bin_hz)
+            .collect();
+        let dict = PyDict::new(py);
+        dict.set_item("frequency_hz", frequency_hz)?;
+        dict.set_item("magnitude_db", response.magnitude_db)?;
+        dict.set_item("phase_deg", response.phase_deg)?;
+        dict.set_item("group_delay_samples", response.group_delay_samples)?;
+        dict.set_item("impulse", response.impulse)?;
+        Ok(dict)
+    }
+}
+
+/// Normalized coefficients of the RBJ low-pass biquad, as `{"b": [...], "a": [1, a1, a2]}`.
+#[pyfunction]
+fn design_biquad(
+    py: Python<'_>,
+    sample_rate: f32,
+    cutoff_hz: f32,
+    q: f32,
+) -> PyResult<Bound<'_, PyDict>> {
+    let mut design = BiQuadFilter::<f64>::new(check_sample_rate(sample_rate)?);
+    design.update_coefficients(cutoff_hz, q);
+    coefficients_dict(py, design.coefficients())
+}
+
+/// Coefficients of the state variable filter, in Andrew Simper's notation: `g`, `k`, `a1`, `a2`
+/// and `a3`.
+#[pyfunction]
+fn design_state_variable(
+    py: Python<'_>,
+    sample_rate: f32,
+    cutoff_hz: f32,
+    q: f32,
+) -> PyResult<Bound<'_, PyDict>> {
+    let mut design = StateVariableFilter::<f64>::new(check_sample_rate(sample_rate)?);
+    design.update_coefficients(cutoff_hz, q);
+    coefficients_dict(py, design.coefficients())
+}
+
+/// Coefficients of the TPT state variable filter, the same for every output: `g`, `h` and `r2`.
+#[pyfunction]
+fn design_state_variable_tpt(
+    py: Python<'_>,
+    sample_rate: f32,
+    cutoff_hz: f32,
+    q: f32,
+) -> PyResult<Bound<'_, PyDict>> {
+    let mut design = StateVariableTPTFilter::<f64>::new(check_sample_rate(sample_rate)?);
+    design.update_coefficients(cutoff_hz, q);
+    coefficients_dict(py, design.coefficients())
+}
+
+#[pymodule]
+pub fn audio_filters(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyFilter>()?;
+    module.add_function(wrap_pyfunction!(design_biquad, module)?)?;
+    module.add_function(wrap_pyfunction!(design_state_variable, module)?)?;
+    module.add_function(wrap_pyfunction!(design_state_variable_tpt, module)?)?;
+    Ok(())
+}
@@ -0,0 +1,165 @@
+//! Runs the module from Python code in an embedded interpreter, with `array.array` standing in
+//! for numpy arrays: both go through the buffer protocol.
+
+use std::ffi::CStr;
+
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+/// Runs `code` with the module imported as `audio_filters`. Python assertions fail the test.
+fn run(code: &CStr) {
+    Python::attach(|py| {
+        let module = pyo3::wrap_pymodule!(audio_filters::audio_filters)(py);
+        let globals = PyDict::new(py);
+        globals.set_item("audio_filters", module).unwrap();
+        if let Err(err) = py.run(code, Some(&globals), None) {
+            err.display(py);
+            panic!("Python code failed: {}", err);
+        }
+    });
+}
+
+#[test]
+fn biquad_matches_its_difference_equation() {
+    // What scipy.signal.lfilter(c["b"], c["a"], x) computes.
+    run(c"
+from array import array
+
+c = audio_filters.design_biquad(48000, 1000, 0.707)
+b, a = c['b'], c['a']
+assert a[0] == 1.0
+
+x = array('d', [1.0] + [0.0] * 63)
+expected, x1, x2, y1, y2 = [], 0.0, 0.0, 0.0, 0.0
+for x0 in x:
+    y0 = b[0] * x0 + b[1] * x1 + b[2] * x2 - a[1] * y1 - a[2] * y2
+    expected.append(y0)
+    x1, x2, y1, y2 = x0, x1, y0, y1
+
+f = audio_filters.Filter.biquad(48000, 1000, 0.707)
+assert f.coefficients() == c
+f.process(x)
+assert all(abs(y - e) < 1e-12 for y, e in zip(x, expected)), (list(x), expected)
+
+# Blocks continue from the state the previous one left.
+f.reset()
+first, second = array('d', [1.0] + [0.0] * 31), array('d', [0.0] * 32)
+f.process(first)
+f.process(second)
+assert list(first) + list(second) == list(x)
+");
+}
+
+#[test]
+fn every_filter_type_processes_both_buffer_types() {
+    run(c"
+from array import array
+
+Filter = audio_filters.Filter
+filters = [
+    Filter.biquad(48000, 1000, 0.707, topology='lattice'),
+    Filter.state_variable(48000, 1000, 0.707),
+    Filter.state_variable_tpt(48000, 1000, 0.707, output='bandpass'),
+    Filter.fir_lowpass(),
+    Filter.fir_highpass(),
+    Filter.fixed_biquad(48000, 1000, 0.707, format='q31', coefficient_bits=24),
+    Filter.fixed_state_variable(48000, 1000, 0.707),
+    Filter.sos([[0.5, 0.0, 0.0, 1.0, -0.5, 0.0], [1.0, 1.0, 0.0, 2.0, 0.0, 0.0]]),
+    Filter.fir([0.25, 0.5, 0.25]),
+]
+for f in filters:
+    for typecode in 'df':
+        block = array(typecode, [1.0] * 256)
+        f.reset()
+        f.process(block)
+        assert all(abs(y) < 10.0 for y in block), (f.coefficients(), list(block))
+        assert block[0] != 1.0 or block[255] != 1.0, f.coefficients()
+
+fir = Filter.fir([0.25, 0.5, 0.25])
+block = array('d', [1.0, 0.0, 0.0, 0.0])
+fir.process(memoryview(block))
+assert list(block) == [0.25, 0.5, 0.25, 0.0]
+assert fir.coefficients() == {'b': [0.25, 0.5, 0.25], 'a': [1.0]}
+
+# Rows are normalized by a0, as scipy's sosfilt does.
+sos = Filter.sos([[1.0, 0.0, 0.0, 2.0, 0.0, 0.0]])
+assert sos.coefficients() == {'sos': [[0.5, 0.0, 0.0, 1.0, 0.0, 0.0]]}
+");
+}
+
+#[test]
+fn float32_precision_matches_the_app() {
+    run(c"
+from array import array
+
+single = audio_filters.Filter.state_variable(48000, 1000, 0.707, precision='float32')
+double = audio_filters.Filter.state_variable(48000, 1000, 0.707)
+assert single.precision == 'float32' and double.precision == 'float64'
+assert single.sample_rate == 48000.0
+
+x, y = array('d', [1.0] + [0.0] * 99), array('d', [1.0] + [0.0] * 99)
+single.process(x)
+double.process(y)
+assert x != y
+assert all(abs(a - b) < 1e-5 for a, b in zip(x, y))
+assert all(a == array('f', [a])[0] for a in x)
+");
+}
+
+#[test]
+fn frequency_response_is_measured_on_a_fresh_filter() {
+    run(c"
+import math
+from array import array
+
+f = audio_filters.Filter.biquad(1000, 100, 0.707)
+f.process(array('d', [1.0] * 10))
+response = f.frequency_response()
+assert len(response['frequency_hz']) == 501
+assert response['frequency_hz'][100] == 100.0
+assert abs(response['magnitude_db'][0]) < 1e-3
+assert abs(response['magnitude_db'][100] + 3.01) < 0.05
+assert abs(response['impulse'][0] - f.coefficients()['b'][0]) < 1e-7
+
+# Moving the cutoff moves the response.
+f.set_parameters(200, 0.707)
+assert abs(f.frequency_response()['magnitude_db'][200] + 3.01) < 0.05
+
+fir = audio_filters.Filter.fir_lowpass()
+response = fir.frequency_response(1000)
+assert abs(response['magnitude_db'][0] - 20 * math.log10(2)) < 1e-3
+assert abs(response['phase_deg'][100] + 18.0) < 1e-3
+assert abs(response['group_delay_samples'][100] - 0.5) < 1e-6
+");
+}
+
+#[test]
+fn invalid_arguments_raise() {
+    run(c"
+from array import array
+
+Filter = audio_filters.Filter
+
+def raises(error, call):
+    try:
+        call()
+    except error as err:
+        return str(err)
+    raise AssertionError('no ' + error.__name__)
+
+assert 'direct_form_i' in raises(ValueError, lambda: Filter.biquad(48000, 1000, 0.7, topology='df3'))
+raises(ValueError, lambda: Filter.state_variable(0, 1000, 0.7))
+raises(ValueError, lambda: Filter.fir([1.0], precision='float16'))
+raises(ValueError, lambda: Filter.sos([[1.0, 0.0, 0.0, 1.0, 0.0]]))
+raises(ValueError, lambda: Filter.sos([[1.0, 0.0, 0.0, 0.0, 0.0, 0.0]]))
+raises(ValueError, lambda: Filter.fir_lowpass().frequency_response())
+raises(ValueError, lambda: Filter.biquad(48000, 1000, 0.7).frequency_response(44100))
+
+f = Filter.fir_lowpass()
+raises(TypeError, lambda: f.process(array('i', [1, 2, 3])))
+raises(TypeError, lambda: f.process([1.0, 2.0]))
+raises(TypeError, lambda: f.process(bytearray(16)))
+raises(ValueError, lambda: f.process(memoryview(array('d', [0.0] * 4)).cast('B').cast('d', [2, 2])))
+raises(ValueError, lambda: f.process(memoryview(array('d', [0.0] * 4)).toreadonly()))
+");
+}
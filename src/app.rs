@@ -1,46 +1,206 @@
-use egui::epaint::*;
-use egui::{pos2, vec2, Frame, Pos2, Rect};
-use egui_plot::{Line, Plot, PlotPoints};
-use std::sync::mpsc::Sender;
-use std::sync::{Arc, Mutex};
+use egui_plot::{Legend, Line, MarkerShape, Plot, PlotPoints, Points};
+use std::sync::mpsc::{Receiver, Sender};
+use std::sync::Arc;
 
+use crate::audio::{AudioEvent, DeviceState, OutputDevice, StreamSettings};
+use crate::export::{export_coefficients, ExportFormat, ExportOptions, Precision};
+use crate::fixed::{noise_floor_db, MAX_COEFFICIENT_BITS, MIN_COEFFICIENT_BITS};
+use crate::quantization::{self, QuantizationAnalysis};
+use crate::registry::{ParamId, ParamScale};
+use crate::response::{
+    encode_png, plot_points, response_csv, response_svg, ResponseExportOptions, ResponseFileFormat,
+    ResponseQuantity,
+};
 use crate::{
-    BiQuadFilter, Filter, FirHighPassFilter, FirLowPassFilter, SelectedFilter, StateVariableFilter,
-    StateVariableTPTFilter,
+    AudioClip, BiQuadTopology, Filter, FilterParams, FilterResponse, FixedFormat, FixedPointParams,
+    ImportedDesign, Preset, PresetBank, PresetFile, SelectedFilter, SelectedSource, StereoMode,
+    FILTERS,
 };
 
+#[allow(clippy::enum_variant_names)]
 pub enum AudioCommand {
     SetVolume(f32),
-    SetFilterFreq(f32),
-    SetResonance(f32),
+    /// Sets a parameter of every channel.
+    SetParam(ParamId, f32),
     SetSelectedFilter(SelectedFilter),
+    SetChannelParam(usize, ParamId, f32),
+    SetChannelSelectedFilter(usize, SelectedFilter),
+    SetTopology(BiQuadTopology),
+    SetChannelTopology(usize, BiQuadTopology),
+    SetFixedPoint(FixedPointParams),
+    SetChannelFixedPoint(usize, FixedPointParams),
+    SetStereoMode(StereoMode),
+    /// Clears the filters' state without touching their settings.
+    ResetFilterState,
+    SetImportedDesign(Arc<ImportedDesign>),
+    SetSource(SelectedSource),
+    SetClip(Arc<AudioClip>),
+    /// Closes the output stream and reopens it with these settings.
+    SetStreamSettings(StreamSettings),
+}
+
+/// How the response plots are drawn.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct PlotSettings {
+    pub log_frequency: bool,
+    pub show_phase: bool,
+    pub show_group_delay: bool,
+    pub show_impulse: bool,
+    /// Plot every design computed in both f32 and f64.
+    pub compare_precision: bool,
+    /// Plot fixed-point filters next to their float reference.
+    pub compare_fixed_point: bool,
+}
+
+impl Default for PlotSettings {
+    fn default() -> Self {
+        Self {
+            log_frequency: false,
+            show_phase: true,
+            show_group_delay: false,
+            show_impulse: false,
+            compare_precision: false,
+            compare_fixed_point: true,
+        }
+    }
 }
 
+impl PlotSettings {
+    pub fn is_shown(&self, quantity: ResponseQuantity) -> bool {
+        match quantity {
+            ResponseQuantity::Magnitude => true,
+            ResponseQuantity::Phase => self.show_phase,
+            ResponseQuantity::GroupDelay => self.show_group_delay,
+            ResponseQuantity::Impulse => self.show_impulse,
+        }
+    }
+}
+
+/// Fields that aren't skipped are saved between sessions through eframe's storage.
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
 pub struct AudioFilterApp {
     pub vol: f32,
-    pub freq_hz: f32,
-    pub resonance_q: f32,
+    /// Left/mid (index 0) and right/side (index 1) parameters. Identical while the channels are
+    /// linked in L/R mode.
+    pub channel_params: [FilterParams; 2],
+    pub channels_linked: bool,
+    pub stereo_mode: StereoMode,
+    #[serde(skip)]
     pub audio_tx: Option<Sender<crate::app::AudioCommand>>,
-    // The magnitude response.
-    pub filter_freq_res: Option<Vec<f32>>,
-    pub phase_res: Option<Vec<f32>>,
+    #[serde(skip)]
+    pub audio_rx: Option<Receiver<AudioEvent>>,
+    #[serde(skip)]
+    pub stream_settings: StreamSettings,
+    #[serde(skip)]
+    pub device_state: DeviceState,
+    #[serde(skip)]
+    pub audio_error: Option<String>,
+    // Times the audio thread reset a filter whose output blew up.
+    #[serde(skip)]
+    pub filter_recoveries: u64,
+    // Devices of the selected host, listed on demand because scanning can be slow.
+    #[serde(skip)]
+    pub output_devices: Option<Vec<OutputDevice>>,
+    // One response per independently tuned channel, plus the imported design when compared.
+    #[serde(skip)]
+    pub responses: Vec<(String, FilterResponse)>,
+    // Error of each fixed-point channel against its float reference, in dBFS.
+    #[serde(skip)]
+    pub noise_floors: Vec<(String, f32)>,
+    pub quantization_bits: u32,
+    #[serde(skip)]
+    pub quantization_channel: usize,
+    #[serde(skip)]
+    pub quantization: Option<QuantizationAnalysis>,
+    #[serde(skip)]
     pub coefficients_changed: bool,
+    #[serde(skip)]
     pub selected_filter_changed: bool,
-    pub selected_filter: SelectedFilter,
+    pub selected_source: SelectedSource,
+    pub clip_path: String,
+    #[serde(skip)]
+    pub clip_status: Option<String>,
+    pub plot_settings: PlotSettings,
+    pub presets: PresetBank,
+    #[serde(skip)]
+    pub preset_name: String,
+    // The preset being renamed and its new name.
+    #[serde(skip)]
+    pub renaming: Option<(String, String)>,
+    pub preset_file_path: String,
+    #[serde(skip)]
+    pub preset_file_status: Option<String>,
+    pub export_options: ExportOptions,
+    pub export_path: String,
+    // Which channel's (or M/S path's) filter gets exported.
+    #[serde(skip)]
+    pub export_channel: usize,
+    #[serde(skip)]
+    pub export_status: Option<String>,
+    pub imported_path: String,
+    #[serde(skip)]
+    pub imported_design: Option<Arc<ImportedDesign>>,
+    #[serde(skip)]
+    pub imported_status: Option<String>,
+    /// Plot the imported design next to the channel responses.
+    pub compare_imported: bool,
+    pub response_export_options: ResponseExportOptions,
+    pub response_export_path: String,
+    #[serde(skip)]
+    pub response_export_status: Option<String>,
+    // Where each plot was last drawn on screen, for PNG captures.
+    #[serde(skip)]
+    pub plot_rects: Vec<(ResponseQuantity, egui::Rect)>,
+    // A PNG capture waiting for its screenshot.
+    #[serde(skip)]
+    pub pending_capture: Option<(ResponseQuantity, std::path::PathBuf)>,
 }
 
 impl Default for AudioFilterApp {
     fn default() -> Self {
         Self {
             vol: 0.3,
-            freq_hz: 1000.0,
-            resonance_q: 0.707,
+            channel_params: [FilterParams::default(); 2],
+            channels_linked: true,
+            stereo_mode: StereoMode::LeftRight,
             audio_tx: None,
-            filter_freq_res: None,
-            phase_res: None,
+            audio_rx: None,
+            stream_settings: StreamSettings::default(),
+            device_state: DeviceState::Closed,
+            audio_error: None,
+            filter_recoveries: 0,
+            output_devices: None,
+            responses: Vec::new(),
+            noise_floors: Vec::new(),
+            quantization_bits: 16,
+            quantization_channel: 0,
+            quantization: None,
             coefficients_changed: false,
             selected_filter_changed: false,
-            selected_filter: SelectedFilter::StateVariable,
+            selected_source: SelectedSource::MonoNoise,
+            clip_path: String::new(),
+            clip_status: None,
+            plot_settings: PlotSettings::default(),
+            presets: PresetBank::default(),
+            preset_name: String::new(),
+            renaming: None,
+            preset_file_path: String::new(),
+            preset_file_status: None,
+            export_options: ExportOptions::default(),
+            export_path: String::new(),
+            export_channel: 0,
+            export_status: None,
+            imported_path: String::new(),
+            imported_design: None,
+            imported_status: None,
+            compare_imported: true,
+            response_export_options: ResponseExportOptions::default(),
+            response_export_path: "response".to_string(),
+            response_export_status: None,
+            plot_rects: Vec::new(),
+            pending_capture: None,
         }
     }
 }
@@ -54,198 +214,1127 @@ impl AudioFilterApp {
     pub fn new() -> Self {
         Default::default()
     }
-}
 
-impl eframe::App for AudioFilterApp {
-    fn save(&mut self, _storage: &mut dyn eframe::Storage) {}
+    /// Restores the previous session from eframe's storage, if there is one.
+    pub fn load(storage: Option<&dyn eframe::Storage>) -> Self {
+        storage
+            .and_then(|storage| eframe::get_value(storage, eframe::APP_KEY))
+            .unwrap_or_default()
+    }
 
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        let sample_rate = 44100;
+    /// Pushes the complete app state to the audio thread, e.g. after restoring a session.
+    pub fn sync_audio(&mut self) {
+        self.send(AudioCommand::SetVolume(self.vol));
+        self.send(AudioCommand::SetSource(self.selected_source));
+        if self.selected_source == SelectedSource::File && !self.clip_path.is_empty() {
+            self.load_clip();
+        }
+        if !self.imported_path.is_empty() {
+            self.import_design();
+        }
+        self.apply_filter_settings();
+    }
 
-        if *(&self.filter_freq_res.is_none())
-            || self.coefficients_changed
-            || self.selected_filter_changed
-        {
-            use realfft::num_complex::ComplexFloat;
-            use realfft::RealFftPlanner;
-
-            let mut impulse: Vec<f32> = (0..sample_rate).map(|_| 0.0).collect();
-            impulse[0] = 1.0;
-
-            let mut impulse_response = match self.selected_filter {
-                SelectedFilter::BiQuad => {
-                    let mut svf = BiQuadFilter::new(sample_rate as f32);
-                    svf.update_coefficients(self.freq_hz, self.resonance_q);
-                    impulse
-                        .iter()
-                        .map(|sample| svf.render(*sample))
-                        .collect::<Vec<f32>>()
-                }
-                SelectedFilter::FirLowPass => {
-                    let mut svf = FirLowPassFilter::new(sample_rate as f32);
-                    svf.update_coefficients(self.freq_hz, self.resonance_q);
-                    impulse
-                        .iter()
-                        .map(|sample| svf.render(*sample))
-                        .collect::<Vec<f32>>()
-                }
-                SelectedFilter::FirHighPass => {
-                    let mut svf = FirHighPassFilter::new(sample_rate as f32);
-                    svf.update_coefficients(self.freq_hz, self.resonance_q);
-                    impulse
-                        .iter()
-                        .map(|sample| svf.render(*sample))
-                        .collect::<Vec<f32>>()
-                }
-                SelectedFilter::StateVariable => {
-                    let mut svf = StateVariableFilter::new(sample_rate as f32);
-                    svf.update_coefficients(self.freq_hz, self.resonance_q);
-                    impulse
-                        .iter()
-                        .map(|sample| svf.render(*sample))
-                        .collect::<Vec<f32>>()
-                }
-                SelectedFilter::StateVariableTPT => {
-                    let mut svf = StateVariableTPTFilter::new(sample_rate as f32);
-                    svf.update_coefficients(self.freq_hz, self.resonance_q);
-                    impulse
-                        .iter()
-                        .map(|sample| svf.render(*sample))
-                        .collect::<Vec<f32>>()
+    /// Sends the stereo mode and all channel parameters to the audio thread.
+    fn apply_filter_settings(&mut self) {
+        self.send(AudioCommand::SetStereoMode(self.stereo_mode));
+        if self.channels_independent() {
+            for (channel, params) in self.channel_params.iter().enumerate() {
+                self.send(AudioCommand::SetChannelSelectedFilter(
+                    channel,
+                    params.filter,
+                ));
+                for id in ParamId::ALL {
+                    self.send(AudioCommand::SetChannelParam(channel, id, params.param(id)));
+                }
+                self.send(AudioCommand::SetChannelTopology(channel, params.topology));
+                self.send(AudioCommand::SetChannelFixedPoint(channel, params.fixed));
+            }
+        } else {
+            self.link_channels();
+        }
+        self.coefficients_changed = true;
+    }
+
+    fn current_preset(&self, name: String) -> Preset {
+        Preset {
+            name,
+            channel_params: self.channel_params,
+            channels_linked: self.channels_linked,
+            stereo_mode: self.stereo_mode,
+        }
+    }
+
+    fn recall_preset(&mut self, preset: Preset) {
+        self.channel_params = preset.channel_params;
+        self.channels_linked = preset.channels_linked;
+        self.stereo_mode = preset.stereo_mode;
+        self.apply_filter_settings();
+    }
+
+    /// Writes the current settings to the preset file path, named after the file.
+    fn export_preset(&mut self) {
+        let path = std::path::Path::new(&self.preset_file_path);
+        let name = path.file_stem().map_or("Preset".to_string(), |stem| {
+            stem.to_string_lossy().into_owned()
+        });
+        let file = PresetFile::new(self.current_preset(name), self.sample_rate() as u32);
+
+        self.preset_file_status = Some(match file.save(path) {
+            Ok(()) => format!("Exported to {}", path.display()),
+            Err(err) => format!("Failed to export: {}", err),
+        });
+    }
+
+    /// Reads a preset file, adds it to the presets and recalls it.
+    fn import_preset(&mut self) {
+        match PresetFile::load(&self.preset_file_path) {
+            Ok(file) => {
+                let mut status = format!("Imported \"{}\"", file.preset.name);
+                if file.sample_rate as usize != self.sample_rate() {
+                    status += &format!(
+                        " (designed at {} Hz, running at {} Hz)",
+                        file.sample_rate,
+                        self.sample_rate()
+                    );
+                }
+                self.preset_file_status = Some(status);
+                self.presets.save(file.preset.clone());
+                self.recall_preset(file.preset);
+            }
+            Err(err) => self.preset_file_status = Some(format!("Failed to import: {}", err)),
+        }
+    }
+
+    /// Builds the filter for `params` at the stream's sample rate, including imported designs.
+    fn create_filter(&self, params: &FilterParams) -> Box<dyn Filter> {
+        params.create_filter_with(self.sample_rate() as f32, self.imported_design.as_deref())
+    }
+
+    fn compute_responses(&mut self) {
+        let sample_rate = self.sample_rate();
+        let imported = self.imported_design.as_deref();
+        let names = if self.channels_independent() {
+            self.channel_names().to_vec()
+        } else {
+            vec!["All channels"]
+        };
+
+        // Each design as f32, which is what the audio path runs, and optionally as f64 too.
+        let compare_precision = self.plot_settings.compare_precision;
+        let mut responses = Vec::new();
+        let mut noise_floors = Vec::new();
+        let mut add =
+            |name: &str, f32_filter: Box<dyn Filter>, f64_filter: Box<dyn Filter<f64>>| {
+                if compare_precision {
+                    let f32_response = FilterResponse::compute(f32_filter, sample_rate);
+                    let f64_response = FilterResponse::compute(f64_filter, sample_rate);
+                    responses.push((format!("{} (f32)", name), f32_response));
+                    responses.push((format!("{} (f64)", name), f64_response));
+                } else {
+                    let response = FilterResponse::compute(f32_filter, sample_rate);
+                    responses.push((name.to_string(), response));
                 }
             };
 
-            let mut real_planner = RealFftPlanner::<f32>::new();
-            let r2c = real_planner.plan_fft_forward(sample_rate);
-            let mut spectrum = r2c.make_output_vec();
-            r2c.process(&mut impulse_response[..], &mut spectrum)
-                .expect("failed to process FFT");
-
-            // instead of graphing the impulse response, calculate the FFT of the impulse response.
-            // Ensure the 20 * log10(spectrum_bin.abs()) is used to plot the absolute value of the
-            // spectrum bin converted to dB
-            self.filter_freq_res = Some(
-                spectrum
-                    .iter()
-                    .map(|f| (f.re.abs() + std::f32::EPSILON).log10() * 20.0)
-                    // .map(|f| (f.im * f.im + f.re * f.re).sqrt().log10() * 20.0)
-                    .collect(),
-            );
-            self.phase_res = Some(
-                spectrum
-                    .iter()
-                    .map(|f| f.im.atan2(f.re) * 180.0 / std::f32::consts::PI)
-                    .collect(),
+        for (name, params) in names.iter().zip(self.channel_params.iter()) {
+            add(
+                name,
+                self.create_filter(params),
+                params.create_filter_as(sample_rate as f32, imported),
             );
-            //self.filter_freq_res = Some(impulse_response);
+
+            // Fixed-point filters are compared with the float design they approximate.
+            if let Some(reference) = params.filter.float_reference() {
+                let reference = FilterParams {
+                    filter: reference,
+                    ..*params
+                };
+                let noise_floor = noise_floor_db(
+                    params.create_filter_as(sample_rate as f32, None),
+                    reference.create_filter_as(sample_rate as f32, None),
+                    sample_rate,
+                );
+                noise_floors.push((name.to_string(), noise_floor));
+
+                if self.plot_settings.compare_fixed_point {
+                    add(
+                        &format!("{} float", name),
+                        self.create_filter(&reference),
+                        reference.create_filter_as(sample_rate as f32, None),
+                    );
+                }
+            }
+        }
+        if let Some(design) = imported.filter(|_| self.compare_imported) {
+            add("Imported", design.create_filter(), design.create_filter());
         }
 
-        egui::TopBottomPanel::top("Top Panel").show(ctx, |ui| {
-            ui.heading("Audio Filter App");
+        self.responses = responses;
+        self.noise_floors = noise_floors;
+
+        let params = self.channel_params[self.quantization_channel];
+        let coefficients = params
+            .create_filter_as::<f64>(sample_rate as f32, imported)
+            .coefficients();
+        self.quantization = Some(QuantizationAnalysis::new(
+            &coefficients,
+            self.quantization_bits,
+            sample_rate,
+        ));
+    }
+
+    fn quantization_controls(&mut self, ui: &mut egui::Ui) {
+        if self.channels_independent() {
+            let names = self.channel_names();
+            let mut channel = self.quantization_channel;
+            egui::ComboBox::from_label("Channel")
+                .selected_text(names[channel])
+                .show_ui(ui, |ui| {
+                    for (index, name) in names.iter().enumerate() {
+                        ui.selectable_value(&mut channel, index, *name);
+                    }
+                });
+            if channel != self.quantization_channel {
+                self.quantization_channel = channel;
+                self.coefficients_changed = true;
+            }
+        } else {
+            self.quantization_channel = 0;
+        }
+
+        let bits_slider = ui.add(
+            egui::Slider::new(
+                &mut self.quantization_bits,
+                quantization::MIN_BITS..=quantization::MAX_BITS,
+            )
+            .text("Coefficient bits"),
+        );
+        if bits_slider.changed() {
+            self.coefficients_changed = true;
+        }
+
+        let Some(analysis) = &self.quantization else {
+            return;
+        };
+        ui.label(format!(
+            "Largest deviation at {} bits: {:.3} dB, {}",
+            analysis.bits,
+            analysis.max_deviation_db,
+            if analysis.stable {
+                "stable"
+            } else {
+                "unstable"
+            }
+        ));
+
+        self.frequency_plot("quantization deviation")
+            .y_axis_label("deviation dB")
+            .show(ui, |plot_ui| {
+                let points = self.plot_points(&analysis.deviation_db, ResponseQuantity::Magnitude);
+                plot_ui.line(Line::new(points).name("Quantized - reference"));
+            });
+
+        let to_points = |poles: &[realfft::num_complex::Complex<f64>]| -> PlotPoints {
+            poles.iter().map(|pole| [pole.re, pole.im]).collect()
+        };
+        Plot::new("poles")
+            .data_aspect(1.0)
+            .view_aspect(1.0)
+            .legend(Legend::default())
+            .x_axis_label("Re")
+            .y_axis_label("Im")
+            .show(ui, |plot_ui| {
+                let unit_circle: PlotPoints = (0..=360)
+                    .map(|degree| {
+                        let angle = (degree as f64).to_radians();
+                        [angle.cos(), angle.sin()]
+                    })
+                    .collect();
+                plot_ui.line(Line::new(unit_circle).name("Unit circle"));
+                plot_ui.points(
+                    Points::new(to_points(&analysis.reference_poles))
+                        .shape(MarkerShape::Circle)
+                        .radius(5.0)
+                        .name("Reference poles"),
+                );
+                plot_ui.points(
+                    Points::new(to_points(&analysis.quantized_poles))
+                        .shape(MarkerShape::Cross)
+                        .radius(6.0)
+                        .name("Quantized poles"),
+                );
+            });
+    }
+
+    fn import_design(&mut self) {
+        match ImportedDesign::load(&self.imported_path) {
+            Ok(design) => {
+                self.imported_status = Some(format!("Loaded {}", design.summary()));
+                let design = Arc::new(design);
+                self.imported_design = Some(design.clone());
+                self.send(AudioCommand::SetImportedDesign(design));
+                self.coefficients_changed = true;
+            }
+            Err(err) => self.imported_status = Some(format!("Failed to import: {}", err)),
+        }
+    }
+
+    fn import_controls(&mut self, ui: &mut egui::Ui) {
+        ui.label("SOS rows (b0 b1 b2 a0 a1 a2) or FIR taps, e.g. from scipy or Octave.");
+        ui.horizontal(|ui| {
+            ui.label("File");
+            ui.text_edit_singleline(&mut self.imported_path);
+            if ui.button("Load").clicked() {
+                self.import_design();
+            }
         });
+        if let Some(status) = &self.imported_status {
+            ui.label(status);
+        }
+        if ui
+            .checkbox(
+                &mut self.compare_imported,
+                "Plot next to the current filter",
+            )
+            .changed()
+        {
+            self.coefficients_changed = true;
+        }
+        ui.label("Select the \"Imported\" filter type to listen to it.");
+    }
 
-        egui::CentralPanel::default().show(ctx, |ui| {
-            self.coefficients_changed = false;
-            self.selected_filter_changed = false;
+    fn export_controls(&mut self, ui: &mut egui::Ui) {
+        if self.channels_independent() {
+            let names = self.channel_names();
+            egui::ComboBox::from_label("Channel")
+                .selected_text(names[self.export_channel])
+                .show_ui(ui, |ui| {
+                    for (channel, name) in names.iter().enumerate() {
+                        ui.selectable_value(&mut self.export_channel, channel, *name);
+                    }
+                });
+        } else {
+            self.export_channel = 0;
+        }
+
+        let options = &mut self.export_options;
+        egui::ComboBox::from_label("Format")
+            .selected_text(format!("{:?}", options.format))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut options.format, ExportFormat::CHeader, "C header");
+                ui.selectable_value(&mut options.format, ExportFormat::RustConst, "Rust const");
+                ui.selectable_value(&mut options.format, ExportFormat::Csv, "CSV");
+            });
+        egui::ComboBox::from_label("Precision")
+            .selected_text(format!("{:?}", options.precision))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut options.precision, Precision::Single, "Single");
+                ui.selectable_value(&mut options.precision, Precision::Double, "Double");
+            });
+        ui.add(egui::Slider::new(&mut options.digits, 3..=17).text("Digits"));
+        ui.horizontal(|ui| {
+            ui.label("Name");
+            ui.text_edit_singleline(&mut options.name);
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("File");
+            ui.text_edit_singleline(&mut self.export_path);
+            if ui.button("Export").clicked() {
+                let params = self.channel_params[self.export_channel];
+                let text = export_coefficients(
+                    &params,
+                    self.imported_design.as_deref(),
+                    self.sample_rate() as u32,
+                    &self.export_options,
+                );
+                let mut path = std::path::PathBuf::from(&self.export_path);
+                if path.extension().is_none() {
+                    path.set_extension(self.export_options.format.extension());
+                }
+                self.export_status = Some(match std::fs::write(&path, text) {
+                    Ok(()) => format!("Exported to {}", path.display()),
+                    Err(err) => format!("Failed to export: {}", err),
+                });
+            }
+        });
+        if let Some(status) = &self.export_status {
+            ui.label(status);
+        }
+    }
+
+    fn preset_controls(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Name");
+            ui.text_edit_singleline(&mut self.preset_name);
+            let name = self.preset_name.trim().to_string();
+            if ui
+                .add_enabled(!name.is_empty(), egui::Button::new("Save"))
+                .clicked()
+            {
+                self.presets.save(self.current_preset(name));
+                self.preset_name.clear();
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("File");
+            ui.text_edit_singleline(&mut self.preset_file_path);
+            if ui.button("Export").clicked() {
+                self.export_preset();
+            }
+            if ui.button("Import").clicked() {
+                self.import_preset();
+            }
+        });
+        if let Some(status) = &self.preset_file_status {
+            ui.label(status);
+        }
+
+        let mut recall = None;
+        let mut delete = None;
+        let mut rename = None;
+
+        for preset in self.presets.presets() {
+            ui.horizontal(|ui| match &mut self.renaming {
+                Some((from, to)) if *from == preset.name => {
+                    ui.text_edit_singleline(to);
+                    if ui.button("OK").clicked() {
+                        rename = Some((from.clone(), to.trim().to_string()));
+                    }
+                    if ui.button("Cancel").clicked() {
+                        rename = Some((from.clone(), from.clone()));
+                    }
+                }
+                _ => {
+                    ui.label(&preset.name);
+                    if ui.button("Load").clicked() {
+                        recall = Some(preset.clone());
+                    }
+                    if ui.button("Rename").clicked() {
+                        self.renaming = Some((preset.name.clone(), preset.name.clone()));
+                    }
+                    if ui.button("Delete").clicked() {
+                        delete = Some(preset.name.clone());
+                    }
+                }
+            });
+        }
+
+        if let Some(preset) = recall {
+            self.recall_preset(preset);
+        }
+        if let Some(name) = delete {
+            self.presets.delete(&name);
+        }
+        // Keep the rename editor open while the new name is rejected (empty or taken).
+        if let Some((from, to)) = rename {
+            if self.presets.rename(&from, &to) {
+                self.renaming = None;
+            }
+        }
+    }
+
+    fn send(&self, cmd: AudioCommand) {
+        if let Some(tx) = &self.audio_tx {
+            _ = tx.send(cmd);
+        }
+    }
+
+    /// Sample rate of the running stream, which is what the response plots are computed at.
+    pub fn sample_rate(&self) -> usize {
+        self.device_state
+            .stream_info()
+            .map_or(44100, |info| info.sample_rate as usize)
+    }
+
+    fn poll_audio_events(&mut self) {
+        let Some(rx) = &self.audio_rx else {
+            return;
+        };
+
+        while let Ok(event) = rx.try_recv() {
+            match event {
+                AudioEvent::StateChanged(state) => {
+                    let sample_rate = self.sample_rate();
+                    if let DeviceState::Running(_) = state {
+                        self.audio_error = None;
+                    }
+                    self.device_state = state;
+                    if self.sample_rate() != sample_rate {
+                        self.coefficients_changed = true;
+                    }
+                }
+                AudioEvent::Error(err) => self.audio_error = Some(err.to_string()),
+                AudioEvent::FilterRecovered(total) => self.filter_recoveries = total,
+            }
+        }
+    }
+
+    fn scan_output_devices(&mut self) {
+        match crate::audio::output_devices(self.stream_settings.host) {
+            Ok(devices) => self.output_devices = Some(devices),
+            Err(err) => {
+                self.output_devices = Some(Vec::new());
+                self.audio_error = Some(err.to_string());
+            }
+        }
+    }
+
+    fn output_controls(&mut self, ui: &mut egui::Ui) {
+        let mut settings = self.stream_settings.clone();
 
-            let mut selected = self.selected_filter;
+        egui::ComboBox::from_label("Host")
+            .selected_text(settings.host.map_or("Default", |host| host.name()))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut settings.host, None, "Default");
+                for host in cpal::available_hosts() {
+                    ui.selectable_value(&mut settings.host, Some(host), host.name());
+                }
+            });
 
-            egui::ComboBox::from_label("Filter Type")
-                .selected_text(format!("{:?}", selected))
+        if settings.host != self.stream_settings.host {
+            // A different host has different devices, start over from its defaults.
+            settings = StreamSettings {
+                host: settings.host,
+                ..Default::default()
+            };
+            self.output_devices = None;
+        }
+
+        if self.output_devices.is_none() {
+            self.scan_output_devices();
+        }
+        let devices = self.output_devices.clone().unwrap_or_default();
+
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_label("Device")
+                .selected_text(settings.device.as_deref().unwrap_or("Default"))
                 .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut settings.device, None, "Default");
+                    for device in &devices {
+                        ui.selectable_value(
+                            &mut settings.device,
+                            Some(device.name.clone()),
+                            &device.name,
+                        );
+                    }
+                });
+            if ui.button("Refresh").clicked() {
+                self.output_devices = None;
+            }
+        });
+
+        if settings.device != self.stream_settings.device {
+            settings.sample_rate = None;
+            settings.buffer_size = None;
+            settings.sample_format = None;
+        }
+
+        // Options of the selected device, or of whichever device the default stream opened.
+        let device_name = settings.device.as_deref().or(self
+            .device_state
+            .stream_info()
+            .map(|info| info.device.as_str()));
+        let device = devices
+            .iter()
+            .find(|d| Some(d.name.as_str()) == device_name);
+
+        let default_text = |value: Option<String>| value.unwrap_or_else(|| "Default".to_string());
+
+        egui::ComboBox::from_label("Sample Rate")
+            .selected_text(default_text(
+                settings.sample_rate.map(|r| format!("{} Hz", r)),
+            ))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut settings.sample_rate, None, "Default");
+                for rate in device
+                    .map(|d| d.sample_rates.as_slice())
+                    .unwrap_or_default()
+                {
+                    ui.selectable_value(
+                        &mut settings.sample_rate,
+                        Some(*rate),
+                        format!("{} Hz", rate),
+                    );
+                }
+            });
+
+        egui::ComboBox::from_label("Buffer Size")
+            .selected_text(default_text(
+                settings.buffer_size.map(|b| format!("{} frames", b)),
+            ))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut settings.buffer_size, None, "Default");
+                for size in device
+                    .map(|d| d.buffer_sizes.as_slice())
+                    .unwrap_or_default()
+                {
                     ui.selectable_value(
-                        &mut selected,
-                        SelectedFilter::StateVariable,
-                        "StateVariable",
+                        &mut settings.buffer_size,
+                        Some(*size),
+                        format!("{} frames", size),
                     );
+                }
+            });
+
+        egui::ComboBox::from_label("Sample Format")
+            .selected_text(default_text(settings.sample_format.map(|f| f.to_string())))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut settings.sample_format, None, "Default");
+                for format in device
+                    .map(|d| d.sample_formats.as_slice())
+                    .unwrap_or_default()
+                {
                     ui.selectable_value(
-                        &mut selected,
-                        SelectedFilter::StateVariableTPT,
-                        "StateVariableTPT",
+                        &mut settings.sample_format,
+                        Some(*format),
+                        format.to_string(),
                     );
-                    ui.selectable_value(&mut selected, SelectedFilter::FirLowPass, "FIR LowPass");
-                    ui.selectable_value(&mut selected, SelectedFilter::FirHighPass, "FIR HighPass");
-                    ui.selectable_value(&mut selected, SelectedFilter::BiQuad, "BiQuad");
-                });
+                }
+            });
 
-            if self.selected_filter != selected {
-                self.selected_filter = selected;
-                self.selected_filter_changed = true;
-                if let Some(tx) = &self.audio_tx {
-                    _ = tx.send(AudioCommand::SetSelectedFilter(self.selected_filter));
+        if settings != self.stream_settings {
+            self.stream_settings = settings;
+            self.send(AudioCommand::SetStreamSettings(
+                self.stream_settings.clone(),
+            ));
+        }
+
+        match &self.device_state {
+            DeviceState::Closed => {
+                ui.label("No output stream");
+            }
+            DeviceState::Running(_) => {}
+            DeviceState::Fallback { reason, .. } => {
+                ui.colored_label(
+                    ui.visuals().warn_fg_color,
+                    format!(
+                        "Using the default device until the selected one returns: {}",
+                        reason
+                    ),
+                );
+            }
+            DeviceState::Reconnecting { reason, attempts } => {
+                ui.colored_label(
+                    ui.visuals().warn_fg_color,
+                    format!("Reconnecting (attempt {}): {}", attempts, reason),
+                );
+            }
+        }
+        if let Some(info) = self.device_state.stream_info() {
+            ui.label(format!(
+                "{} / {}: {} Hz, {} ch, {}, buffer {}",
+                info.host.name(),
+                info.device,
+                info.sample_rate,
+                info.channels,
+                info.sample_format,
+                info.buffer_size
+                    .map_or("default".to_string(), |b| format!("{} frames", b)),
+            ));
+        }
+        if let Some(err) = &self.audio_error {
+            ui.colored_label(ui.visuals().error_fg_color, err);
+        }
+    }
+
+    fn channel_names(&self) -> [&'static str; 2] {
+        match self.stereo_mode {
+            StereoMode::LeftRight => ["Left", "Right"],
+            StereoMode::MidSide => ["Mid", "Side"],
+        }
+    }
+
+    /// Whether each channel (or M/S path) currently has its own controls.
+    fn channels_independent(&self) -> bool {
+        !self.channels_linked || self.stereo_mode == StereoMode::MidSide
+    }
+
+    /// Snaps every channel back to the first channel's settings.
+    fn link_channels(&mut self) {
+        let params = self.channel_params[0];
+        self.channel_params = [params; 2];
+        self.send(AudioCommand::SetSelectedFilter(params.filter));
+        for id in ParamId::ALL {
+            self.send(AudioCommand::SetParam(id, params.param(id)));
+        }
+        self.send(AudioCommand::SetTopology(params.topology));
+        self.send(AudioCommand::SetFixedPoint(params.fixed));
+        self.coefficients_changed = true;
+    }
+
+    fn stereo_controls(&mut self, ui: &mut egui::Ui) {
+        let mut selected = self.stereo_mode;
+
+        egui::ComboBox::from_label("Stereo Mode")
+            .selected_text(match selected {
+                StereoMode::LeftRight => "Left/Right",
+                StereoMode::MidSide => "Mid/Side",
+            })
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut selected, StereoMode::LeftRight, "Left/Right");
+                ui.selectable_value(&mut selected, StereoMode::MidSide, "Mid/Side");
+            });
+
+        if self.stereo_mode != selected {
+            self.stereo_mode = selected;
+            self.send(AudioCommand::SetStereoMode(selected));
+            if self.channels_independent() {
+                self.coefficients_changed = true;
+            } else {
+                self.link_channels();
+            }
+        }
+
+        if self.stereo_mode == StereoMode::LeftRight
+            && ui
+                .checkbox(&mut self.channels_linked, "Link channels")
+                .changed()
+        {
+            self.coefficients_changed = true;
+            if self.channels_linked {
+                self.link_channels();
+            }
+        }
+    }
+
+    /// Builds a plot with a linear or logarithmic frequency axis, per the plot settings.
+    fn frequency_plot(&self, id: &str) -> Plot<'static> {
+        let log_frequency = self.plot_settings.log_frequency;
+        // Separate ids so each axis mode keeps its own zoom.
+        let plot = Plot::new((id, log_frequency))
+            .allow_drag(true)
+            .allow_scroll(true)
+            .view_aspect(2.0)
+            .legend(Legend::default())
+            .x_axis_label("Frequency Hz");
+
+        if log_frequency {
+            plot.x_grid_spacer(egui_plot::log_grid_spacer(10))
+                .x_axis_formatter(|mark, _range| format!("{:.0}", 10f64.powf(mark.value)))
+                .label_formatter(|name, point| {
+                    format!("{}\n{:.1} Hz\n{:.2}", name, 10f64.powf(point.x), point.y)
+                })
+        } else {
+            plot
+        }
+    }
+
+    fn plot_points(&self, values: &[f32], quantity: ResponseQuantity) -> PlotPoints {
+        plot_points(values, quantity, self.plot_settings.log_frequency).into()
+    }
+
+    fn response_plot(&self, quantity: ResponseQuantity) -> Plot<'static> {
+        let plot = if quantity.is_spectrum() {
+            self.frequency_plot(quantity.name())
+        } else {
+            Plot::new(quantity.name())
+                .allow_drag(true)
+                .allow_scroll(true)
+                .view_aspect(2.0)
+                .legend(Legend::default())
+                .x_axis_label(quantity.x_label())
+        };
+        plot.y_axis_label(quantity.y_label())
+    }
+
+    // for 0 to half-nyquist, plot frequency response
+    fn response_plots(&mut self, ui: &mut egui::Ui) {
+        let mut plot_rects = Vec::new();
+
+        for quantity in ResponseQuantity::ALL {
+            if !self.plot_settings.is_shown(quantity) {
+                continue;
+            }
+            let response = self.response_plot(quantity).show(ui, |plot_ui| {
+                for (name, response) in self.responses.iter() {
+                    let points = self.plot_points(quantity.values(response), quantity);
+                    plot_ui.line(Line::new(points).name(name));
                 }
+            });
+            plot_rects.push((quantity, response.response.rect.intersect(ui.clip_rect())));
+        }
+
+        self.plot_rects = plot_rects;
+    }
+
+    fn export_response(&mut self) {
+        let options = self.response_export_options.clone();
+        let mut path = std::path::PathBuf::from(&self.response_export_path);
+        if path.extension().is_none() {
+            path.set_extension(options.format.extension());
+        }
+
+        let text = match options.format {
+            ResponseFileFormat::Csv => {
+                response_csv(&self.responses, options.quantity, self.sample_rate())
+            }
+            ResponseFileFormat::Svg => response_svg(
+                &self.responses,
+                options.quantity,
+                self.plot_settings.log_frequency,
+            ),
+            ResponseFileFormat::Png => {
+                // Captured from the next frame's screenshot, see `save_capture`.
+                self.pending_capture = Some((options.quantity, path));
+                self.response_export_status = None;
+                return;
             }
+        };
+        self.response_export_status = Some(match std::fs::write(&path, text) {
+            Ok(()) => format!("Exported to {}", path.display()),
+            Err(err) => format!("Failed to export: {}", err),
+        });
+    }
 
-            let volume_slider = ui.add(egui::Slider::new(&mut self.vol, 0.0..=1.0).text("Volume"));
-            let freq_slider = ui.add(
-                egui::Slider::new(&mut self.freq_hz, 20.0..=18000.0)
-                    .text("Frequency Hz")
-                    .logarithmic(true),
-            );
-            let reso_slider = ui.add(
-                egui::Slider::new(&mut self.resonance_q, 0.1..=30.0)
-                    .text("Resonance (Q)")
-                    .logarithmic(true),
-            );
+    fn save_capture(&mut self, screenshot: &egui::ColorImage, pixels_per_point: f32) {
+        let Some((quantity, path)) = self.pending_capture.take() else {
+            return;
+        };
+
+        let screen = egui::Rect::from_min_size(
+            egui::Pos2::ZERO,
+            egui::vec2(screenshot.width() as f32, screenshot.height() as f32) / pixels_per_point,
+        );
+        let rect = self
+            .plot_rects
+            .iter()
+            .find(|(shown, _)| *shown == quantity)
+            .map(|(_, rect)| rect.intersect(screen))
+            .filter(|rect| rect.is_positive());
+        let Some(rect) = rect else {
+            self.response_export_status = Some(format!(
+                "Failed to export: the {} plot isn't visible",
+                quantity.name().to_lowercase()
+            ));
+            return;
+        };
+
+        let image = screenshot.region(&rect, Some(pixels_per_point));
+        self.response_export_status = Some(
+            match encode_png(&image)
+                .map_err(|err| err.to_string())
+                .and_then(|bytes| std::fs::write(&path, bytes).map_err(|err| err.to_string()))
+            {
+                Ok(()) => format!("Exported to {}", path.display()),
+                Err(err) => format!("Failed to export: {}", err),
+            },
+        );
+    }
 
-            if volume_slider.dragged() {
-                if let Some(tx) = &self.audio_tx {
-                    _ = tx.send(AudioCommand::SetVolume(self.vol));
+    fn response_export_controls(&mut self, ui: &mut egui::Ui) {
+        let options = &mut self.response_export_options;
+        egui::ComboBox::from_label("Data")
+            .selected_text(options.quantity.name())
+            .show_ui(ui, |ui| {
+                for quantity in ResponseQuantity::ALL {
+                    ui.selectable_value(&mut options.quantity, quantity, quantity.name());
                 }
+            });
+        egui::ComboBox::from_label("Format")
+            .selected_text(format!("{:?}", options.format))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut options.format, ResponseFileFormat::Csv, "CSV");
+                ui.selectable_value(&mut options.format, ResponseFileFormat::Svg, "SVG");
+                ui.selectable_value(&mut options.format, ResponseFileFormat::Png, "PNG");
+            });
+        if options.format == ResponseFileFormat::Png {
+            ui.label("PNG captures the plot as drawn, so it must be shown and on screen.");
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("File");
+            ui.text_edit_singleline(&mut self.response_export_path);
+            if ui.button("Export").clicked() {
+                self.export_response();
+                if self.pending_capture.is_some() {
+                    ui.ctx()
+                        .send_viewport_cmd(egui::ViewportCommand::Screenshot);
+                }
+            }
+        });
+        if let Some(status) = &self.response_export_status {
+            ui.label(status);
+        }
+    }
+
+    fn load_clip(&mut self) {
+        match AudioClip::load_wav(&self.clip_path) {
+            Ok(clip) => {
+                self.clip_status = Some(format!(
+                    "Loaded {} channel(s), {} Hz, {:.1} s",
+                    clip.channels,
+                    clip.sample_rate,
+                    clip.num_frames() as f32 / clip.sample_rate as f32
+                ));
+                self.send(AudioCommand::SetClip(Arc::new(clip)));
             }
+            Err(err) => self.clip_status = Some(format!("Failed to load: {}", err)),
+        }
+    }
+
+    fn source_controls(&mut self, ui: &mut egui::Ui) {
+        let mut selected = self.selected_source;
 
-            if freq_slider.dragged() {
-                if let Some(tx) = &self.audio_tx {
-                    _ = tx.send(AudioCommand::SetFilterFreq(self.freq_hz));
+        egui::ComboBox::from_label("Source")
+            .selected_text(format!("{:?}", selected))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut selected, SelectedSource::MonoNoise, "Mono Noise");
+                ui.selectable_value(&mut selected, SelectedSource::StereoNoise, "Stereo Noise");
+                ui.selectable_value(&mut selected, SelectedSource::File, "WAV File");
+            });
+
+        if self.selected_source != selected {
+            self.selected_source = selected;
+            self.send(AudioCommand::SetSource(selected));
+        }
+
+        if self.selected_source == SelectedSource::File {
+            ui.horizontal(|ui| {
+                ui.label("Path");
+                ui.text_edit_singleline(&mut self.clip_path);
+                if ui.button("Load").clicked() {
+                    self.load_clip();
                 }
-                self.coefficients_changed = true;
+            });
+            if let Some(status) = &self.clip_status {
+                ui.label(status);
             }
+        }
+    }
+
+    /// Filter type, cutoff and resonance for one channel, or for all channels when `channel` is
+    /// `None`.
+    fn channel_controls(&mut self, ui: &mut egui::Ui, channel: Option<usize>) {
+        let index = channel.unwrap_or(0);
+        let names = self.channel_names();
+        let label = |name: &str| match channel {
+            Some(channel) => format!("{} {}", names[channel], name),
+            None => name.to_string(),
+        };
 
-            if reso_slider.dragged() {
-                if let Some(tx) = &self.audio_tx {
-                    _ = tx.send(AudioCommand::SetResonance(self.resonance_q));
+        let mut selected = self.channel_params[index].filter;
+
+        egui::ComboBox::from_label(label("Filter Type"))
+            .selected_text(selected.name())
+            .show_ui(ui, |ui| {
+                for info in FILTERS {
+                    ui.selectable_value(&mut selected, info.filter, info.name);
                 }
-                self.coefficients_changed = true;
+            });
+
+        if self.channel_params[index].filter != selected {
+            self.channel_params[index].filter = selected;
+            self.selected_filter_changed = true;
+            self.send(match channel {
+                Some(channel) => AudioCommand::SetChannelSelectedFilter(channel, selected),
+                None => AudioCommand::SetSelectedFilter(selected),
+            });
+        }
+
+        if selected.info().uses_topology {
+            let mut topology = self.channel_params[index].topology;
+            egui::ComboBox::from_label(label("Topology"))
+                .selected_text(format!("{:?}", topology))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(
+                        &mut topology,
+                        BiQuadTopology::DirectFormI,
+                        "Direct Form I",
+                    );
+                    ui.selectable_value(
+                        &mut topology,
+                        BiQuadTopology::DirectFormII,
+                        "Direct Form II",
+                    );
+                    ui.selectable_value(
+                        &mut topology,
+                        BiQuadTopology::TransposedDirectFormII,
+                        "Transposed Direct Form II",
+                    );
+                    ui.selectable_value(&mut topology, BiQuadTopology::Lattice, "Lattice");
+                });
+
+            if self.channel_params[index].topology != topology {
+                self.channel_params[index].topology = topology;
+                self.selected_filter_changed = true;
+                self.send(match channel {
+                    Some(channel) => AudioCommand::SetChannelTopology(channel, topology),
+                    None => AudioCommand::SetTopology(topology),
+                });
             }
+        }
 
-            // for 0 to half-nyquist, plot frequency response
-            if let Some(filter_freq_res) = &self.filter_freq_res {
-                let fft: PlotPoints = filter_freq_res
-                    .iter()
-                    .enumerate()
-                    .map(|(x, y)| [x as f64, *y as f64])
-                    .collect();
-                let line = Line::new(fft);
-                //let bounds = egui_plot::PlotBounds::from_min_max([0.0, -100.0], [23000.0, 60.0])
-
-                // TODO: Plot frequency in log scale
-                Plot::new("frequencies")
-                    .allow_drag(true)
-                    .allow_scroll(true)
-                    .view_aspect(2.0)
-                    .x_axis_label("Frequency Hz")
-                    .y_axis_label("dB")
-                    //.x_grid_spacer(egui_plot::log_grid_spacer(10))
-                    .show(ui, |plot_ui| plot_ui.line(line));
-            }
-
-            if let Some(phase_res) = &self.phase_res {
-                let phase_plot: PlotPoints = phase_res
-                    .iter()
-                    .enumerate()
-                    .map(|(x, y)| [x as f64, *y as f64])
-                    .collect();
-                let line = Line::new(phase_plot);
+        if selected.float_reference().is_some() {
+            let mut fixed = self.channel_params[index].fixed;
+            egui::ComboBox::from_label(label("Sample format"))
+                .selected_text(format!("{:?}", fixed.format))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut fixed.format, FixedFormat::Q15, "Q15");
+                    ui.selectable_value(&mut fixed.format, FixedFormat::Q31, "Q31");
+                });
+            ui.add(
+                egui::Slider::new(
+                    &mut fixed.coefficient_bits,
+                    MIN_COEFFICIENT_BITS..=MAX_COEFFICIENT_BITS,
+                )
+                .text(label("Coefficient bits")),
+            );
 
-                Plot::new("phase response")
-                    .allow_drag(true)
-                    .allow_scroll(true)
-                    .view_aspect(2.0)
-                    .x_axis_label("Frequency Hz")
-                    .y_axis_label("phase degrees")
-                    //.x_grid_spacer(egui_plot::log_grid_spacer(10))
-                    .show(ui, |plot_ui| plot_ui.line(line));
+            if self.channel_params[index].fixed != fixed {
+                self.channel_params[index].fixed = fixed;
+                self.selected_filter_changed = true;
+                self.send(match channel {
+                    Some(channel) => AudioCommand::SetChannelFixedPoint(channel, fixed),
+                    None => AudioCommand::SetFixedPoint(fixed),
+                });
             }
+        }
+
+        let mut params = self.channel_params[index];
+        for descriptor in selected.info().params {
+            let mut value = params.param(descriptor.id);
+            let slider = egui::Slider::new(&mut value, descriptor.min..=descriptor.max)
+                .text(label(&descriptor.label()))
+                .logarithmic(descriptor.scale == ParamScale::Logarithmic);
+            if ui.add(slider).changed() {
+                params.set_param(descriptor.id, value);
+                self.send(match channel {
+                    Some(channel) => AudioCommand::SetChannelParam(channel, descriptor.id, value),
+                    None => AudioCommand::SetParam(descriptor.id, value),
+                });
+                self.coefficients_changed = true;
+            }
+        }
+        self.channel_params[index] = params;
+
+        if channel.is_none() {
+            self.channel_params = [params; 2];
+        }
+    }
+}
+
+impl eframe::App for AudioFilterApp {
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, eframe::APP_KEY, self);
+    }
+
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.poll_audio_events();
+        // Audio events arrive without user input, so keep checking for them.
+        ctx.request_repaint_after(std::time::Duration::from_millis(250));
+
+        let screenshot = ctx.input(|input| {
+            input.events.iter().find_map(|event| match event {
+                egui::Event::Screenshot { image, .. } => Some(image.clone()),
+                _ => None,
+            })
+        });
+        if let Some(screenshot) = screenshot {
+            self.save_capture(&screenshot, ctx.pixels_per_point());
+        }
+
+        if self.responses.is_empty() || self.coefficients_changed || self.selected_filter_changed {
+            self.compute_responses();
+        }
+
+        egui::TopBottomPanel::top("Top Panel").show(ctx, |ui| {
+            ui.heading("Audio Filter App");
+        });
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                self.coefficients_changed = false;
+                self.selected_filter_changed = false;
+
+                egui::CollapsingHeader::new("Output").show(ui, |ui| self.output_controls(ui));
+                egui::CollapsingHeader::new("Presets").show(ui, |ui| self.preset_controls(ui));
+                egui::CollapsingHeader::new("Import Coefficients")
+                    .show(ui, |ui| self.import_controls(ui));
+                egui::CollapsingHeader::new("Export Coefficients")
+                    .show(ui, |ui| self.export_controls(ui));
+                egui::CollapsingHeader::new("Quantization Analysis")
+                    .show(ui, |ui| self.quantization_controls(ui));
+                egui::CollapsingHeader::new("Export Response")
+                    .show(ui, |ui| self.response_export_controls(ui));
+
+                self.source_controls(ui);
+
+                let volume_slider =
+                    ui.add(egui::Slider::new(&mut self.vol, 0.0..=1.0).text("Volume"));
+                if volume_slider.changed() {
+                    self.send(AudioCommand::SetVolume(self.vol));
+                }
+
+                self.stereo_controls(ui);
+
+                if ui
+                    .button("Reset filter state")
+                    .on_hover_text("Clear the filters' memory, e.g. after a blow-up or to stop a ringing tail")
+                    .clicked()
+                {
+                    self.send(AudioCommand::ResetFilterState);
+                }
+
+                if self.filter_recoveries > 0 {
+                    ui.colored_label(
+                        ui.visuals().warn_fg_color,
+                        format!(
+                            "Filter output became NaN or infinite {} time(s); the filter was reset.",
+                            self.filter_recoveries
+                        ),
+                    );
+                }
+
+                if self.channels_independent() {
+                    for channel in 0..self.channel_params.len() {
+                        ui.separator();
+                        self.channel_controls(ui, Some(channel));
+                    }
+                } else {
+                    self.channel_controls(ui, None);
+                }
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.plot_settings.log_frequency, "Log frequency");
+                    ui.checkbox(&mut self.plot_settings.show_phase, "Show phase");
+                    ui.checkbox(&mut self.plot_settings.show_group_delay, "Show group delay");
+                    ui.checkbox(
+                        &mut self.plot_settings.show_impulse,
+                        "Show impulse response",
+                    );
+                    if ui
+                        .checkbox(
+                            &mut self.plot_settings.compare_precision,
+                            "Compare f32 and f64",
+                        )
+                        .changed()
+                    {
+                        self.coefficients_changed = true;
+                    }
+                    if !self.noise_floors.is_empty()
+                        && ui
+                            .checkbox(
+                                &mut self.plot_settings.compare_fixed_point,
+                                "Compare fixed point with float",
+                            )
+                            .changed()
+                    {
+                        self.coefficients_changed = true;
+                    }
+                });
+                for (name, noise_floor) in &self.noise_floors {
+                    ui.label(format!(
+                        "{} fixed-point noise floor: {:.1} dBFS",
+                        name, noise_floor
+                    ));
+                }
+
+                self.response_plots(ui);
+            });
         });
     }
 }
@@ -0,0 +1,598 @@
+use atomic_float::AtomicF32;
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use std::fmt;
+use std::sync::atomic::Ordering;
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use web_time::Instant;
+
+use crate::app::AudioCommand;
+use crate::{FilterParams, MultiChannelFilter, SourcePlayer};
+
+/// Sample rates offered in the UI when a device supports them.
+pub const COMMON_SAMPLE_RATES: [u32; 9] = [
+    22050, 32000, 44100, 48000, 88200, 96000, 176400, 192000, 384000,
+];
+
+/// Fixed buffer sizes offered in the UI when a device supports them.
+pub const COMMON_BUFFER_SIZES: [u32; 8] = [32, 64, 128, 256, 512, 1024, 2048, 4096];
+
+/// The output stream the user asked for. `None` means "use the host or device default".
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StreamSettings {
+    pub host: Option<cpal::HostId>,
+    pub device: Option<String>,
+    pub sample_rate: Option<u32>,
+    pub buffer_size: Option<u32>,
+    pub sample_format: Option<cpal::SampleFormat>,
+}
+
+/// What was actually opened, reported back to the UI.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamInfo {
+    pub host: cpal::HostId,
+    pub device: String,
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub buffer_size: Option<u32>,
+    pub sample_format: cpal::SampleFormat,
+}
+
+/// An output device and the stream options it advertises.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputDevice {
+    pub name: String,
+    pub sample_rates: Vec<u32>,
+    pub buffer_sizes: Vec<u32>,
+    pub sample_formats: Vec<cpal::SampleFormat>,
+}
+
+/// Where the engine is in the life cycle of its output stream.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DeviceState {
+    /// No stream has been requested yet.
+    Closed,
+    /// The requested stream is playing.
+    Running(StreamInfo),
+    /// The requested device failed and the default device is playing until it comes back.
+    Fallback { info: StreamInfo, reason: String },
+    /// Nothing is playing. Another attempt is made every `RETRY_INTERVAL`.
+    Reconnecting { reason: String, attempts: u32 },
+}
+
+impl DeviceState {
+    /// The stream that is currently playing, if any.
+    pub fn stream_info(&self) -> Option<&StreamInfo> {
+        match self {
+            DeviceState::Running(info) | DeviceState::Fallback { info, .. } => Some(info),
+            DeviceState::Closed | DeviceState::Reconnecting { .. } => None,
+        }
+    }
+}
+
+/// Messages from the audio thread to the UI.
+pub enum AudioEvent {
+    StateChanged(DeviceState),
+    Error(AudioError),
+    /// A filter produced NaN or infinity and was reset. Carries the total count since startup.
+    FilterRecovered(u64),
+}
+
+/// How often a lost device is retried, and how often a fallback checks for the original device.
+pub const RETRY_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Failed reopen attempts before giving up on the requested device and using the default one.
+pub const ATTEMPTS_BEFORE_FALLBACK: u32 = 3;
+
+#[derive(Debug)]
+pub enum AudioError {
+    HostUnavailable(cpal::HostId),
+    NoDefaultDevice,
+    DeviceNotFound(String),
+    Devices(cpal::DevicesError),
+    DeviceName(cpal::DeviceNameError),
+    DefaultConfig(cpal::DefaultStreamConfigError),
+    SupportedConfigs(cpal::SupportedStreamConfigsError),
+    UnsupportedConfig,
+    UnsupportedSampleFormat(cpal::SampleFormat),
+    BuildStream(cpal::BuildStreamError),
+    PlayStream(cpal::PlayStreamError),
+    Stream(cpal::StreamError),
+}
+
+impl fmt::Display for AudioError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AudioError::HostUnavailable(host) => {
+                write!(f, "audio host {} is unavailable", host.name())
+            }
+            AudioError::NoDefaultDevice => write!(f, "no default output device"),
+            AudioError::DeviceNotFound(name) => write!(f, "output device \"{}\" not found", name),
+            AudioError::Devices(err) => write!(f, "failed to list devices: {}", err),
+            AudioError::DeviceName(err) => write!(f, "failed to read device name: {}", err),
+            AudioError::DefaultConfig(err) => write!(f, "no default output config: {}", err),
+            AudioError::SupportedConfigs(err) => {
+                write!(f, "failed to query device configs: {}", err)
+            }
+            AudioError::UnsupportedConfig => {
+                write!(
+                    f,
+                    "the device doesn't support the requested sample rate/format"
+                )
+            }
+            AudioError::UnsupportedSampleFormat(format) => {
+                write!(f, "unsupported sample format {}", format)
+            }
+            AudioError::BuildStream(err) => write!(f, "failed to build output stream: {}", err),
+            AudioError::PlayStream(err) => write!(f, "failed to start output stream: {}", err),
+            AudioError::Stream(err) => write!(f, "stream error: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for AudioError {}
+
+fn host(host_id: Option<cpal::HostId>) -> Result<cpal::Host, AudioError> {
+    match host_id {
+        Some(id) => cpal::host_from_id(id).map_err(|_| AudioError::HostUnavailable(id)),
+        None => Ok(cpal::default_host()),
+    }
+}
+
+fn device(host: &cpal::Host, name: Option<&str>) -> Result<cpal::Device, AudioError> {
+    match name {
+        Some(name) => host
+            .output_devices()
+            .map_err(AudioError::Devices)?
+            .find(|device| device.name().is_ok_and(|n| n == name))
+            .ok_or_else(|| AudioError::DeviceNotFound(name.to_string())),
+        None => host
+            .default_output_device()
+            .ok_or(AudioError::NoDefaultDevice),
+    }
+}
+
+/// Lists the output devices of a host with the options each one supports.
+pub fn output_devices(host_id: Option<cpal::HostId>) -> Result<Vec<OutputDevice>, AudioError> {
+    let host = host(host_id)?;
+    let mut devices = Vec::new();
+
+    for device in host.output_devices().map_err(AudioError::Devices)? {
+        let name = device.name().map_err(AudioError::DeviceName)?;
+        // Some backends list devices that refuse to be queried; skip those instead of failing.
+        let Ok(configs) = device.supported_output_configs() else {
+            continue;
+        };
+
+        let mut output_device = OutputDevice {
+            name,
+            sample_rates: Vec::new(),
+            buffer_sizes: Vec::new(),
+            sample_formats: Vec::new(),
+        };
+
+        for config in configs {
+            for rate in COMMON_SAMPLE_RATES {
+                if (config.min_sample_rate().0..=config.max_sample_rate().0).contains(&rate)
+                    && !output_device.sample_rates.contains(&rate)
+                {
+                    output_device.sample_rates.push(rate);
+                }
+            }
+            if let cpal::SupportedBufferSize::Range { min, max } = *config.buffer_size() {
+                for size in COMMON_BUFFER_SIZES {
+                    if (min..=max).contains(&size) && !output_device.buffer_sizes.contains(&size) {
+                        output_device.buffer_sizes.push(size);
+                    }
+                }
+            }
+            if !output_device
+                .sample_formats
+                .contains(&config.sample_format())
+            {
+                output_device.sample_formats.push(config.sample_format());
+            }
+        }
+
+        output_device.sample_rates.sort();
+        output_device.buffer_sizes.sort();
+        devices.push(output_device);
+    }
+
+    Ok(devices)
+}
+
+// Picks a supported config that satisfies the requested rate and format, falling back to the
+// device default for anything left unspecified.
+fn stream_config(
+    device: &cpal::Device,
+    settings: &StreamSettings,
+) -> Result<cpal::SupportedStreamConfig, AudioError> {
+    let default = device
+        .default_output_config()
+        .map_err(AudioError::DefaultConfig)?;
+
+    let sample_rate = settings.sample_rate.unwrap_or(default.sample_rate().0);
+    let sample_format = settings.sample_format.unwrap_or(default.sample_format());
+
+    if sample_rate == default.sample_rate().0 && sample_format == default.sample_format() {
+        return Ok(default);
+    }
+
+    let mut candidates = device
+        .supported_output_configs()
+        .map_err(AudioError::SupportedConfigs)?
+        .filter(|config| config.sample_format() == sample_format)
+        .filter_map(|config| config.try_with_sample_rate(cpal::SampleRate(sample_rate)))
+        .collect::<Vec<_>>();
+
+    // Prefer the default channel layout, the filters are laid out for stereo.
+    candidates.sort_by_key(|config| config.channels() != default.channels());
+    candidates
+        .into_iter()
+        .next()
+        .ok_or(AudioError::UnsupportedConfig)
+}
+
+/// Owns the output stream and the shared state its callback renders from. Lives on the audio
+/// thread because a `cpal::Stream` can't be sent between threads on every platform.
+///
+/// The filter and source outlive any single stream, so parameters and filter state survive
+/// device changes, disconnects and reconnects.
+pub struct AudioEngine {
+    volume: Arc<AtomicF32>,
+    source: Arc<Mutex<SourcePlayer>>,
+    filter: Arc<Mutex<MultiChannelFilter>>,
+    events: Sender<AudioEvent>,
+    stream: Option<cpal::Stream>,
+    // Bumped for every stream so errors from a stream that was already replaced are ignored.
+    generation: u64,
+    stream_errors_tx: Sender<(u64, cpal::StreamError)>,
+    stream_errors_rx: Receiver<(u64, cpal::StreamError)>,
+    requested: StreamSettings,
+    state: DeviceState,
+    attempts: u32,
+    retry_at: Option<Instant>,
+    // Filter recoveries already reported to the UI.
+    recoveries: u64,
+}
+
+impl AudioEngine {
+    pub fn new(params: FilterParams, volume: f32, events: Sender<AudioEvent>) -> Self {
+        // Placeholder format until the first stream is opened.
+        let sample_rate = 44100.0;
+        let num_channels = 2;
+
+        let (stream_errors_tx, stream_errors_rx) = channel();
+
+        Self {
+            volume: Arc::new(AtomicF32::new(volume)),
+            source: Arc::new(Mutex::new(SourcePlayer::new(sample_rate, num_channels))),
+            filter: Arc::new(Mutex::new(MultiChannelFilter::new(
+                sample_rate,
+                num_channels,
+                params,
+            ))),
+            events,
+            stream: None,
+            generation: 0,
+            stream_errors_tx,
+            stream_errors_rx,
+            requested: StreamSettings::default(),
+            state: DeviceState::Closed,
+            attempts: 0,
+            retry_at: None,
+            recoveries: 0,
+        }
+    }
+
+    pub fn state(&self) -> &DeviceState {
+        &self.state
+    }
+
+    /// The parameters the filter on `channel` is running with.
+    pub fn params(&self, channel: usize) -> Option<FilterParams> {
+        self.filter.lock().unwrap().params(channel)
+    }
+
+    /// Handles commands from the UI until it hangs up, checking on the stream in between.
+    pub fn run(mut self, commands: Receiver<AudioCommand>) {
+        loop {
+            match commands.recv_timeout(RETRY_INTERVAL / 4) {
+                Ok(cmd) => self.handle_command(cmd),
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+            self.tick();
+        }
+    }
+
+    /// Handles the commands already sent and checks on the stream, without waiting. For the
+    /// browser, where there is no audio thread and the UI calls this every frame.
+    pub fn poll(&mut self, commands: &Receiver<AudioCommand>) {
+        while let Ok(cmd) = commands.try_recv() {
+            self.handle_command(cmd);
+        }
+        self.tick();
+    }
+
+    /// Switches to new stream settings. Whatever happens is reported to the UI as a state change.
+    pub fn request(&mut self, settings: StreamSettings) {
+        self.requested = settings;
+        self.attempts = 0;
+        self.connect();
+    }
+
+    /// Reacts to stream errors and retries lost devices. Call this regularly.
+    pub fn tick(&mut self) {
+        let recoveries = self.filter.lock().unwrap().recoveries();
+        if recoveries != self.recoveries {
+            self.recoveries = recoveries;
+            log::warn!(
+                "filter output was not finite, filter reset ({} so far)",
+                recoveries
+            );
+            _ = self.events.send(AudioEvent::FilterRecovered(recoveries));
+        }
+
+        while let Ok((generation, err)) = self.stream_errors_rx.try_recv() {
+            if generation != self.generation {
+                continue;
+            }
+            match err {
+                // The device is gone, the stream won't produce audio again.
+                cpal::StreamError::DeviceNotAvailable => {
+                    self.connection_failed(AudioError::Stream(err))
+                }
+                // Backend errors such as underruns are worth showing but the stream keeps going.
+                cpal::StreamError::BackendSpecific { .. } => self.report(AudioError::Stream(err)),
+            }
+        }
+
+        if self
+            .retry_at
+            .is_some_and(|retry_at| Instant::now() >= retry_at)
+        {
+            self.retry_at = None;
+            match self.state {
+                DeviceState::Reconnecting { .. } => self.connect(),
+                // Only leave the fallback once the requested device shows up again, so the
+                // fallback isn't interrupted by attempts that are bound to fail.
+                DeviceState::Fallback { .. } => {
+                    let available = host(self.requested.host)
+                        .and_then(|host| device(&host, self.requested.device.as_deref()))
+                        .is_ok();
+                    if available {
+                        self.connect();
+                    } else {
+                        self.retry_at = Some(Instant::now() + RETRY_INTERVAL);
+                    }
+                }
+                DeviceState::Closed | DeviceState::Running(_) => {}
+            }
+        }
+    }
+
+    fn connect(&mut self) {
+        let settings = self.requested.clone();
+        match self.open(&settings) {
+            Ok(info) => {
+                self.attempts = 0;
+                self.retry_at = None;
+                self.set_state(DeviceState::Running(info));
+            }
+            Err(err) => self.connection_failed(err),
+        }
+    }
+
+    fn connection_failed(&mut self, err: AudioError) {
+        self.stream = None;
+        self.attempts += 1;
+        self.retry_at = Some(Instant::now() + RETRY_INTERVAL);
+
+        let reason = err.to_string();
+        self.report(err);
+
+        if self.attempts >= ATTEMPTS_BEFORE_FALLBACK && self.requested != StreamSettings::default()
+        {
+            match self.open(&StreamSettings::default()) {
+                Ok(info) => {
+                    self.set_state(DeviceState::Fallback { info, reason });
+                    return;
+                }
+                Err(err) => self.report(err),
+            }
+        }
+
+        self.set_state(DeviceState::Reconnecting {
+            reason,
+            attempts: self.attempts,
+        });
+    }
+
+    fn set_state(&mut self, state: DeviceState) {
+        if self.state != state {
+            self.state = state.clone();
+            _ = self.events.send(AudioEvent::StateChanged(state));
+        }
+    }
+
+    fn report(&self, err: AudioError) {
+        log::warn!("{}", err);
+        _ = self.events.send(AudioEvent::Error(err));
+    }
+
+    /// Closes the current stream (if any) and opens a new one. Filter and source settings carry
+    /// over; they are only rebuilt if the sample rate or channel count changed. Prefer `request`,
+    /// which also takes care of retries and fallbacks.
+    pub fn open(&mut self, settings: &StreamSettings) -> Result<StreamInfo, AudioError> {
+        // Drop the old stream first, some backends only allow one stream per device.
+        self.stream = None;
+        self.generation += 1;
+
+        let host = host(settings.host)?;
+        let device = device(&host, settings.device.as_deref())?;
+        let supported = stream_config(&device, settings)?;
+        let sample_format = supported.sample_format();
+
+        let mut config: cpal::StreamConfig = supported.into();
+        if let Some(buffer_size) = settings.buffer_size {
+            config.buffer_size = cpal::BufferSize::Fixed(buffer_size);
+        }
+
+        let sample_rate = config.sample_rate.0 as f32;
+        let num_channels = config.channels as usize;
+        self.filter
+            .lock()
+            .unwrap()
+            .set_format(sample_rate, num_channels);
+        self.source
+            .lock()
+            .unwrap()
+            .set_format(sample_rate, num_channels);
+
+        let stream = match sample_format {
+            cpal::SampleFormat::I8 => self.make_stream::<i8>(&device, &config),
+            cpal::SampleFormat::I16 => self.make_stream::<i16>(&device, &config),
+            cpal::SampleFormat::I32 => self.make_stream::<i32>(&device, &config),
+            cpal::SampleFormat::I64 => self.make_stream::<i64>(&device, &config),
+            cpal::SampleFormat::U8 => self.make_stream::<u8>(&device, &config),
+            cpal::SampleFormat::U16 => self.make_stream::<u16>(&device, &config),
+            cpal::SampleFormat::U32 => self.make_stream::<u32>(&device, &config),
+            cpal::SampleFormat::U64 => self.make_stream::<u64>(&device, &config),
+            cpal::SampleFormat::F32 => self.make_stream::<f32>(&device, &config),
+            cpal::SampleFormat::F64 => self.make_stream::<f64>(&device, &config),
+            sample_format => Err(AudioError::UnsupportedSampleFormat(sample_format)),
+        }?;
+
+        // If the stream is disposed of, audio is DONE. The engine keeps it alive until the next
+        // `open` or until the audio thread exits.
+        stream.play().map_err(AudioError::PlayStream)?;
+        self.stream = Some(stream);
+
+        Ok(StreamInfo {
+            host: host.id(),
+            device: device.name().map_err(AudioError::DeviceName)?,
+            sample_rate: config.sample_rate.0,
+            channels: config.channels,
+            buffer_size: settings.buffer_size,
+            sample_format,
+        })
+    }
+
+    pub fn handle_command(&mut self, cmd: AudioCommand) {
+        match cmd {
+            AudioCommand::SetVolume(new_vol) => {
+                self.volume.store(new_vol, Ordering::Relaxed);
+            }
+            AudioCommand::SetParam(id, value) => {
+                let mut filter = self.filter.lock().unwrap();
+                filter.modify_params(None, |p| p.set_param(id, value));
+            }
+            AudioCommand::SetSelectedFilter(sel_fil) => {
+                let mut filter = self.filter.lock().unwrap();
+                filter.modify_params(None, |p| p.filter = sel_fil);
+            }
+            AudioCommand::SetChannelParam(channel, id, value) => {
+                let mut filter = self.filter.lock().unwrap();
+                filter.modify_params(Some(channel), |p| p.set_param(id, value));
+            }
+            AudioCommand::SetChannelSelectedFilter(channel, sel_fil) => {
+                let mut filter = self.filter.lock().unwrap();
+                filter.modify_params(Some(channel), |p| p.filter = sel_fil);
+            }
+            AudioCommand::SetTopology(topology) => {
+                let mut filter = self.filter.lock().unwrap();
+                filter.modify_params(None, |p| p.topology = topology);
+            }
+            AudioCommand::SetChannelTopology(channel, topology) => {
+                let mut filter = self.filter.lock().unwrap();
+                filter.modify_params(Some(channel), |p| p.topology = topology);
+            }
+            AudioCommand::SetFixedPoint(fixed) => {
+                let mut filter = self.filter.lock().unwrap();
+                filter.modify_params(None, |p| p.fixed = fixed);
+            }
+            AudioCommand::SetChannelFixedPoint(channel, fixed) => {
+                let mut filter = self.filter.lock().unwrap();
+                filter.modify_params(Some(channel), |p| p.fixed = fixed);
+            }
+            AudioCommand::ResetFilterState => self.filter.lock().unwrap().reset(),
+            AudioCommand::SetStereoMode(stereo_mode) => {
+                self.filter.lock().unwrap().set_stereo_mode(stereo_mode);
+            }
+            AudioCommand::SetImportedDesign(design) => {
+                self.filter.lock().unwrap().set_imported(Some(design));
+            }
+            AudioCommand::SetSource(selected_source) => {
+                self.source.lock().unwrap().set_source(selected_source);
+            }
+            AudioCommand::SetClip(clip) => {
+                self.source.lock().unwrap().set_clip(Some(clip));
+            }
+            AudioCommand::SetStreamSettings(settings) => {
+                self.request(settings);
+            }
+        }
+    }
+
+    fn make_stream<T>(
+        &self,
+        device: &cpal::Device,
+        config: &cpal::StreamConfig,
+    ) -> Result<cpal::Stream, AudioError>
+    where
+        T: cpal::SizedSample + cpal::FromSample<f32>,
+    {
+        let num_channels = config.channels as usize;
+        let mut frame_buffer = vec![0.0; num_channels];
+        let volume = self.volume.clone();
+        let source = self.source.clone();
+        let filter = self.filter.clone();
+
+        // Errors go to the engine first, it decides whether the stream has to be reopened.
+        let generation = self.generation;
+        let stream_errors = self.stream_errors_tx.clone();
+        let err_fn = move |err| {
+            _ = stream_errors.send((generation, err));
+        };
+
+        device
+            .build_output_stream(
+                config,
+                move |output: &mut [T], _: &cpal::OutputCallbackInfo| {
+                    process_frame(output, &mut frame_buffer, &volume, &source, &filter)
+                },
+                err_fn,
+                None,
+            )
+            .map_err(AudioError::BuildStream)
+    }
+}
+
+fn process_frame<SampleType>(
+    output: &mut [SampleType],
+    frame_buffer: &mut [f32],
+    volume: &AtomicF32,
+    source: &Mutex<SourcePlayer>,
+    filter: &Mutex<MultiChannelFilter>,
+) where
+    SampleType: cpal::Sample + cpal::FromSample<f32>,
+{
+    let volume = volume.load(Ordering::Relaxed);
+
+    // TODO: Not great...  I don't like locks in the audio thread as they're unbounded.
+    // Lock once per buffer rather than once per frame.
+    let mut source = source.lock().unwrap();
+    let mut filter = filter.lock().unwrap();
+
+    for frame in output.chunks_mut(frame_buffer.len()) {
+        source.next_frame(frame_buffer);
+        filter.render_frame(frame_buffer);
+
+        for (sample, filter_sample) in frame.iter_mut().zip(frame_buffer.iter()) {
+            *sample = SampleType::from_sample(filter_sample * volume);
+        }
+    }
+}
@@ -0,0 +1,265 @@
+use serde::{Deserialize, Serialize};
+
+use std::sync::Arc;
+
+use crate::registry::{ParamId, CUTOFF, MAX_CUTOFF_RATIO, RESONANCE};
+use crate::{BiQuadTopology, Filter, FixedPointParams, ImportedDesign, Sample, SelectedFilter};
+
+/// Everything needed to build and tune the filter on one channel.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FilterParams {
+    pub filter: SelectedFilter,
+    pub cutoff_hz: f32,
+    pub resonance_q: f32,
+    /// Structure used when `filter` is `SelectedFilter::BiQuad`.
+    pub topology: BiQuadTopology,
+    /// Word lengths used by the fixed-point filters.
+    pub fixed: FixedPointParams,
+}
+
+impl Default for FilterParams {
+    fn default() -> Self {
+        Self {
+            filter: SelectedFilter::StateVariable,
+            cutoff_hz: CUTOFF.default,
+            resonance_q: RESONANCE.default,
+            topology: BiQuadTopology::default(),
+            fixed: FixedPointParams::default(),
+        }
+    }
+}
+
+impl FilterParams {
+    /// Builds the filter described by these parameters with its coefficients already computed.
+    pub fn create_filter(&self, sample_rate: f32) -> Box<dyn Filter> {
+        self.create_filter_with(sample_rate, None)
+    }
+
+    /// Like `create_filter`, but builds `SelectedFilter::Imported` from `imported`.
+    pub fn create_filter_with(
+        &self,
+        sample_rate: f32,
+        imported: Option<&ImportedDesign>,
+    ) -> Box<dyn Filter> {
+        self.create_filter_as(sample_rate, imported)
+    }
+
+    /// Like `create_filter_with`, at any precision.
+    pub fn create_filter_as<T: Sample>(
+        &self,
+        sample_rate: f32,
+        imported: Option<&ImportedDesign>,
+    ) -> Box<dyn Filter<T>> {
+        let mut filter = self
+            .filter
+            .info()
+            .create
+            .create(sample_rate, self, imported);
+        filter.update_coefficients(self.cutoff_at(sample_rate), self.resonance_q);
+        filter
+    }
+
+    /// The cutoff the filter is built with at `sample_rate`, kept below Nyquist.
+    pub fn cutoff_at(&self, sample_rate: f32) -> f32 {
+        self.cutoff_hz.min(sample_rate * MAX_CUTOFF_RATIO)
+    }
+
+    pub fn param(&self, id: ParamId) -> f32 {
+        match id {
+            ParamId::Cutoff => self.cutoff_hz,
+            ParamId::Resonance => self.resonance_q,
+        }
+    }
+
+    pub fn set_param(&mut self, id: ParamId, value: f32) {
+        match id {
+            ParamId::Cutoff => self.cutoff_hz = value,
+            ParamId::Resonance => self.resonance_q = value,
+        }
+    }
+
+    /// Whether switching between `self` and `other` needs a new filter instance rather than new
+    /// coefficients.
+    pub fn same_structure(&self, other: &FilterParams) -> bool {
+        self.filter == other.filter && self.topology == other.topology && self.fixed == other.fixed
+    }
+}
+
+/// How the first two channels are routed into their filters.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum StereoMode {
+    /// Channel 0 filters left, channel 1 filters right.
+    #[default]
+    LeftRight,
+    /// Left/right are encoded to mid (channel 0) and side (channel 1), filtered, then decoded back.
+    MidSide,
+}
+
+/// Encodes a left/right pair to mid/side. `decode_mid_side` is the exact inverse.
+pub fn encode_mid_side(left: f32, right: f32) -> (f32, f32) {
+    ((left + right) * 0.5, (left - right) * 0.5)
+}
+
+pub fn decode_mid_side(mid: f32, side: f32) -> (f32, f32) {
+    (mid + side, mid - side)
+}
+
+/// Runs one filter instance per channel so every channel keeps its own state. Channels can share
+/// parameters (linked) or be tuned independently.
+pub struct MultiChannelFilter {
+    sample_rate: f32,
+    stereo_mode: StereoMode,
+    params: Vec<FilterParams>,
+    filters: Vec<Box<dyn Filter>>,
+    imported: Option<Arc<ImportedDesign>>,
+    // Times a filter produced NaN or infinity and had to be reset.
+    recoveries: u64,
+}
+
+impl MultiChannelFilter {
+    pub fn new(sample_rate: f32, num_channels: usize, params: FilterParams) -> Self {
+        Self {
+            sample_rate,
+            stereo_mode: StereoMode::LeftRight,
+            params: vec![params; num_channels],
+            filters: (0..num_channels)
+                .map(|_| params.create_filter(sample_rate))
+                .collect(),
+            imported: None,
+            recoveries: 0,
+        }
+    }
+
+    fn create(&self, params: &FilterParams) -> Box<dyn Filter> {
+        params.create_filter_with(self.sample_rate, self.imported.as_deref())
+    }
+
+    /// Replaces the design used by channels set to `SelectedFilter::Imported`.
+    pub fn set_imported(&mut self, imported: Option<Arc<ImportedDesign>>) {
+        self.imported = imported;
+        for channel in 0..self.num_channels() {
+            if self.params[channel].filter == SelectedFilter::Imported {
+                self.filters[channel] = self.create(&self.params[channel]);
+            }
+        }
+    }
+
+    pub fn num_channels(&self) -> usize {
+        self.filters.len()
+    }
+
+    pub fn sample_rate(&self) -> f32 {
+        self.sample_rate
+    }
+
+    /// Adapts to a new stream format while keeping every channel's parameters. Filters are only
+    /// rebuilt when the sample rate changes; added channels copy the last channel's parameters.
+    pub fn set_format(&mut self, sample_rate: f32, num_channels: usize) {
+        if sample_rate != self.sample_rate {
+            self.sample_rate = sample_rate;
+            self.filters = self
+                .params
+                .iter()
+                .map(|params| self.create(params))
+                .collect();
+        }
+
+        let last = self.params.last().copied().unwrap_or_default();
+        self.params.resize(num_channels, last);
+        self.filters.truncate(num_channels);
+        while self.filters.len() < num_channels {
+            self.filters.push(self.create(&last));
+        }
+    }
+
+    pub fn stereo_mode(&self) -> StereoMode {
+        self.stereo_mode
+    }
+
+    /// Switches between L/R and M/S routing. The filters are reset because their state no longer
+    /// describes the signal they will see.
+    pub fn set_stereo_mode(&mut self, stereo_mode: StereoMode) {
+        if self.stereo_mode != stereo_mode {
+            self.stereo_mode = stereo_mode;
+            self.reset();
+        }
+    }
+
+    pub fn params(&self, channel: usize) -> Option<FilterParams> {
+        self.params.get(channel).copied()
+    }
+
+    /// Updates the parameters of a single channel. Channels the device doesn't have are ignored.
+    pub fn set_params(&mut self, channel: usize, params: FilterParams) {
+        let Some(current) = self.params.get_mut(channel) else {
+            return;
+        };
+
+        if !current.same_structure(&params) {
+            self.filters[channel] = self.create(&params);
+        } else if *current != params {
+            self.filters[channel]
+                .update_coefficients(params.cutoff_at(self.sample_rate), params.resonance_q);
+        }
+        self.params[channel] = params;
+    }
+
+    /// Applies the same parameters to every channel.
+    pub fn set_all_params(&mut self, params: FilterParams) {
+        for channel in 0..self.num_channels() {
+            self.set_params(channel, params);
+        }
+    }
+
+    /// Applies `update` to a copy of each channel's parameters, e.g. to change only the cutoff.
+    pub fn modify_params(&mut self, channel: Option<usize>, update: impl Fn(&mut FilterParams)) {
+        let channels = match channel {
+            Some(channel) => channel..channel + 1,
+            None => 0..self.num_channels(),
+        };
+
+        for channel in channels {
+            if let Some(mut params) = self.params(channel) {
+                update(&mut params);
+                self.set_params(channel, params);
+            }
+        }
+    }
+
+    /// Clears every channel's filter state, keeping the parameters.
+    pub fn reset(&mut self) {
+        for filter in self.filters.iter_mut() {
+            filter.reset();
+        }
+    }
+
+    /// How many times a filter blew up to NaN or infinity and was reset by `render_frame`.
+    pub fn recoveries(&self) -> u64 {
+        self.recoveries
+    }
+
+    /// Filters one interleaved frame in place, each sample through its own channel's filter.
+    /// A filter whose output is not finite is reset and outputs silence for that sample, so a
+    /// blow-up costs a click rather than the rest of the stream.
+    pub fn render_frame(&mut self, frame: &mut [f32]) {
+        let mid_side = self.stereo_mode == StereoMode::MidSide && frame.len() >= 2;
+
+        if mid_side {
+            (frame[0], frame[1]) = encode_mid_side(frame[0], frame[1]);
+        }
+
+        for (channel, sample) in frame.iter_mut().enumerate().take(self.filters.len()) {
+            *sample = self.filters[channel].render(*sample);
+            if !sample.is_finite() {
+                self.filters[channel].reset();
+                self.recoveries += 1;
+                *sample = 0.0;
+            }
+        }
+
+        if mid_side {
+            (frame[0], frame[1]) = decode_mid_side(frame[0], frame[1]);
+        }
+    }
+}
@@ -0,0 +1,342 @@
+//! Dumps filter coefficients as source code or CSV for use outside this app, e.g. on embedded
+//! targets.
+
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+
+use crate::registry::ParamId;
+use crate::{BiQuadCoefficients, Coefficients, FilterParams, ImportedDesign, Sample};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ExportFormat {
+    CHeader,
+    RustConst,
+    Csv,
+}
+
+impl ExportFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::CHeader => "h",
+            ExportFormat::RustConst => "rs",
+            ExportFormat::Csv => "csv",
+        }
+    }
+}
+
+/// Floating point type the coefficients are declared as in generated code.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Precision {
+    Single,
+    Double,
+}
+
+impl Precision {
+    fn c_type(self) -> &'static str {
+        match self {
+            Precision::Single => "float",
+            Precision::Double => "double",
+        }
+    }
+
+    fn rust_type(self) -> &'static str {
+        match self {
+            Precision::Single => "f32",
+            Precision::Double => "f64",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExportOptions {
+    pub format: ExportFormat,
+    pub precision: Precision,
+    /// Digits after the decimal point, in scientific notation.
+    pub digits: usize,
+    /// Prefix for the generated identifiers.
+    pub name: String,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            format: ExportFormat::CHeader,
+            precision: Precision::Single,
+            digits: 9,
+            name: "filter".to_string(),
+        }
+    }
+}
+
+// The coefficients as flat values, a list, or a table, which is all the output formats need.
+// Values are held as `f64` so double precision exports keep every digit.
+enum Values {
+    Scalars(Vec<(&'static str, f64)>),
+    Taps(Vec<f64>),
+    Sections(Vec<[f64; 5]>),
+}
+
+const SECTION_COLUMNS: [&str; 5] = ["b0", "b1", "b2", "a1", "a2"];
+
+fn section_row<T: Sample>(section: &BiQuadCoefficients<T>) -> [f64; 5] {
+    [section.b0, section.b1, section.b2, section.a1, section.a2].map(|value| value.as_f64())
+}
+
+fn values<T: Sample>(coefficients: &Coefficients<T>) -> Values {
+    match coefficients {
+        Coefficients::BiQuad(section) => Values::Scalars(
+            SECTION_COLUMNS
+                .iter()
+                .copied()
+                .zip(section_row(section))
+                .collect(),
+        ),
+        Coefficients::StateVariable { g, k, a1, a2, a3 } => Values::Scalars(vec![
+            ("g", g.as_f64()),
+            ("k", k.as_f64()),
+            ("a1", a1.as_f64()),
+            ("a2", a2.as_f64()),
+            ("a3", a3.as_f64()),
+        ]),
+        Coefficients::StateVariableTPT { g, h, r2 } => Values::Scalars(vec![
+            ("g", g.as_f64()),
+            ("h", h.as_f64()),
+            ("r2", r2.as_f64()),
+        ]),
+        Coefficients::Fir(taps) => Values::Taps(taps.iter().map(|tap| tap.as_f64()).collect()),
+        Coefficients::Cascade(sections) => {
+            Values::Sections(sections.iter().map(section_row).collect())
+        }
+    }
+}
+
+/// Turns a user supplied name into something usable as a C and Rust identifier.
+fn identifier(name: &str) -> String {
+    let mut ident: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if ident.is_empty() || ident.starts_with(|c: char| c.is_ascii_digit()) {
+        ident.insert_str(0, "filter_");
+    }
+    ident
+}
+
+fn number(value: f64, options: &ExportOptions) -> String {
+    format!("{:.*e}", options.digits, value)
+}
+
+/// The filter type and the parameters it was designed with, which are only the ones the filter
+/// responds to, with the cutoff as clamped for `sample_rate`.
+fn description(params: &FilterParams, sample_rate: u32) -> String {
+    let mut parts = vec![format!("{:?}", params.filter)];
+    for descriptor in params.filter.info().params {
+        let value = match descriptor.id {
+            ParamId::Cutoff => params.cutoff_at(sample_rate as f32),
+            id => params.param(id),
+        };
+        parts.push(format!("{} {}", descriptor.label(), value));
+    }
+    parts.push(format!("sample rate {} Hz", sample_rate));
+    parts.join(", ")
+}
+
+/// Renders the coefficients of the filter built from `params` at `sample_rate` in the chosen
+/// format. The filter is designed at the export precision, so double precision output isn't
+/// rounded through `f32`. `imported` is the design used for `SelectedFilter::Imported`.
+pub fn export_coefficients(
+    params: &FilterParams,
+    imported: Option<&ImportedDesign>,
+    sample_rate: u32,
+    options: &ExportOptions,
+) -> String {
+    let values = match options.precision {
+        Precision::Single => values(
+            &params
+                .create_filter_as::<f32>(sample_rate as f32, imported)
+                .coefficients(),
+        ),
+        Precision::Double => values(
+            &params
+                .create_filter_as::<f64>(sample_rate as f32, imported)
+                .coefficients(),
+        ),
+    };
+
+    match options.format {
+        ExportFormat::CHeader => c_header(params, sample_rate, &values, options),
+        ExportFormat::RustConst => rust_const(params, sample_rate, &values, options),
+        ExportFormat::Csv => csv(params, sample_rate, &values, options),
+    }
+}
+
+fn c_header(
+    params: &FilterParams,
+    sample_rate: u32,
+    values: &Values,
+    options: &ExportOptions,
+) -> String {
+    let prefix = identifier(&options.name).to_uppercase();
+    let ty = options.precision.c_type();
+    let suffix = match options.precision {
+        Precision::Single => "f",
+        Precision::Double => "",
+    };
+    let num = |value: f64| format!("{}{}", number(value, options), suffix);
+
+    let mut out = String::new();
+    _ = writeln!(out, "/* {} */", description(params, sample_rate));
+    _ = writeln!(out, "#ifndef {}_COEFFICIENTS_H", prefix);
+    _ = writeln!(out, "#define {}_COEFFICIENTS_H", prefix);
+    _ = writeln!(out);
+    _ = writeln!(out, "#define {}_SAMPLE_RATE {}", prefix, sample_rate);
+    _ = writeln!(out);
+
+    match values {
+        Values::Scalars(scalars) => {
+            for (name, value) in scalars {
+                _ = writeln!(
+                    out,
+                    "static const {} {}_{} = {};",
+                    ty,
+                    prefix,
+                    name.to_uppercase(),
+                    num(*value)
+                );
+            }
+        }
+        Values::Taps(taps) => {
+            let taps: Vec<String> = taps.iter().map(|tap| num(*tap)).collect();
+            _ = writeln!(out, "#define {}_NUM_TAPS {}", prefix, taps.len());
+            _ = writeln!(
+                out,
+                "static const {} {}_TAPS[{}_NUM_TAPS] = {{ {} }};",
+                ty,
+                prefix,
+                prefix,
+                taps.join(", ")
+            );
+        }
+        Values::Sections(sections) => {
+            _ = writeln!(
+                out,
+                "/* One row per section: b0, b1, b2, a1, a2 (a0 = 1). */"
+            );
+            _ = writeln!(out, "#define {}_NUM_SECTIONS {}", prefix, sections.len());
+            _ = writeln!(
+                out,
+                "static const {} {}_SOS[{}_NUM_SECTIONS][5] = {{",
+                ty, prefix, prefix
+            );
+            for section in sections {
+                let row: Vec<String> = section.iter().map(|value| num(*value)).collect();
+                _ = writeln!(out, "    {{ {} }},", row.join(", "));
+            }
+            _ = writeln!(out, "}};");
+        }
+    }
+
+    _ = writeln!(out);
+    _ = writeln!(out, "#endif");
+    out
+}
+
+fn rust_const(
+    params: &FilterParams,
+    sample_rate: u32,
+    values: &Values,
+    options: &ExportOptions,
+) -> String {
+    let prefix = identifier(&options.name).to_uppercase();
+    let ty = options.precision.rust_type();
+    let num = |value: f64| number(value, options);
+
+    let mut out = String::new();
+    _ = writeln!(out, "// {}", description(params, sample_rate));
+    _ = writeln!(
+        out,
+        "pub const {}_SAMPLE_RATE: u32 = {};",
+        prefix, sample_rate
+    );
+
+    match values {
+        Values::Scalars(scalars) => {
+            for (name, value) in scalars {
+                _ = writeln!(
+                    out,
+                    "pub const {}_{}: {} = {};",
+                    prefix,
+                    name.to_uppercase(),
+                    ty,
+                    num(*value)
+                );
+            }
+        }
+        Values::Taps(taps) => {
+            let taps: Vec<String> = taps.iter().map(|tap| num(*tap)).collect();
+            _ = writeln!(
+                out,
+                "pub const {}_TAPS: [{}; {}] = [{}];",
+                prefix,
+                ty,
+                taps.len(),
+                taps.join(", ")
+            );
+        }
+        Values::Sections(sections) => {
+            _ = writeln!(out, "/// One row per section: b0, b1, b2, a1, a2 (a0 = 1).");
+            _ = writeln!(
+                out,
+                "pub const {}_SOS: [[{}; 5]; {}] = [",
+                prefix,
+                ty,
+                sections.len()
+            );
+            for section in sections {
+                let row: Vec<String> = section.iter().map(|value| num(*value)).collect();
+                _ = writeln!(out, "    [{}],", row.join(", "));
+            }
+            _ = writeln!(out, "];");
+        }
+    }
+
+    out
+}
+
+fn csv(
+    params: &FilterParams,
+    sample_rate: u32,
+    values: &Values,
+    options: &ExportOptions,
+) -> String {
+    let num = |value: f64| number(value, options);
+
+    // Metadata goes in `#` comments, which numpy, pandas and Octave can all be told to skip.
+    let mut out = String::new();
+    _ = writeln!(out, "# {}", description(params, sample_rate));
+    _ = writeln!(out, "# sample_rate,{}", sample_rate);
+
+    match values {
+        Values::Scalars(scalars) => {
+            _ = writeln!(out, "name,value");
+            for (name, value) in scalars {
+                _ = writeln!(out, "{},{}", name, num(*value));
+            }
+        }
+        Values::Taps(taps) => {
+            _ = writeln!(out, "index,tap");
+            for (index, tap) in taps.iter().enumerate() {
+                _ = writeln!(out, "{},{}", index, num(*tap));
+            }
+        }
+        Values::Sections(sections) => {
+            _ = writeln!(out, "section,{}", SECTION_COLUMNS.join(","));
+            for (index, section) in sections.iter().enumerate() {
+                let row: Vec<String> = section.iter().map(|value| num(*value)).collect();
+                _ = writeln!(out, "{},{}", index, row.join(","));
+            }
+        }
+    }
+
+    out
+}
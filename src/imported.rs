@@ -0,0 +1,293 @@
+//! Filters designed elsewhere (scipy, Octave, ...) and loaded from text files.
+//!
+//! Two layouts are understood, with values separated by commas, semicolons or whitespace:
+//!
+//! * Second-order sections: one section per row as `b0 b1 b2 a0 a1 a2`, the layout of scipy's
+//!   `sos` arrays and Octave's `tf2sos`. Sections are normalized by their `a0`.
+//! * FIR taps: a single column or a single row of numbers.
+//!
+//! Lines starting with `#` or `%` are comments, and `[`/`]` are ignored so pasted numpy or Octave
+//! matrices load as-is. A header row naming the columns (as written by the coefficient export) is
+//! also accepted: `b0,b1,b2,a1,a2` columns are read as pre-normalized sections, a `tap` column as
+//! FIR taps, and other columns (such as an index) are skipped. A `name,value` header, which the
+//! export writes for a single filter, is read as one section from its `b0`, `b1`, `b2`, `a1` and
+//! `a2` rows; state variable exports have no such rows and can't be imported.
+
+use std::fmt;
+use std::path::Path;
+
+use crate::{BiQuadCoefficients, Filter, FirFilter, Sample, SosFilter};
+
+/// Coefficients loaded from a file, ready to be turned into filters. Kept in `f64`, the
+/// precision scipy and Octave write them in, and only rounded when a filter is built.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImportedDesign {
+    Sos(Vec<BiQuadCoefficients<f64>>),
+    Fir(Vec<f64>),
+}
+
+#[derive(Debug)]
+pub enum ImportError {
+    Io(std::io::Error),
+    Empty,
+    InvalidNumber {
+        line: usize,
+        value: String,
+    },
+    RaggedRows {
+        line: usize,
+    },
+    MissingColumns {
+        line: usize,
+        columns: usize,
+        header: usize,
+    },
+    UnsupportedShape {
+        rows: usize,
+        columns: usize,
+    },
+    ZeroA0 {
+        section: usize,
+    },
+    MissingValue {
+        name: &'static str,
+    },
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportError::Io(err) => write!(f, "{}", err),
+            ImportError::Empty => write!(f, "no coefficients found"),
+            ImportError::InvalidNumber { line, value } => {
+                write!(f, "line {}: \"{}\" is not a number", line, value)
+            }
+            ImportError::RaggedRows { line } => {
+                write!(f, "line {}: rows have different numbers of columns", line)
+            }
+            ImportError::MissingColumns {
+                line,
+                columns,
+                header,
+            } => write!(
+                f,
+                "line {}: {} columns but the header names {}",
+                line, columns, header
+            ),
+            ImportError::UnsupportedShape { rows, columns } => write!(
+                f,
+                "{} rows of {} columns is neither SOS (6 columns) nor a list of FIR taps",
+                rows, columns
+            ),
+            ImportError::ZeroA0 { section } => write!(f, "section {} has a0 = 0", section),
+            ImportError::MissingValue { name } => {
+                write!(
+                    f,
+                    "no {} value; only b0, b1, b2, a1 and a2 can be imported",
+                    name
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+impl From<std::io::Error> for ImportError {
+    fn from(err: std::io::Error) -> Self {
+        ImportError::Io(err)
+    }
+}
+
+fn fields(line: &str) -> impl Iterator<Item = &str> {
+    line.split(|c: char| c == ',' || c == ';' || c.is_whitespace())
+        .map(|field| field.trim_matches(|c| c == '[' || c == ']'))
+        .filter(|field| !field.is_empty())
+}
+
+fn number(line: usize, value: &str) -> Result<f64, ImportError> {
+    value.parse().map_err(|_| ImportError::InvalidNumber {
+        line,
+        value: value.to_string(),
+    })
+}
+
+impl ImportedDesign {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ImportError> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, ImportError> {
+        let mut header: Option<Vec<String>> = None;
+        let mut rows: Vec<Vec<f64>> = Vec::new();
+        let mut named: Vec<(String, f64)> = Vec::new();
+
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with('%') {
+                continue;
+            }
+
+            let values: Vec<&str> = fields(line).collect();
+            if values.is_empty() {
+                continue;
+            }
+
+            // A non-numeric first row names the columns.
+            if rows.is_empty() && header.is_none() && values[0].parse::<f64>().is_err() {
+                header = Some(values.iter().map(|v| v.to_lowercase()).collect());
+                continue;
+            }
+
+            // One coefficient per row rather than per column.
+            if header
+                .as_ref()
+                .is_some_and(|header| header[..] == ["name", "value"])
+            {
+                if values.len() < 2 {
+                    return Err(ImportError::MissingColumns {
+                        line: index + 1,
+                        columns: values.len(),
+                        header: 2,
+                    });
+                }
+                named.push((values[0].to_lowercase(), number(index + 1, values[1])?));
+                continue;
+            }
+
+            let row = values
+                .iter()
+                .map(|value| number(index + 1, value))
+                .collect::<Result<Vec<f64>, _>>()?;
+
+            if rows.first().is_some_and(|first| first.len() != row.len()) {
+                return Err(ImportError::RaggedRows { line: index + 1 });
+            }
+            if let Some(header) = header.as_ref().filter(|header| row.len() < header.len()) {
+                return Err(ImportError::MissingColumns {
+                    line: index + 1,
+                    columns: row.len(),
+                    header: header.len(),
+                });
+            }
+            rows.push(row);
+        }
+
+        if !named.is_empty() {
+            return Self::from_named_values(&named);
+        }
+        if rows.is_empty() {
+            return Err(ImportError::Empty);
+        }
+
+        match header {
+            Some(header) => Self::from_named_columns(&header, &rows),
+            None => Self::from_shape(&rows),
+        }
+    }
+
+    fn from_named_columns(header: &[String], rows: &[Vec<f64>]) -> Result<Self, ImportError> {
+        let column = |name: &str| header.iter().position(|h| h == name);
+
+        if let Some(tap) = column("tap") {
+            return Ok(ImportedDesign::Fir(
+                rows.iter().map(|row| row[tap]).collect(),
+            ));
+        }
+
+        let names = ["b0", "b1", "b2", "a1", "a2"];
+        let Some(indices) = names.iter().map(|n| column(n)).collect::<Option<Vec<_>>>() else {
+            return Self::from_shape(rows);
+        };
+        let a0 = column("a0");
+
+        rows.iter()
+            .enumerate()
+            .map(|(section, row)| {
+                let coefficients = [
+                    row[indices[0]],
+                    row[indices[1]],
+                    row[indices[2]],
+                    a0.map_or(1.0, |a0| row[a0]),
+                    row[indices[3]],
+                    row[indices[4]],
+                ];
+                normalize(section, coefficients)
+            })
+            .collect::<Result<_, _>>()
+            .map(ImportedDesign::Sos)
+    }
+
+    fn from_named_values(values: &[(String, f64)]) -> Result<Self, ImportError> {
+        let value = |name: &str| values.iter().find(|(n, _)| n == name).map(|&(_, v)| v);
+        let coefficient = |name| value(name).ok_or(ImportError::MissingValue { name });
+
+        let section = [
+            coefficient("b0")?,
+            coefficient("b1")?,
+            coefficient("b2")?,
+            value("a0").unwrap_or(1.0),
+            coefficient("a1")?,
+            coefficient("a2")?,
+        ];
+        normalize(0, section).map(|section| ImportedDesign::Sos(vec![section]))
+    }
+
+    fn from_shape(rows: &[Vec<f64>]) -> Result<Self, ImportError> {
+        let columns = rows[0].len();
+
+        if columns == 6 {
+            rows.iter()
+                .enumerate()
+                .map(|(section, row)| {
+                    normalize(section, [row[0], row[1], row[2], row[3], row[4], row[5]])
+                })
+                .collect::<Result<_, _>>()
+                .map(ImportedDesign::Sos)
+        } else if columns == 1 {
+            Ok(ImportedDesign::Fir(rows.iter().map(|row| row[0]).collect()))
+        } else if rows.len() == 1 {
+            Ok(ImportedDesign::Fir(rows[0].clone()))
+        } else {
+            Err(ImportError::UnsupportedShape {
+                rows: rows.len(),
+                columns,
+            })
+        }
+    }
+
+    /// A short description for the UI.
+    pub fn summary(&self) -> String {
+        match self {
+            ImportedDesign::Sos(sections) => format!("{} second-order section(s)", sections.len()),
+            ImportedDesign::Fir(taps) => format!("FIR, {} taps", taps.len()),
+        }
+    }
+
+    pub fn create_filter<T: Sample>(&self) -> Box<dyn Filter<T>> {
+        match self {
+            ImportedDesign::Sos(sections) => Box::new(SosFilter::new(
+                sections.iter().map(|section| section.cast()).collect(),
+            )),
+            ImportedDesign::Fir(taps) => Box::new(FirFilter::new(
+                taps.iter().map(|&tap| T::cast(tap)).collect(),
+            )),
+        }
+    }
+}
+
+fn normalize(
+    section: usize,
+    [b0, b1, b2, a0, a1, a2]: [f64; 6],
+) -> Result<BiQuadCoefficients<f64>, ImportError> {
+    if a0 == 0.0 {
+        return Err(ImportError::ZeroA0 { section });
+    }
+    Ok(BiQuadCoefficients {
+        b0: b0 / a0,
+        b1: b1 / a0,
+        b2: b2 / a0,
+        a1: a1 / a0,
+        a2: a2 / a0,
+    })
+}
@@ -1,268 +1,86 @@
+//! The filter playground: the egui app and audio engine (behind the default `gui` feature), plus
+//! analysis, import/export and presets. The filters themselves live in `filters-core` and are
+//! re-exported here.
+
 #![warn(clippy::all, rust_2018_idioms)]
 
-mod app;
+use serde::{Deserialize, Serialize};
 
-pub use app::AudioFilterApp;
+#[cfg(feature = "gui")]
+pub mod app;
+#[cfg(feature = "gui")]
+pub mod audio;
+pub mod channel;
+pub mod export;
+pub mod imported;
+pub mod preset;
+pub mod quantization;
+pub mod registry;
+pub mod response;
+pub mod source;
+
+pub use filters_core::*;
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg(feature = "gui")]
+pub use app::AudioFilterApp;
+pub use channel::{FilterParams, MultiChannelFilter, StereoMode};
+pub use imported::ImportedDesign;
+pub use preset::{Preset, PresetBank, PresetFile};
+pub use registry::{FilterInfo, FILTERS};
+pub use response::FilterResponse;
+pub use source::{AudioClip, SelectedSource, SourcePlayer};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum SelectedFilter {
     BiQuad,
     FirLowPass,
     FirHighPass,
     StateVariableTPT,
     StateVariable,
-}
-
-pub enum FilterType {
-    LowPass,
-    HighPass,
-    BandPass,
-}
-
-pub trait Filter: Send {
-    fn reset(&mut self);
-    fn render(&mut self, input_sample: f32) -> f32;
-    fn update_coefficients(&mut self, cutoff_freq: f32, reso: f32);
-}
-
-pub struct BiQuadFilter {
-    sample_rate: f32,
-    b0: f32,
-    b1: f32,
-    b2: f32,
-    a0: f32,
-    a1: f32,
-    a2: f32,
-    x1: f32,
-    x2: f32,
-    y1: f32,
-    y2: f32,
-}
-
-impl BiQuadFilter {
-    pub fn new(sample_rate: f32) -> Self {
-        Self {
-            sample_rate,
-            b0: 1.0,
-            a0: 1.0,
-            a1: 0.0,
-            a2: 0.0,
-            b1: 0.0,
-            b2: 0.0,
-            x1: 0.0,
-            x2: 0.0,
-            y1: 0.0,
-            y2: 0.0,
+    /// Coefficients loaded from a file, see `ImportedDesign`.
+    Imported,
+    /// `BiQuad` in Q15/Q31 fixed point.
+    FixedBiQuad,
+    /// `StateVariable` in Q15/Q31 fixed point.
+    FixedStateVariable,
+}
+
+impl SelectedFilter {
+    /// Every filter type, in menu order.
+    pub const ALL: [SelectedFilter; FILTERS.len()] = {
+        let mut all = [SelectedFilter::BiQuad; FILTERS.len()];
+        let mut i = 0;
+        while i < all.len() {
+            all[i] = FILTERS[i].filter;
+            i += 1;
         }
+        all
+    };
+
+    /// The filter's entry in the registry.
+    pub fn info(self) -> &'static FilterInfo {
+        FILTERS
+            .iter()
+            .find(|info| info.filter == self)
+            .expect("every filter type is registered")
     }
-}
-
-impl Filter for BiQuadFilter {
-    fn reset(&mut self) {}
-
-    fn update_coefficients(&mut self, cutoff_frequency: f32, resonance: f32) {
-        let w0 = 2.0 * std::f32::consts::PI * cutoff_frequency / self.sample_rate;
-        let alpha = w0.sin() / (2.0 * resonance);
-
-        // Low Pass
-        //self.b0 = (1 - w0.cos()) / 2.0;
-        self.b1 = 1.0 - w0.cos();
-        self.b2 = (1.0 - w0.cos()) / 2.0;
-        self.a0 = 1.0 + alpha;
-        self.a1 = -2.0 * w0.cos();
-        self.a2 = 1.0 - alpha;
-    }
-
-    fn render(&mut self, input_sample: f32) -> f32 {
-        let yn = (self.b0 / self.a0) * input_sample
-            + (self.b1 / self.a0) * self.x1
-            + (self.b2 / self.a0) * self.x2
-            - (self.a1 / self.a0) * self.y1
-            - (self.a2 / self.a0) * self.y2;
-
-        self.x2 = self.x1;
-        self.x1 = input_sample;
-
-        self.y2 = self.y1;
-        self.y1 = yn;
-
-        yn
-    }
-}
-
-pub struct FirLowPassFilter {
-    sample_rate: f32,
-    s1: f32,
-    s2: f32,
-}
-
-impl FirLowPassFilter {
-    pub fn new(sample_rate: f32) -> Self {
-        Self {
-            sample_rate,
-            s1: 0.0,
-            s2: 0.0,
-        }
-    }
-}
-
-impl Filter for FirLowPassFilter {
-    fn reset(&mut self) {}
-    fn update_coefficients(&mut self, _cutoff_freq: f32, _resonance: f32) {}
-
-    // Difference Equation: y[n] = a0.x[n] + a1.x[n-1]
-    fn render(&mut self, input_sample: f32) -> f32 {
-        let y = self.s1 + self.s2 + input_sample;
-
-        // self.s2 = self.s1; // Playing with additional state delays
-        self.s1 = input_sample;
-
-        y
-    }
-}
-
-pub struct FirHighPassFilter {
-    sample_rate: f32,
-    s1: f32,
-    s2: f32,
-}
-
-impl FirHighPassFilter {
-    pub fn new(sample_rate: f32) -> Self {
-        Self {
-            sample_rate,
-            s1: 0.0,
-            s2: 0.0,
-        }
-    }
-}
-
-impl Filter for FirHighPassFilter {
-    fn reset(&mut self) {}
-    fn update_coefficients(&mut self, _cutoff_freq: f32, _resonance: f32) {}
-
-    // Difference Equation: y[n] = a0.x[n] - a1.x[n-1]
-    fn render(&mut self, input_sample: f32) -> f32 {
-        let y = input_sample - self.s1 - self.s2;
-
-        // self.s2 = self.s1; // Playing with additional state delays
-        self.s1 = input_sample;
-
-        y
-    }
-}
-
-// JUCE implementation of juce_StateVariableTPTFilter
-pub struct StateVariableTPTFilter {
-    pub sample_rate: f32,
-    filter_type: FilterType,
-    // Coefficients
-    g: f32,
-    h: f32,
-    r2: f32,
-    // Intermediate State ?
-    s1: f32,
-    s2: f32,
-}
-
-impl StateVariableTPTFilter {
-    pub fn new(sample_rate: f32) -> Self {
-        Self {
-            sample_rate,
-            filter_type: FilterType::BandPass,
-            g: 0.0,
-            h: 0.0,
-            r2: 0.0,
-            s1: 0.0,
-            s2: 0.0,
-        }
-    }
-}
-
-impl Filter for StateVariableTPTFilter {
-    fn reset(&mut self) {
-        self.filter_type = FilterType::BandPass;
-        self.g = 0.0;
-        self.h = 0.0;
-        self.r2 = 0.0;
-        self.s1 = 0.0;
-        self.s2 = 0.0;
-    }
-
-    fn update_coefficients(&mut self, cutoff_freq: f32, resonance: f32) {
-        self.g = (std::f32::consts::PI * cutoff_freq / self.sample_rate).tan();
-        self.r2 = 1.0 / resonance;
-        self.h = 1.0 / (1.0 + self.r2 * self.g + self.g * self.g);
-    }
-
-    fn render(&mut self, input_sample: f32) -> f32 {
-        let y_high_pass = self.h * (input_sample - self.s1 * (self.g + self.r2) - self.s2);
-        let y_band_pass = y_high_pass * self.g + self.s1;
-        self.s1 = y_high_pass * self.g + y_band_pass;
-
-        let y_low_pass = y_band_pass * self.g + self.s2;
-        self.s2 = y_band_pass * self.g + y_low_pass;
-
-        match self.filter_type {
-            FilterType::LowPass => return y_low_pass,
-            FilterType::BandPass => return y_band_pass,
-            FilterType::HighPass => return y_high_pass,
-        }
-    }
-}
-
-pub struct StateVariableFilter {
-    pub sample_rate: f32,
-    g: f32, // cutoff freq
-    k: f32, // resonance
-    a1: f32,
-    a2: f32,
-    a3: f32,
-    ic1eq: f32,
-    ic2eq: f32,
-}
-
-impl StateVariableFilter {
-    pub fn new(sample_rate: f32) -> Self {
-        Self {
-            sample_rate,
-            g: 0.0,
-            k: 0.0,
-            a1: 0.0,
-            a2: 0.0,
-            a3: 0.0,
-            ic1eq: 0.0,
-            ic2eq: 0.0,
-        }
-    }
-}
 
-impl Filter for StateVariableFilter {
-    fn reset(&mut self) {
-        self.g = 0.0;
-        self.k = 0.0;
-        self.a1 = 0.0;
-        self.a2 = 0.0;
-        self.a3 = 0.0;
-        self.ic1eq = 0.0;
-        self.ic2eq = 0.0;
+    pub fn name(self) -> &'static str {
+        self.info().name
     }
 
-    fn update_coefficients(&mut self, cutoff: f32, q: f32) {
-        self.g = (std::f32::consts::PI * cutoff / self.sample_rate).tan();
-        self.k = 1.0 / q;
-        self.a1 = 1.0 / (1.0 + self.g * (self.g + self.k));
-        self.a2 = self.g * self.a1;
-        self.a3 = self.g * self.a2;
+    /// Builds a fresh instance of the selected filter with default coefficients. `Imported` has no
+    /// design to build from here and passes audio through; use `ImportedDesign::create_filter`.
+    pub fn create<T: Sample>(self, sample_rate: f32) -> Box<dyn Filter<T>> {
+        let params = FilterParams {
+            filter: self,
+            ..FilterParams::default()
+        };
+        self.info().create.create(sample_rate, &params, None)
     }
 
-    fn render(&mut self, sample: f32) -> f32 {
-        // v1..v3 are voltages at different nodes
-        let v3 = sample - self.ic2eq;
-        let v1 = self.a1 * self.ic1eq + self.a2 * v3;
-        let v2 = self.ic2eq + self.a2 * self.ic1eq + self.a3 * v3; // lp
-        self.ic1eq = 2.0 * v1 - self.ic1eq; // state of capacitors
-        self.ic2eq = 2.0 * v2 - self.ic2eq; // state of capacitors
-        v2
+    /// The float filter a fixed-point filter implements, to compare against.
+    pub fn float_reference(self) -> Option<SelectedFilter> {
+        self.info().float_reference
     }
 }
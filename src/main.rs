@@ -2,184 +2,33 @@
 //#![cfg_attr(not(debug_assertions), windows_subsystem = "windows")] // hide console window on
 // Windows in release mode
 
-use atomic_float::AtomicF32;
-use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
-use std::sync::atomic::Ordering;
 use std::sync::mpsc::channel;
-use std::sync::{Arc, Mutex};
+#[cfg(target_arch = "wasm32")]
+use std::sync::mpsc::Receiver;
 
-mod app;
-use filters::{
-    BiQuadFilter, Filter, FirHighPassFilter, FirLowPassFilter, SelectedFilter, StateVariableFilter,
-    StateVariableTPTFilter,
-};
-
-use crate::app::{AudioCommand, AudioFilterApp};
-
-pub struct NoiseGen {
-    noise_seed: u32,
-}
-
-impl NoiseGen {
-    pub fn new() -> Self {
-        Self { noise_seed: 22222 }
-    }
-
-    pub fn reset(&mut self) {
-        self.noise_seed = 22222;
-    }
-
-    pub fn next_value(&mut self) -> f32 {
-        self.noise_seed = self.noise_seed * 196314165 + 907633515;
-        let tmp = ((self.noise_seed >> 7) as i32) - 16777216;
-        tmp as f32 / 16777216.0f32
-    }
-}
+use filters::app::{AudioCommand, AudioFilterApp};
+use filters::audio::{AudioEngine, AudioEvent, StreamSettings};
+use filters::{FilterParams, SelectedFilter};
 
 #[cfg(not(target_arch = "wasm32"))]
 fn main() -> eframe::Result {
     env_logger::init();
 
-    let mut cutoff_freq_hz = 1000.0;
-    let mut resonance_q = 0.707;
-    let mut selected_filter = SelectedFilter::StateVariable;
-    let svf: Arc<Mutex<Box<dyn Filter>>> =
-        Arc::new(Mutex::new(Box::new(StateVariableFilter::new(44100.0))));
-
-    {
-        let mut f = svf.lock().unwrap();
-        f.reset();
-        f.update_coefficients(cutoff_freq_hz, resonance_q);
-    }
+    let params = FilterParams {
+        filter: SelectedFilter::StateVariable,
+        ..Default::default()
+    };
+    let volume = AudioFilterApp::new().vol;
 
-    // I don't want to store everything in a mutex, so at least for now keep the volume as an Atomic and pass the filter behind
-    // an Arc<Mutex>
-    let volume = std::sync::Arc::new(AtomicF32::new(0.3));
-    let (ui_tx, ui_rx) = channel::<crate::app::AudioCommand>();
+    let (ui_tx, ui_rx) = channel::<AudioCommand>();
+    let (audio_tx, audio_rx) = channel::<AudioEvent>();
 
     let _audio_thread = std::thread::spawn(move || {
-        let volume_clone = volume.clone();
-        let filter = svf.clone();
-        let selected_filter = selected_filter.clone();
-        let sample_rate = 44100;
-
-        let host = cpal::default_host();
-
-        let device = host
-            .default_output_device()
-            .expect("Failed to get default output device");
-        let config = device
-            .default_output_config()
-            .expect("Failed to get default device config");
-
-        let stream = match config.sample_format() {
-            cpal::SampleFormat::I8 => {
-                make_stream::<i8>(&device, &config.into(), volume_clone, filter)
-            }
-            cpal::SampleFormat::I16 => {
-                make_stream::<i16>(&device, &config.into(), volume_clone, filter)
-            }
-            cpal::SampleFormat::I32 => {
-                make_stream::<i32>(&device, &config.into(), volume_clone, filter)
-            }
-            cpal::SampleFormat::I64 => {
-                make_stream::<i64>(&device, &config.into(), volume_clone, filter)
-            }
-            cpal::SampleFormat::U8 => {
-                make_stream::<u8>(&device, &config.into(), volume_clone, filter)
-            }
-            cpal::SampleFormat::U16 => {
-                make_stream::<u16>(&device, &config.into(), volume_clone, filter)
-            }
-            cpal::SampleFormat::U32 => {
-                make_stream::<u32>(&device, &config.into(), volume_clone, filter)
-            }
-            cpal::SampleFormat::U64 => {
-                make_stream::<u64>(&device, &config.into(), volume_clone, filter)
-            }
-            cpal::SampleFormat::F32 => {
-                make_stream::<f32>(&device, &config.into(), volume_clone, filter)
-            }
-            cpal::SampleFormat::F64 => {
-                make_stream::<f64>(&device, &config.into(), volume_clone, filter)
-            }
-            _sample_format => panic!("Unexpected sample format!!!"),
-        };
-
-        // If the stream is disposed of, audio is DONE. The stream needs to be alive for the duration of the app.
-        // This is okay because the thread is looping forever to try and read commands from the UI, so it'll never stop.
-        // I wonder if the stream could instead be stored in the App's state...?
-        stream.play().unwrap();
+        let mut engine = AudioEngine::new(params, volume, audio_tx);
+        engine.request(StreamSettings::default());
 
-        // make updates
-        loop {
-            match ui_rx.try_recv() {
-                Ok(cmd) => match cmd {
-                    AudioCommand::SetVolume(new_vol) => {
-                        volume.store(new_vol, Ordering::Relaxed);
-                    }
-                    AudioCommand::SetFilterFreq(cuttoff_freq_hz_new) => {
-                        cutoff_freq_hz = cuttoff_freq_hz_new;
-                        {
-                            let mut filter = svf.lock().unwrap();
-                            filter.update_coefficients(cutoff_freq_hz, resonance_q);
-                        }
-                    }
-                    AudioCommand::SetResonance(resonance_q_new) => {
-                        resonance_q = resonance_q_new;
-                        {
-                            let mut filter = svf.lock().unwrap();
-                            filter.update_coefficients(cutoff_freq_hz, resonance_q);
-                        }
-                    }
-                    AudioCommand::SetSelectedFilter(sel_fil) => {
-                        match sel_fil {
-                            SelectedFilter::BiQuad => {
-                                let mut f = BiQuadFilter::new(sample_rate as f32);
-                                f.update_coefficients(cutoff_freq_hz, resonance_q);
-                                {
-                                    let mut filter = svf.lock().unwrap();
-                                    *filter = Box::new(f);
-                                }
-                            }
-                            SelectedFilter::FirLowPass => {
-                                let mut f = FirLowPassFilter::new(sample_rate as f32);
-                                f.update_coefficients(cutoff_freq_hz, resonance_q);
-                                {
-                                    let mut filter = svf.lock().unwrap();
-                                    *filter = Box::new(f);
-                                }
-                            }
-                            SelectedFilter::FirHighPass => {
-                                let mut f = FirHighPassFilter::new(sample_rate as f32);
-                                f.update_coefficients(cutoff_freq_hz, resonance_q);
-                                {
-                                    let mut filter = svf.lock().unwrap();
-                                    *filter = Box::new(f);
-                                }
-                            }
-                            SelectedFilter::StateVariable => {
-                                let mut f = StateVariableFilter::new(sample_rate as f32);
-                                f.update_coefficients(cutoff_freq_hz, resonance_q);
-                                {
-                                    let mut filter = svf.lock().unwrap();
-                                    *filter = Box::new(f);
-                                }
-                            }
-                            SelectedFilter::StateVariableTPT => {
-                                let mut f = StateVariableTPTFilter::new(sample_rate as f32);
-                                f.update_coefficients(cutoff_freq_hz, resonance_q);
-                                {
-                                    let mut filter = svf.lock().unwrap();
-                                    *filter = Box::new(f);
-                                }
-                            }
-                        };
-                    }
-                },
-                Err(_) => (),
-            }
-        }
+        // The engine keeps the stream alive until the UI hangs up.
+        engine.run(ui_rx);
     }); // Audio Thread End
 
     let native_options = eframe::NativeOptions {
@@ -189,78 +38,102 @@ fn main() -> eframe::Result {
         ..Default::default()
     };
 
-    let mut app = AudioFilterApp::new();
-    app.audio_tx = Some(ui_tx);
-    app.selected_filter = selected_filter;
-
     eframe::run_native(
         "Audio Filters",
         native_options,
-        Box::new(|_| Ok(Box::new(app))),
+        Box::new(|cc| {
+            // The audio thread starts from defaults, then catches up with the restored session.
+            let mut app = AudioFilterApp::load(cc.storage);
+            app.audio_tx = Some(ui_tx);
+            app.audio_rx = Some(audio_rx);
+            app.sync_audio();
+            Ok(Box::new(app))
+        }),
     )
 }
 
-fn make_stream<T>(
-    device: &cpal::Device,
-    config: &cpal::StreamConfig,
-    volume: Arc<AtomicF32>,
-    filter: Arc<Mutex<Box<dyn Filter>>>,
-) -> cpal::Stream
-where
-    T: cpal::SizedSample + cpal::FromSample<f32>,
-{
-    let num_channels = config.channels as usize;
-    let mut noise_gen = NoiseGen::new();
-
-    let err_fn = |err| eprintln!("Error building output sound stream {}", err);
-
-    let stream = device
-        .build_output_stream(
-            config,
-            move |output: &mut [T], _: &cpal::OutputCallbackInfo| {
-                // process_frame(output, &mut noise_gen, &mut svf, vol, num_channels)
-                process_frame(
-                    output,
-                    &mut noise_gen,
-                    num_channels,
-                    volume.clone(),
-                    filter.clone(),
-                )
-            },
-            err_fn,
-            None,
-        )
-        .expect("Failed to build output stream...");
-
-    stream
+/// In the browser the audio engine runs on the UI thread: there are no threads to block, and
+/// Web Audio calls back on the main thread anyway.
+#[cfg(target_arch = "wasm32")]
+fn main() {
+    use eframe::wasm_bindgen::JsCast as _;
+
+    eframe::WebLogger::init(log::LevelFilter::Info).ok();
+
+    wasm_bindgen_futures::spawn_local(async {
+        let canvas = eframe::web_sys::window()
+            .and_then(|window| window.document())
+            .and_then(|document| document.get_element_by_id("the_canvas_id"))
+            .and_then(|element| {
+                element
+                    .dyn_into::<eframe::web_sys::HtmlCanvasElement>()
+                    .ok()
+            })
+            .expect("index.html has a canvas with id `the_canvas_id`");
+
+        let params = FilterParams {
+            filter: SelectedFilter::StateVariable,
+            ..Default::default()
+        };
+        let volume = AudioFilterApp::new().vol;
+
+        let (ui_tx, ui_rx) = channel::<AudioCommand>();
+        let (audio_tx, audio_rx) = channel::<AudioEvent>();
+        let engine = AudioEngine::new(params, volume, audio_tx);
+
+        let result = eframe::WebRunner::new()
+            .start(
+                canvas,
+                eframe::WebOptions::default(),
+                Box::new(|cc| {
+                    let mut app = AudioFilterApp::load(cc.storage);
+                    app.audio_tx = Some(ui_tx);
+                    app.audio_rx = Some(audio_rx);
+                    app.sync_audio();
+                    Ok(Box::new(WebApp {
+                        app,
+                        engine,
+                        commands: ui_rx,
+                        started: false,
+                    }))
+                }),
+            )
+            .await;
+        if let Err(err) = result {
+            log::error!("failed to start the app: {:?}", err);
+        }
+    });
 }
 
-fn process_frame<SampleType>(
-    output: &mut [SampleType],
-    noise_gen: &mut NoiseGen,
-    num_channels: usize,
-    volume: Arc<AtomicF32>,
-    filter: Arc<Mutex<Box<dyn Filter>>>,
-) where
-    SampleType: cpal::Sample + cpal::FromSample<f32>,
-{
-    let volume = volume.load(Ordering::Relaxed);
-
-    for frame in output.chunks_mut(num_channels) {
-        let next_noise = noise_gen.next_value();
+/// The app together with the audio engine it drives between frames.
+#[cfg(target_arch = "wasm32")]
+struct WebApp {
+    app: AudioFilterApp,
+    engine: AudioEngine,
+    commands: Receiver<AudioCommand>,
+    /// Browsers keep audio suspended until the user interacts with the page, so the stream is
+    /// opened on the first click or key press.
+    started: bool,
+}
 
-        // TODO: Not great...  I don't like locks in the audio thread as they're unbounded.
-        // Lock for the shortest span possible... just to render the filter's output.
-        let filter_sample: f32;
-        {
-            let mut svf = filter.lock().unwrap();
-            filter_sample = svf.render(next_noise);
-        }
-        let next_sample = filter_sample * volume;
-        let value: SampleType = SampleType::from_sample(next_sample);
+#[cfg(target_arch = "wasm32")]
+impl eframe::App for WebApp {
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        self.app.save(storage);
+    }
 
-        for sample in frame.iter_mut() {
-            *sample = value;
+    fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        if !self.started {
+            if ctx.input(|input| input.pointer.any_pressed() || !input.keys_down.is_empty()) {
+                self.started = true;
+                self.engine.request(StreamSettings::default());
+            } else {
+                egui::TopBottomPanel::top("start_audio").show(ctx, |ui| {
+                    ui.label("Click anywhere to start audio");
+                });
+            }
         }
+        self.app.update(ctx, frame);
+        self.engine.poll(&self.commands);
     }
 }
@@ -0,0 +1,183 @@
+//! Filter presets, kept in the app and shared as preset files.
+//!
+//! A preset file is JSON with a format version, the sample rate the preset was designed at, and
+//! the preset itself:
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "sample_rate": 48000,
+//!   "preset": {
+//!     "name": "Side high-pass",
+//!     "channel_params": [
+//!       { "filter": "StateVariable", "cutoff_hz": 1000.0, "resonance_q": 0.707 },
+//!       { "filter": "FirHighPass", "cutoff_hz": 250.0, "resonance_q": 0.707 }
+//!     ],
+//!     "channels_linked": false,
+//!     "stereo_mode": "MidSide"
+//!   }
+//! }
+//! ```
+//!
+//! `filter` is a `SelectedFilter` variant name, an optional `topology` names the `BiQuadTopology`
+//! used by `BiQuad` filters, and an optional `fixed` holds the word lengths of the fixed-point
+//! filters, e.g. `{ "format": "Q31", "coefficient_bits": 20 }`. Frequencies are in Hz, and
+//! `channel_params` holds left/mid then right/side. Fields missing from a preset take their
+//! default value, so a file written before a field existed still loads. Files newer than
+//! `PRESET_FILE_VERSION` are rejected rather than half-read.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+
+use crate::{FilterParams, StereoMode};
+
+/// Version written to new preset files. Bump it when the format changes in a way old readers
+/// would misinterpret, and teach `PresetFile::from_json` to upgrade the older versions.
+pub const PRESET_FILE_VERSION: u32 = 1;
+
+/// A named filter configuration. Covers everything that shapes the filtering, but not the volume
+/// or the source being filtered.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Preset {
+    pub name: String,
+    #[serde(default)]
+    pub channel_params: [FilterParams; 2],
+    #[serde(default = "linked_by_default")]
+    pub channels_linked: bool,
+    #[serde(default)]
+    pub stereo_mode: StereoMode,
+}
+
+fn linked_by_default() -> bool {
+    true
+}
+
+/// A preset as written to disk.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PresetFile {
+    pub version: u32,
+    /// Sample rate the preset was designed at. Parameters are in Hz so they carry over to other
+    /// rates, but the response near Nyquist will differ.
+    pub sample_rate: u32,
+    pub preset: Preset,
+}
+
+#[derive(Debug)]
+pub enum PresetFileError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    MissingVersion,
+    UnsupportedVersion(u64),
+}
+
+impl fmt::Display for PresetFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PresetFileError::Io(err) => write!(f, "{}", err),
+            PresetFileError::Json(err) => write!(f, "invalid preset file: {}", err),
+            PresetFileError::MissingVersion => write!(f, "preset file has no version"),
+            PresetFileError::UnsupportedVersion(version) => write!(
+                f,
+                "unsupported preset file version {} (this build reads up to {})",
+                version, PRESET_FILE_VERSION
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PresetFileError {}
+
+impl From<std::io::Error> for PresetFileError {
+    fn from(err: std::io::Error) -> Self {
+        PresetFileError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for PresetFileError {
+    fn from(err: serde_json::Error) -> Self {
+        PresetFileError::Json(err)
+    }
+}
+
+impl PresetFile {
+    pub fn new(preset: Preset, sample_rate: u32) -> Self {
+        Self {
+            version: PRESET_FILE_VERSION,
+            sample_rate,
+            preset,
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String, PresetFileError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, PresetFileError> {
+        let value: serde_json::Value = serde_json::from_str(json)?;
+        let version = value
+            .get("version")
+            .and_then(|v| v.as_u64())
+            .ok_or(PresetFileError::MissingVersion)?;
+
+        // Upgrades from older versions go here, before the current format is deserialized.
+        match u32::try_from(version) {
+            Ok(1) => Ok(serde_json::from_value(value)?),
+            _ => Err(PresetFileError::UnsupportedVersion(version)),
+        }
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), PresetFileError> {
+        std::fs::write(path, self.to_json()?)?;
+        Ok(())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, PresetFileError> {
+        Self::from_json(&std::fs::read_to_string(path)?)
+    }
+}
+
+/// Presets kept in the app, with unique names.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PresetBank {
+    presets: Vec<Preset>,
+}
+
+impl PresetBank {
+    pub fn presets(&self) -> &[Preset] {
+        &self.presets
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Preset> {
+        self.presets.iter().find(|preset| preset.name == name)
+    }
+
+    /// Adds a preset, replacing any preset that already has its name.
+    pub fn save(&mut self, preset: Preset) {
+        match self.presets.iter_mut().find(|p| p.name == preset.name) {
+            Some(existing) => *existing = preset,
+            None => self.presets.push(preset),
+        }
+    }
+
+    /// Renames a preset. Returns `false` if there's no preset called `from`, or `to` is empty or
+    /// already taken.
+    pub fn rename(&mut self, from: &str, to: &str) -> bool {
+        if to.is_empty() || (from != to && self.get(to).is_some()) {
+            return false;
+        }
+
+        match self.presets.iter_mut().find(|p| p.name == from) {
+            Some(preset) => {
+                preset.name = to.to_string();
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn delete(&mut self, name: &str) -> Option<Preset> {
+        let index = self.presets.iter().position(|p| p.name == name)?;
+        Some(self.presets.remove(index))
+    }
+}
@@ -0,0 +1,212 @@
+//! How a filter's response and poles move when its coefficients are stored with fewer bits.
+//!
+//! The coefficients a filter runs with (see `Filter::coefficients`) are rounded to signed fixed
+//! point words of N bits. All coefficients of a filter share one format: a sign bit, enough
+//! integer bits for the largest coefficient, and the rest fractional. The structure is kept, so a
+//! state variable filter is quantized in its own `a1..a3` rather than as an equivalent biquad.
+//!
+//! Both designs are then turned into second-order sections to evaluate them exactly, without
+//! simulating the filters.
+
+use realfft::num_complex::Complex;
+
+use crate::{BiQuadCoefficients, Coefficients};
+
+pub const MIN_BITS: u32 = 8;
+pub const MAX_BITS: u32 = 32;
+
+/// How far below its peak the reference response is still counted in `max_deviation_db`. Around
+/// zeros of the response (a low-pass at Nyquist, a high-pass at DC) tiny errors are huge in dB.
+pub const DEVIATION_RANGE_DB: f64 = 60.0;
+
+/// A filter as a transfer function in z.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TransferFunction {
+    /// Second-order sections in cascade.
+    Sections(Vec<BiQuadCoefficients<f64>>),
+    /// An FIR polynomial, with all its poles at the origin.
+    Fir(Vec<f64>),
+}
+
+/// A two-state linear filter `s' = A s + B x, y = C s + D x`.
+struct StateSpace {
+    a: [[f64; 2]; 2],
+    b: [f64; 2],
+    c: [f64; 2],
+    d: f64,
+}
+
+impl StateSpace {
+    /// The equivalent biquad, from the characteristic polynomial of `A` and the adjugate of
+    /// `zI - A`.
+    fn to_section(&self) -> BiQuadCoefficients<f64> {
+        let Self { a, b, c, d } = self;
+        let trace = a[0][0] + a[1][1];
+        let det = a[0][0] * a[1][1] - a[0][1] * a[1][0];
+
+        let cb = c[0] * b[0] + c[1] * b[1];
+        let c_adj_b = -c[0] * a[1][1] * b[0] + c[0] * a[0][1] * b[1] + c[1] * a[1][0] * b[0]
+            - c[1] * a[0][0] * b[1];
+        BiQuadCoefficients {
+            b0: *d,
+            b1: -d * trace + cb,
+            b2: d * det + c_adj_b,
+            a1: -trace,
+            a2: det,
+        }
+    }
+}
+
+impl TransferFunction {
+    /// The transfer function the filter realizes with these coefficients.
+    pub fn new(coefficients: &Coefficients<f64>) -> Self {
+        match coefficients {
+            Coefficients::BiQuad(section) => TransferFunction::Sections(vec![*section]),
+            Coefficients::Cascade(sections) => TransferFunction::Sections(sections.clone()),
+            Coefficients::Fir(taps) => TransferFunction::Fir(taps.clone()),
+            // The low-pass output of `StateVariableFilter::render`.
+            Coefficients::StateVariable { a1, a2, a3, .. } => {
+                TransferFunction::Sections(vec![StateSpace {
+                    a: [[2.0 * a1 - 1.0, -2.0 * a2], [2.0 * a2, 1.0 - 2.0 * a3]],
+                    b: [2.0 * a2, 2.0 * a3],
+                    c: [*a2, 1.0 - a3],
+                    d: *a3,
+                }
+                .to_section()])
+            }
+            // The band-pass output of `StateVariableTPTFilter::render`.
+            Coefficients::StateVariableTPT { g, h, r2 } => {
+                let gh = g * h;
+                let feedback = 1.0 - gh * (g + r2);
+                TransferFunction::Sections(vec![StateSpace {
+                    a: [
+                        [1.0 - 2.0 * gh * (g + r2), -2.0 * gh],
+                        [2.0 * g * feedback, 1.0 - 2.0 * g * gh],
+                    ],
+                    b: [2.0 * gh, 2.0 * g * gh],
+                    c: [feedback, -gh],
+                    d: gh,
+                }
+                .to_section()])
+            }
+        }
+    }
+
+    /// The response at `w` radians per sample.
+    pub fn response(&self, w: f64) -> Complex<f64> {
+        let z1 = Complex::from_polar(1.0, -w);
+        let z2 = z1 * z1;
+        match self {
+            TransferFunction::Sections(sections) => sections
+                .iter()
+                .map(|s| (s.b0 + z1 * s.b1 + z2 * s.b2) / (1.0 + z1 * s.a1 + z2 * s.a2))
+                .product(),
+            TransferFunction::Fir(taps) => taps
+                .iter()
+                .rev()
+                .fold(Complex::new(0.0, 0.0), |sum, tap| sum * z1 + tap),
+        }
+    }
+
+    /// Roots of each section's denominator. FIR filters have none away from the origin.
+    pub fn poles(&self) -> Vec<Complex<f64>> {
+        let TransferFunction::Sections(sections) = self else {
+            return Vec::new();
+        };
+
+        sections
+            .iter()
+            .flat_map(|s| {
+                // z^2 + a1 z + a2 = 0
+                let root = Complex::new(s.a1 * s.a1 - 4.0 * s.a2, 0.0).sqrt();
+                [(-s.a1 + root) / 2.0, (-s.a1 - root) / 2.0]
+            })
+            .collect()
+    }
+}
+
+/// The coefficients the structure multiplies by while running.
+fn values_mut(coefficients: &mut Coefficients<f64>) -> Vec<&mut f64> {
+    match coefficients {
+        Coefficients::BiQuad(s) => vec![&mut s.b0, &mut s.b1, &mut s.b2, &mut s.a1, &mut s.a2],
+        // g and k only describe the design; rendering uses a1..a3.
+        Coefficients::StateVariable { a1, a2, a3, .. } => vec![a1, a2, a3],
+        Coefficients::StateVariableTPT { g, h, r2 } => vec![g, h, r2],
+        Coefficients::Fir(taps) => taps.iter_mut().collect(),
+        Coefficients::Cascade(sections) => sections
+            .iter_mut()
+            .flat_map(|s| [&mut s.b0, &mut s.b1, &mut s.b2, &mut s.a1, &mut s.a2])
+            .collect(),
+    }
+}
+
+/// Rounds every coefficient to a signed `bits` word in a shared fixed-point format.
+pub fn quantize(coefficients: &Coefficients<f64>, bits: u32) -> Coefficients<f64> {
+    let bits = bits.clamp(MIN_BITS, MAX_BITS);
+    let mut quantized = coefficients.clone();
+    let mut values = values_mut(&mut quantized);
+
+    let largest = values.iter().fold(0.0f64, |max, v| max.max(v.abs()));
+    let mut integer_bits = 0;
+    while largest >= (1u64 << integer_bits) as f64 && integer_bits < bits - 1 {
+        integer_bits += 1;
+    }
+    let scale = (1u64 << (bits - 1 - integer_bits)) as f64;
+    let limit = (1u64 << (bits - 1)) as f64;
+
+    for value in values.iter_mut() {
+        **value = ((**value * scale).round()).clamp(-limit, limit - 1.0) / scale;
+    }
+    quantized
+}
+
+/// A design compared with itself after quantization.
+pub struct QuantizationAnalysis {
+    pub bits: u32,
+    /// Quantized minus reference magnitude, in dB, one value per Hz up to Nyquist.
+    pub deviation_db: Vec<f32>,
+    /// Largest deviation where the reference is within `DEVIATION_RANGE_DB` of its peak.
+    pub max_deviation_db: f32,
+    pub reference_poles: Vec<Complex<f64>>,
+    pub quantized_poles: Vec<Complex<f64>>,
+    /// Whether every quantized pole is inside the unit circle.
+    pub stable: bool,
+}
+
+impl QuantizationAnalysis {
+    pub fn new(coefficients: &Coefficients<f64>, bits: u32, sample_rate: usize) -> Self {
+        let reference = TransferFunction::new(coefficients);
+        let quantized = TransferFunction::new(&quantize(coefficients, bits));
+
+        let to_db = |h: Complex<f64>| 20.0 * (h.norm() + 1e-12).log10();
+        let (reference_db, quantized_db): (Vec<f64>, Vec<f64>) = (0..=sample_rate / 2)
+            .map(|hz| {
+                let w = 2.0 * std::f64::consts::PI * hz as f64 / sample_rate as f64;
+                (to_db(reference.response(w)), to_db(quantized.response(w)))
+            })
+            .unzip();
+        let deviation_db: Vec<f32> = quantized_db
+            .iter()
+            .zip(&reference_db)
+            .map(|(quantized, reference)| (quantized - reference) as f32)
+            .collect();
+
+        let floor_db =
+            reference_db.iter().fold(f64::MIN, |max, &db| max.max(db)) - DEVIATION_RANGE_DB;
+        let max_deviation_db = deviation_db
+            .iter()
+            .zip(&reference_db)
+            .filter(|(_, &reference)| reference >= floor_db)
+            .fold(0.0f32, |max, (d, _)| max.max(d.abs()));
+
+        let quantized_poles = quantized.poles();
+        Self {
+            bits,
+            deviation_db,
+            max_deviation_db,
+            reference_poles: reference.poles(),
+            stable: quantized_poles.iter().all(|pole| pole.norm() < 1.0),
+            quantized_poles,
+        }
+    }
+}
@@ -0,0 +1,288 @@
+//! The filter types the app offers, and what the UI and the audio engine need to know about each.
+//!
+//! Every `SelectedFilter` has one `FilterInfo` in `FILTERS` with its name, its constructor and the
+//! parameters it responds to. The filter menu, the parameter sliders, `SelectedFilter::ALL` and
+//! `FilterParams::create_filter` all read it, so adding a filter type is a new variant and its
+//! entry here.
+
+use crate::{
+    BiQuadFilter, Filter, FilterParams, FirHighPassFilter, FirLowPassFilter, FixedBiQuadFilter,
+    FixedStateVariableFilter, ImportedDesign, PerSample, Sample, SelectedFilter, SosFilter,
+    StateVariableFilter, StateVariableTPTFilter,
+};
+
+/// The `FilterParams` field a parameter sets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParamId {
+    Cutoff,
+    Resonance,
+}
+
+impl ParamId {
+    pub const ALL: [ParamId; 2] = [ParamId::Cutoff, ParamId::Resonance];
+}
+
+/// How a control spreads a parameter's range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParamScale {
+    Linear,
+    Logarithmic,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ParamDescriptor {
+    pub id: ParamId,
+    pub name: &'static str,
+    pub unit: &'static str,
+    pub min: f32,
+    pub max: f32,
+    pub default: f32,
+    pub scale: ParamScale,
+}
+
+impl ParamDescriptor {
+    /// Name and unit for a control, e.g. "Frequency (Hz)".
+    pub fn label(&self) -> String {
+        if self.unit.is_empty() {
+            self.name.to_string()
+        } else {
+            format!("{} ({})", self.name, self.unit)
+        }
+    }
+}
+
+/// `CUTOFF.max` is above Nyquist at low sample rates, so filters are built with the cutoff clamped
+/// to this fraction of the sample rate.
+pub use crate::MAX_CUTOFF_RATIO;
+
+pub const CUTOFF: ParamDescriptor = ParamDescriptor {
+    id: ParamId::Cutoff,
+    name: "Frequency",
+    unit: "Hz",
+    min: 20.0,
+    max: 18000.0,
+    default: 1000.0,
+    scale: ParamScale::Logarithmic,
+};
+
+pub const RESONANCE: ParamDescriptor = ParamDescriptor {
+    id: ParamId::Resonance,
+    name: "Resonance",
+    unit: "Q",
+    min: 0.1,
+    max: 30.0,
+    default: 0.707,
+    scale: ParamScale::Logarithmic,
+};
+
+/// Parameters of the filters designed from a cutoff and a resonance.
+const CUTOFF_AND_RESONANCE: &[ParamDescriptor] = &[CUTOFF, RESONANCE];
+
+type Create<T> = fn(f32, &FilterParams, Option<&ImportedDesign>) -> Box<dyn Filter<T>>;
+
+/// Builds a filter at its default coefficients. Both fields are usually the same generic
+/// function, e.g. `Constructor { f32: biquad, f64: biquad }`, since function pointers can't be
+/// generic themselves.
+#[derive(Clone, Copy)]
+pub struct Constructor {
+    pub f32: Create<f32>,
+    pub f64: Create<f64>,
+}
+
+impl Constructor {
+    pub fn create<T: Sample>(
+        &self,
+        sample_rate: f32,
+        params: &FilterParams,
+        imported: Option<&ImportedDesign>,
+    ) -> Box<dyn Filter<T>> {
+        (T::select(self))(sample_rate, params, imported)
+    }
+}
+
+impl PerSample for Constructor {
+    type Output<T: Sample> = Create<T>;
+
+    fn for_f32(&self) -> Create<f32> {
+        self.f32
+    }
+
+    fn for_f64(&self) -> Create<f64> {
+        self.f64
+    }
+}
+
+pub struct FilterInfo {
+    pub filter: SelectedFilter,
+    /// Shown in the filter menu and to plugin hosts.
+    pub name: &'static str,
+    pub create: Constructor,
+    /// The parameters the filter responds to, in the order they are shown. Others in
+    /// `FilterParams` are ignored.
+    pub params: &'static [ParamDescriptor],
+    /// Whether the filter is built with `FilterParams::topology`.
+    pub uses_topology: bool,
+    /// For fixed-point filters, which are built with `FilterParams::fixed`, the float filter they
+    /// implement.
+    pub float_reference: Option<SelectedFilter>,
+}
+
+/// Every filter type, in menu order.
+pub const FILTERS: &[FilterInfo] = &[
+    FilterInfo {
+        filter: SelectedFilter::StateVariable,
+        name: "StateVariable",
+        create: Constructor {
+            f32: state_variable,
+            f64: state_variable,
+        },
+        params: CUTOFF_AND_RESONANCE,
+        uses_topology: false,
+        float_reference: None,
+    },
+    FilterInfo {
+        filter: SelectedFilter::StateVariableTPT,
+        name: "StateVariableTPT",
+        create: Constructor {
+            f32: state_variable_tpt,
+            f64: state_variable_tpt,
+        },
+        params: CUTOFF_AND_RESONANCE,
+        uses_topology: false,
+        float_reference: None,
+    },
+    FilterInfo {
+        filter: SelectedFilter::FirLowPass,
+        name: "FIR LowPass",
+        create: Constructor {
+            f32: fir_lowpass,
+            f64: fir_lowpass,
+        },
+        params: &[],
+        uses_topology: false,
+        float_reference: None,
+    },
+    FilterInfo {
+        filter: SelectedFilter::FirHighPass,
+        name: "FIR HighPass",
+        create: Constructor {
+            f32: fir_highpass,
+            f64: fir_highpass,
+        },
+        params: &[],
+        uses_topology: false,
+        float_reference: None,
+    },
+    FilterInfo {
+        filter: SelectedFilter::BiQuad,
+        name: "BiQuad",
+        create: Constructor {
+            f32: biquad,
+            f64: biquad,
+        },
+        params: CUTOFF_AND_RESONANCE,
+        uses_topology: true,
+        float_reference: None,
+    },
+    FilterInfo {
+        filter: SelectedFilter::Imported,
+        name: "Imported",
+        create: Constructor {
+            f32: imported,
+            f64: imported,
+        },
+        params: &[],
+        uses_topology: false,
+        float_reference: None,
+    },
+    FilterInfo {
+        filter: SelectedFilter::FixedBiQuad,
+        name: "Fixed-point BiQuad",
+        create: Constructor {
+            f32: fixed_biquad,
+            f64: fixed_biquad,
+        },
+        params: CUTOFF_AND_RESONANCE,
+        uses_topology: false,
+        float_reference: Some(SelectedFilter::BiQuad),
+    },
+    FilterInfo {
+        filter: SelectedFilter::FixedStateVariable,
+        name: "Fixed-point StateVariable",
+        create: Constructor {
+            f32: fixed_state_variable,
+            f64: fixed_state_variable,
+        },
+        params: CUTOFF_AND_RESONANCE,
+        uses_topology: false,
+        float_reference: Some(SelectedFilter::StateVariable),
+    },
+];
+
+fn state_variable<T: Sample>(
+    sample_rate: f32,
+    _params: &FilterParams,
+    _imported: Option<&ImportedDesign>,
+) -> Box<dyn Filter<T>> {
+    Box::new(StateVariableFilter::new(sample_rate))
+}
+
+fn state_variable_tpt<T: Sample>(
+    sample_rate: f32,
+    _params: &FilterParams,
+    _imported: Option<&ImportedDesign>,
+) -> Box<dyn Filter<T>> {
+    Box::new(StateVariableTPTFilter::new(sample_rate))
+}
+
+fn fir_lowpass<T: Sample>(
+    sample_rate: f32,
+    _params: &FilterParams,
+    _imported: Option<&ImportedDesign>,
+) -> Box<dyn Filter<T>> {
+    Box::new(FirLowPassFilter::new(sample_rate))
+}
+
+fn fir_highpass<T: Sample>(
+    sample_rate: f32,
+    _params: &FilterParams,
+    _imported: Option<&ImportedDesign>,
+) -> Box<dyn Filter<T>> {
+    Box::new(FirHighPassFilter::new(sample_rate))
+}
+
+fn biquad<T: Sample>(
+    sample_rate: f32,
+    params: &FilterParams,
+    _imported: Option<&ImportedDesign>,
+) -> Box<dyn Filter<T>> {
+    Box::new(BiQuadFilter::with_topology(sample_rate, params.topology))
+}
+
+/// Passes audio through until a design is loaded.
+fn imported<T: Sample>(
+    _sample_rate: f32,
+    _params: &FilterParams,
+    imported: Option<&ImportedDesign>,
+) -> Box<dyn Filter<T>> {
+    match imported {
+        Some(design) => design.create_filter(),
+        None => Box::new(SosFilter::new(Vec::new())),
+    }
+}
+
+fn fixed_biquad<T: Sample>(
+    sample_rate: f32,
+    params: &FilterParams,
+    _imported: Option<&ImportedDesign>,
+) -> Box<dyn Filter<T>> {
+    Box::new(FixedBiQuadFilter::new(sample_rate, params.fixed))
+}
+
+fn fixed_state_variable<T: Sample>(
+    sample_rate: f32,
+    params: &FilterParams,
+    _imported: Option<&ImportedDesign>,
+) -> Box<dyn Filter<T>> {
+    Box::new(FixedStateVariableFilter::new(sample_rate, params.fixed))
+}
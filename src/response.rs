@@ -0,0 +1,416 @@
+//! Frequency and impulse responses of filters, and writing them out as CSV data or SVG/PNG plots
+//! for use outside the app.
+
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+
+use crate::{Filter, Sample};
+
+/// Responses below this fraction of the impulse peak count as decayed.
+const IMPULSE_FLOOR: f32 = 1e-6;
+
+/// The response of a filter to a unit impulse, one second long, and its spectrum. Frequency
+/// domain values have one entry per FFT bin (1 Hz apart). The analysis runs in double precision
+/// whatever the filter's sample type, so f32 and f64 filters can be compared.
+pub struct FilterResponse {
+    pub magnitude_db: Vec<f32>,
+    pub phase_deg: Vec<f32>,
+    /// NaN where the magnitude is too small for the delay to be meaningful.
+    pub group_delay_samples: Vec<f32>,
+    pub impulse: Vec<f32>,
+}
+
+impl FilterResponse {
+    pub fn compute<T: Sample>(mut filter: Box<dyn Filter<T>>, sample_rate: usize) -> Self {
+        use realfft::RealFftPlanner;
+
+        let mut impulse: Vec<T> = (0..sample_rate).map(|_| T::zero()).collect();
+        impulse[0] = T::one();
+
+        let impulse_response = impulse
+            .iter()
+            .map(|sample| filter.render(*sample).as_f64())
+            .collect::<Vec<f64>>();
+
+        let mut real_planner = RealFftPlanner::<f64>::new();
+        let r2c = real_planner.plan_fft_forward(sample_rate);
+        let mut spectrum = r2c.make_output_vec();
+        r2c.process(&mut impulse_response.clone(), &mut spectrum)
+            .expect("failed to process FFT");
+
+        // Group delay is Re(FFT(n h[n]) / FFT(h[n])), which avoids unwrapping the phase.
+        let mut ramped: Vec<f64> = impulse_response
+            .iter()
+            .enumerate()
+            .map(|(n, h)| n as f64 * h)
+            .collect();
+        let mut ramped_spectrum = r2c.make_output_vec();
+        r2c.process(&mut ramped, &mut ramped_spectrum)
+            .expect("failed to process FFT");
+
+        Self {
+            magnitude_db: spectrum
+                .iter()
+                .map(|f| ((f.norm() + f32::EPSILON as f64).log10() * 20.0) as f32)
+                .collect(),
+            phase_deg: spectrum
+                .iter()
+                .map(|f| f.im.atan2(f.re).to_degrees() as f32)
+                .collect(),
+            group_delay_samples: spectrum
+                .iter()
+                .zip(ramped_spectrum.iter())
+                .map(|(h, nh)| {
+                    let power = h.norm_sqr();
+                    if power > 1e-12 {
+                        ((nh * h.conj()).re / power) as f32
+                    } else {
+                        f32::NAN
+                    }
+                })
+                .collect(),
+            impulse: impulse_response.iter().map(|h| *h as f32).collect(),
+        }
+    }
+
+    /// The impulse response up to where it has decayed, so plots and exports of short filters
+    /// aren't a second of zeros.
+    pub fn decayed_impulse(&self) -> &[f32] {
+        let peak = self
+            .impulse
+            .iter()
+            .fold(0.0f32, |peak, x| peak.max(x.abs()));
+        let len = self
+            .impulse
+            .iter()
+            .rposition(|x| x.abs() > peak * IMPULSE_FLOOR)
+            .map_or(1, |last| last + 1);
+        // A little tail makes the decay visible.
+        &self.impulse[..(len + len / 10 + 1).min(self.impulse.len())]
+    }
+}
+
+/// One of the response plots.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ResponseQuantity {
+    Magnitude,
+    Phase,
+    GroupDelay,
+    Impulse,
+}
+
+impl ResponseQuantity {
+    pub const ALL: [ResponseQuantity; 4] = [
+        ResponseQuantity::Magnitude,
+        ResponseQuantity::Phase,
+        ResponseQuantity::GroupDelay,
+        ResponseQuantity::Impulse,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ResponseQuantity::Magnitude => "Magnitude",
+            ResponseQuantity::Phase => "Phase",
+            ResponseQuantity::GroupDelay => "Group delay",
+            ResponseQuantity::Impulse => "Impulse response",
+        }
+    }
+
+    pub fn x_label(self) -> &'static str {
+        match self {
+            ResponseQuantity::Impulse => "Samples",
+            _ => "Frequency Hz",
+        }
+    }
+
+    pub fn y_label(self) -> &'static str {
+        match self {
+            ResponseQuantity::Magnitude => "dB",
+            ResponseQuantity::Phase => "phase degrees",
+            ResponseQuantity::GroupDelay => "group delay samples",
+            ResponseQuantity::Impulse => "amplitude",
+        }
+    }
+
+    /// Whether the values are indexed by frequency rather than by sample.
+    pub fn is_spectrum(self) -> bool {
+        self != ResponseQuantity::Impulse
+    }
+
+    pub fn values(self, response: &FilterResponse) -> &[f32] {
+        match self {
+            ResponseQuantity::Magnitude => &response.magnitude_db,
+            ResponseQuantity::Phase => &response.phase_deg,
+            ResponseQuantity::GroupDelay => &response.group_delay_samples,
+            ResponseQuantity::Impulse => response.decayed_impulse(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ResponseFileFormat {
+    Csv,
+    Svg,
+    /// A capture of the plot as drawn on screen.
+    Png,
+}
+
+impl ResponseFileFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ResponseFileFormat::Csv => "csv",
+            ResponseFileFormat::Svg => "svg",
+            ResponseFileFormat::Png => "png",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResponseExportOptions {
+    pub quantity: ResponseQuantity,
+    pub format: ResponseFileFormat,
+}
+
+impl Default for ResponseExportOptions {
+    fn default() -> Self {
+        Self {
+            quantity: ResponseQuantity::Magnitude,
+            format: ResponseFileFormat::Csv,
+        }
+    }
+}
+
+/// One column per named response, with the frequency in Hz or the sample index in the first.
+pub fn response_csv(
+    responses: &[(String, FilterResponse)],
+    quantity: ResponseQuantity,
+    sample_rate: usize,
+) -> String {
+    let columns: Vec<&[f32]> = responses
+        .iter()
+        .map(|(_, response)| quantity.values(response))
+        .collect();
+    let rows = columns.iter().map(|column| column.len()).max().unwrap_or(0);
+
+    let mut out = String::new();
+    _ = writeln!(out, "# {} ({})", quantity.name(), quantity.y_label());
+    _ = writeln!(out, "# sample_rate,{}", sample_rate);
+
+    let index = if quantity.is_spectrum() {
+        "frequency_hz"
+    } else {
+        "sample"
+    };
+    let names: Vec<&str> = responses.iter().map(|(name, _)| name.as_str()).collect();
+    _ = writeln!(out, "{},{}", index, names.join(","));
+
+    // Bins are 1 Hz apart, so the row index is also the frequency. Shorter impulse responses are
+    // padded with empty cells.
+    for row in 0..rows {
+        let cells: Vec<String> = columns
+            .iter()
+            .map(|column| column.get(row).map_or(String::new(), |v| v.to_string()))
+            .collect();
+        _ = writeln!(out, "{},{}", row, cells.join(","));
+    }
+
+    out
+}
+
+const SVG_WIDTH: f64 = 800.0;
+const SVG_HEIGHT: f64 = 400.0;
+const MARGIN_LEFT: f64 = 70.0;
+const MARGIN_RIGHT: f64 = 20.0;
+const MARGIN_TOP: f64 = 20.0;
+const MARGIN_BOTTOM: f64 = 50.0;
+const COLORS: [&str; 4] = ["#1f77b4", "#ff7f0e", "#2ca02c", "#d62728"];
+
+/// Points of one response as drawn, with x in Hz (or log10 Hz) or samples.
+pub fn plot_points(
+    values: &[f32],
+    quantity: ResponseQuantity,
+    log_frequency: bool,
+) -> Vec<[f64; 2]> {
+    let log_x = log_frequency && quantity.is_spectrum();
+    // DC has no place on a log axis, so skip it there.
+    values
+        .iter()
+        .enumerate()
+        .skip(log_x as usize)
+        .filter(|(_, y)| y.is_finite())
+        .map(|(index, y)| {
+            let x = index as f64;
+            [if log_x { x.log10() } else { x }, *y as f64]
+        })
+        .collect()
+}
+
+/// About `target` round tick positions covering `min..=max`, with their labels.
+fn ticks(min: f64, max: f64, target: f64) -> Vec<(f64, String)> {
+    let raw_step = (max - min) / target;
+    let magnitude = 10f64.powf(raw_step.log10().floor());
+    let step = [1.0, 2.0, 5.0, 10.0]
+        .iter()
+        .map(|m| m * magnitude)
+        .find(|step| *step >= raw_step)
+        .unwrap_or(10.0 * magnitude);
+    let decimals = (-step.log10().floor()).max(0.0) as usize;
+
+    let first = (min / step).ceil() as i64;
+    let last = (max / step).floor() as i64;
+    (first..=last)
+        .map(|i| {
+            let value = i as f64 * step;
+            (value, format!("{:.*}", decimals, value))
+        })
+        .collect()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Draws one response plot as a standalone SVG document.
+pub fn response_svg(
+    responses: &[(String, FilterResponse)],
+    quantity: ResponseQuantity,
+    log_frequency: bool,
+) -> String {
+    let log_x = log_frequency && quantity.is_spectrum();
+    let series: Vec<(&str, Vec<[f64; 2]>)> = responses
+        .iter()
+        .map(|(name, response)| {
+            let values = quantity.values(response);
+            (name.as_str(), plot_points(values, quantity, log_frequency))
+        })
+        .collect();
+
+    let (mut x_min, mut x_max, mut y_min, mut y_max) = (f64::MAX, f64::MIN, f64::MAX, f64::MIN);
+    for [x, y] in series.iter().flat_map(|(_, points)| points.iter()) {
+        x_min = x_min.min(*x);
+        x_max = x_max.max(*x);
+        y_min = y_min.min(*y);
+        y_max = y_max.max(*y);
+    }
+    if x_min >= x_max {
+        (x_min, x_max) = (0.0, 1.0);
+    }
+    if y_min >= y_max {
+        (y_min, y_max) = (y_min.min(0.0) - 1.0, y_max.max(0.0) + 1.0);
+    }
+    let y_padding = (y_max - y_min) * 0.05;
+    (y_min, y_max) = (y_min - y_padding, y_max + y_padding);
+
+    let plot_width = SVG_WIDTH - MARGIN_LEFT - MARGIN_RIGHT;
+    let plot_height = SVG_HEIGHT - MARGIN_TOP - MARGIN_BOTTOM;
+    let to_x = |x: f64| MARGIN_LEFT + (x - x_min) / (x_max - x_min) * plot_width;
+    let to_y = |y: f64| MARGIN_TOP + (y_max - y) / (y_max - y_min) * plot_height;
+
+    let mut out = String::new();
+    _ = writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}" font-family="sans-serif" font-size="12">"#,
+        w = SVG_WIDTH,
+        h = SVG_HEIGHT
+    );
+    _ = writeln!(out, r#"<rect width="100%" height="100%" fill="white"/>"#);
+
+    // Grid and tick labels.
+    let x_ticks: Vec<(f64, String)> = if log_x {
+        (x_min.ceil() as i32..=x_max.floor() as i32)
+            .map(|decade| (decade as f64, format!("{}", 10f64.powi(decade))))
+            .collect()
+    } else {
+        ticks(x_min, x_max, 8.0)
+    };
+    for (x, label) in x_ticks {
+        let px = to_x(x);
+        _ = writeln!(
+            out,
+            r##"<line x1="{px:.1}" y1="{top}" x2="{px:.1}" y2="{bottom}" stroke="#ddd"/><text x="{px:.1}" y="{label_y}" text-anchor="middle">{label}</text>"##,
+            top = MARGIN_TOP,
+            bottom = MARGIN_TOP + plot_height,
+            label_y = MARGIN_TOP + plot_height + 16.0,
+        );
+    }
+    for (y, label) in ticks(y_min, y_max, 6.0) {
+        let py = to_y(y);
+        _ = writeln!(
+            out,
+            r##"<line x1="{left}" y1="{py:.1}" x2="{right}" y2="{py:.1}" stroke="#ddd"/><text x="{label_x}" y="{label_y:.1}" text-anchor="end">{label}</text>"##,
+            left = MARGIN_LEFT,
+            right = MARGIN_LEFT + plot_width,
+            label_x = MARGIN_LEFT - 6.0,
+            label_y = py + 4.0,
+        );
+    }
+    _ = writeln!(
+        out,
+        r#"<rect x="{}" y="{}" width="{}" height="{}" fill="none" stroke="black"/>"#,
+        MARGIN_LEFT, MARGIN_TOP, plot_width, plot_height
+    );
+
+    // Axis labels.
+    _ = writeln!(
+        out,
+        r#"<text x="{:.1}" y="{}" text-anchor="middle">{}</text>"#,
+        MARGIN_LEFT + plot_width / 2.0,
+        SVG_HEIGHT - 12.0,
+        quantity.x_label()
+    );
+    _ = writeln!(
+        out,
+        r#"<text transform="translate(16 {:.1}) rotate(-90)" text-anchor="middle">{}</text>"#,
+        MARGIN_TOP + plot_height / 2.0,
+        quantity.y_label()
+    );
+
+    // Curves and legend.
+    for (index, (name, points)) in series.iter().enumerate() {
+        let color = COLORS[index % COLORS.len()];
+        let mut path = String::new();
+        for [x, y] in points {
+            _ = write!(path, "{:.1},{:.1} ", to_x(*x), to_y(*y));
+        }
+        _ = writeln!(
+            out,
+            r#"<polyline fill="none" stroke="{}" stroke-width="1.5" points="{}"/>"#,
+            color,
+            path.trim_end()
+        );
+
+        let legend_y = MARGIN_TOP + 16.0 + index as f64 * 16.0;
+        let legend_x = MARGIN_LEFT + plot_width - 120.0;
+        _ = writeln!(
+            out,
+            r#"<line x1="{:.1}" y1="{:.1}" x2="{:.1}" y2="{:.1}" stroke="{}" stroke-width="2"/><text x="{:.1}" y="{:.1}">{}</text>"#,
+            legend_x,
+            legend_y - 4.0,
+            legend_x + 20.0,
+            legend_y - 4.0,
+            color,
+            legend_x + 26.0,
+            legend_y,
+            escape(name)
+        );
+    }
+
+    _ = writeln!(out, "</svg>");
+    out
+}
+
+/// Encodes an RGBA image, such as a screenshot of a plot, as PNG.
+#[cfg(feature = "gui")]
+pub fn encode_png(image: &egui::ColorImage) -> Result<Vec<u8>, png::EncodingError> {
+    let mut bytes = Vec::new();
+    let mut encoder = png::Encoder::new(&mut bytes, image.width() as u32, image.height() as u32);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(image.as_raw())?;
+    writer.finish()?;
+    Ok(bytes)
+}
@@ -0,0 +1,145 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::NoiseGen;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum SelectedSource {
+    /// The same white noise sample on every channel.
+    MonoNoise,
+    /// Independent white noise on every channel.
+    StereoNoise,
+    /// A looped audio clip loaded from disk.
+    File,
+}
+
+/// Interleaved audio decoded from a WAV file.
+pub struct AudioClip {
+    pub sample_rate: u32,
+    pub channels: usize,
+    samples: Vec<f32>,
+}
+
+impl AudioClip {
+    pub fn load_wav<P: AsRef<Path>>(path: P) -> Result<Self, hound::Error> {
+        let mut reader = hound::WavReader::open(path)?;
+        let spec = reader.spec();
+
+        let samples = match spec.sample_format {
+            hound::SampleFormat::Float => reader.samples::<f32>().collect::<Result<Vec<_>, _>>()?,
+            hound::SampleFormat::Int => {
+                let scale = 1.0 / (1u64 << (spec.bits_per_sample - 1)) as f32;
+                reader
+                    .samples::<i32>()
+                    .map(|s| s.map(|s| s as f32 * scale))
+                    .collect::<Result<Vec<_>, _>>()?
+            }
+        };
+
+        Ok(Self {
+            sample_rate: spec.sample_rate,
+            channels: spec.channels as usize,
+            samples,
+        })
+    }
+
+    pub fn num_frames(&self) -> usize {
+        self.samples.len() / self.channels.max(1)
+    }
+
+    /// Sample of `channel` at `frame`. Channels beyond the clip's own are wrapped around, so a mono
+    /// clip feeds every output channel.
+    pub fn sample(&self, frame: usize, channel: usize) -> f32 {
+        self.samples[frame * self.channels + channel % self.channels]
+    }
+}
+
+/// Produces one frame of the selected source at a time for the audio callback.
+pub struct SourcePlayer {
+    selected_source: SelectedSource,
+    sample_rate: f32,
+    noise: Vec<NoiseGen>,
+    clip: Option<Arc<AudioClip>>,
+    // Fractional read position into the clip, in clip frames.
+    clip_position: f64,
+}
+
+impl SourcePlayer {
+    pub fn new(sample_rate: f32, num_channels: usize) -> Self {
+        Self {
+            selected_source: SelectedSource::MonoNoise,
+            sample_rate,
+            noise: (0..num_channels.max(1) as u32)
+                .map(|ch| NoiseGen::with_seed(22222 + ch * 7919))
+                .collect(),
+            clip: None,
+            clip_position: 0.0,
+        }
+    }
+
+    /// Adapts to a new stream format, keeping the selected source and clip.
+    pub fn set_format(&mut self, sample_rate: f32, num_channels: usize) {
+        self.sample_rate = sample_rate;
+        while self.noise.len() < num_channels {
+            let ch = self.noise.len() as u32;
+            self.noise.push(NoiseGen::with_seed(22222 + ch * 7919));
+        }
+        self.noise.truncate(num_channels.max(1));
+    }
+
+    pub fn selected_source(&self) -> SelectedSource {
+        self.selected_source
+    }
+
+    pub fn set_source(&mut self, selected_source: SelectedSource) {
+        self.selected_source = selected_source;
+    }
+
+    pub fn set_clip(&mut self, clip: Option<Arc<AudioClip>>) {
+        self.clip = clip;
+        self.clip_position = 0.0;
+    }
+
+    /// Fills `frame` (one sample per channel) with the next output of the source.
+    pub fn next_frame(&mut self, frame: &mut [f32]) {
+        match self.selected_source {
+            SelectedSource::MonoNoise => {
+                let value = self.noise[0].next_value();
+                frame.fill(value);
+            }
+            SelectedSource::StereoNoise => {
+                for (sample, noise) in frame.iter_mut().zip(self.noise.iter_mut()) {
+                    *sample = noise.next_value();
+                }
+            }
+            SelectedSource::File => self.next_clip_frame(frame),
+        }
+    }
+
+    // Plays the clip in a loop, linearly interpolating when its sample rate differs from the device.
+    fn next_clip_frame(&mut self, frame: &mut [f32]) {
+        let clip = match &self.clip {
+            Some(clip) if clip.num_frames() > 0 => clip,
+            _ => {
+                frame.fill(0.0);
+                return;
+            }
+        };
+
+        let num_frames = clip.num_frames();
+        let index = self.clip_position as usize;
+        let next_index = (index + 1) % num_frames;
+        let fraction = (self.clip_position - index as f64) as f32;
+
+        for (channel, sample) in frame.iter_mut().enumerate() {
+            let a = clip.sample(index, channel);
+            let b = clip.sample(next_index, channel);
+            *sample = a + (b - a) * fraction;
+        }
+
+        // A step can be longer than a short clip, so wrap with a remainder rather than one subtraction.
+        self.clip_position += clip.sample_rate as f64 / self.sample_rate as f64;
+        self.clip_position %= num_frames as f64;
+    }
+}
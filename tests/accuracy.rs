@@ -0,0 +1,371 @@
+//! Filters checked against their analytical responses. All second-order designs here are
+//! bilinear transforms prewarped at the cutoff, so the analog textbook values hold exactly at the
+//! cutoff frequency.
+
+use filters::{
+    BiQuadCoefficients, BiQuadFilter, BiQuadTopology, Coefficients, Filter, FilterResponse,
+    FilterType, FirHighPassFilter, FirLowPassFilter, FixedBiQuadFilter, FixedFormat,
+    FixedPointParams, FixedStateVariableFilter, Sample, SosFilter, StateVariableFilter,
+    StateVariableTPTFilter,
+};
+
+const SAMPLE_RATE: usize = 48000;
+const NYQUIST: usize = SAMPLE_RATE / 2;
+const BUTTERWORTH_Q: f32 = std::f32::consts::FRAC_1_SQRT_2;
+/// 20 log10(1 / sqrt(2))
+const MINUS_3_DB: f32 = -3.0103;
+const CUTOFFS: [f32; 4] = [100.0, 1000.0, 5000.0, 15000.0];
+
+const TOPOLOGIES: [BiQuadTopology; 4] = [
+    BiQuadTopology::DirectFormI,
+    BiQuadTopology::DirectFormII,
+    BiQuadTopology::TransposedDirectFormII,
+    BiQuadTopology::Lattice,
+];
+
+fn tuned<T: Sample>(
+    mut filter: Box<dyn Filter<T>>,
+    cutoff: f32,
+    q: f32,
+) -> (String, Box<dyn Filter<T>>) {
+    filter.update_coefficients(cutoff, q);
+    (format!("{} Hz, Q {}", cutoff, q), filter)
+}
+
+/// The low-pass filters, which all realize the same transfer function.
+fn low_passes<T: Sample>(cutoff: f32, q: f32) -> Vec<(String, Box<dyn Filter<T>>)> {
+    let sample_rate = SAMPLE_RATE as f32;
+    let mut filters: Vec<(String, Box<dyn Filter<T>>)> = TOPOLOGIES
+        .iter()
+        .map(|&topology| {
+            let (settings, filter) = tuned(
+                Box::new(BiQuadFilter::<T>::with_topology(sample_rate, topology)),
+                cutoff,
+                q,
+            );
+            (format!("BiQuad {:?}, {}", topology, settings), filter)
+        })
+        .collect();
+
+    let (settings, filter) = tuned(
+        Box::new(StateVariableFilter::<T>::new(sample_rate)),
+        cutoff,
+        q,
+    );
+    filters.push((format!("StateVariable, {}", settings), filter));
+
+    let (settings, filter) = tuned(
+        Box::new(StateVariableTPTFilter::<T>::with_filter_type(
+            sample_rate,
+            FilterType::LowPass,
+        )),
+        cutoff,
+        q,
+    );
+    filters.push((format!("StateVariableTPT low-pass, {}", settings), filter));
+    filters
+}
+
+fn tpt<T: Sample>(filter_type: FilterType, cutoff: f32, q: f32) -> Box<dyn Filter<T>> {
+    let mut filter = Box::new(StateVariableTPTFilter::<T>::with_filter_type(
+        SAMPLE_RATE as f32,
+        filter_type,
+    ));
+    filter.update_coefficients(cutoff, q);
+    filter
+}
+
+fn response<T: Sample>(filter: Box<dyn Filter<T>>) -> FilterResponse {
+    FilterResponse::compute(filter, SAMPLE_RATE)
+}
+
+fn assert_db(name: &str, hz: usize, actual: f32, expected: f32, tolerance: f32) {
+    assert!(
+        (actual - expected).abs() <= tolerance,
+        "{}: {:.4} dB at {} Hz, expected {:.4} dB",
+        name,
+        actual,
+        hz,
+        expected
+    );
+}
+
+fn check_low_pass_cutoff<T: Sample>(tolerance: f32) {
+    for cutoff in CUTOFFS {
+        for (name, filter) in low_passes::<T>(cutoff, BUTTERWORTH_Q) {
+            let hz = cutoff as usize;
+            assert_db(
+                &name,
+                hz,
+                response(filter).magnitude_db[hz],
+                MINUS_3_DB,
+                tolerance,
+            );
+        }
+    }
+}
+
+#[test]
+fn butterworth_low_pass_is_3_db_down_at_cutoff() {
+    check_low_pass_cutoff::<f32>(0.01);
+    check_low_pass_cutoff::<f64>(0.001);
+}
+
+#[test]
+fn butterworth_high_pass_is_3_db_down_at_cutoff() {
+    for cutoff in CUTOFFS {
+        let hz = cutoff as usize;
+        let name = format!("StateVariableTPT high-pass, {} Hz", cutoff);
+        let f32_response = response(tpt::<f32>(FilterType::HighPass, cutoff, BUTTERWORTH_Q));
+        assert_db(&name, hz, f32_response.magnitude_db[hz], MINUS_3_DB, 0.01);
+        let f64_response = response(tpt::<f64>(FilterType::HighPass, cutoff, BUTTERWORTH_Q));
+        assert_db(&name, hz, f64_response.magnitude_db[hz], MINUS_3_DB, 0.001);
+    }
+}
+
+#[test]
+fn low_pass_has_unity_dc_gain() {
+    for cutoff in CUTOFFS {
+        for q in [0.5, BUTTERWORTH_Q, 4.0] {
+            for (name, filter) in low_passes::<f32>(cutoff, q) {
+                assert_db(&name, 0, response(filter).magnitude_db[0], 0.0, 0.01);
+            }
+            for (name, filter) in low_passes::<f64>(cutoff, q) {
+                assert_db(&name, 0, response(filter).magnitude_db[0], 0.0, 1e-4);
+            }
+        }
+    }
+}
+
+#[test]
+fn high_pass_rejects_dc() {
+    for cutoff in CUTOFFS {
+        for q in [0.5, BUTTERWORTH_Q, 4.0] {
+            let dc = response(tpt::<f32>(FilterType::HighPass, cutoff, q)).magnitude_db[0];
+            assert!(dc < -90.0, "{} Hz, Q {}: {} dB at DC", cutoff, q, dc);
+            let dc = response(tpt::<f64>(FilterType::HighPass, cutoff, q)).magnitude_db[0];
+            assert!(dc < -120.0, "{} Hz, Q {}: {} dB at DC", cutoff, q, dc);
+        }
+    }
+
+    let dc = response::<f32>(Box::new(FirHighPassFilter::new(SAMPLE_RATE as f32))).magnitude_db[0];
+    assert!(dc < -120.0, "FirHighPass: {} dB at DC", dc);
+}
+
+/// Zeros of the transfer functions: the low-pass filters and the FIR low-pass at Nyquist, the
+/// FIR high-pass at DC and the band-pass at both.
+#[test]
+fn notches_are_deep() {
+    let depth = |name: &str, hz: usize, db: f32, limit: f32| {
+        assert!(db < limit, "{}: {:.1} dB at {} Hz", name, db, hz);
+    };
+
+    for cutoff in CUTOFFS {
+        for (name, filter) in low_passes::<f64>(cutoff, BUTTERWORTH_Q) {
+            depth(
+                &name,
+                NYQUIST,
+                response(filter).magnitude_db[NYQUIST],
+                -100.0,
+            );
+        }
+
+        let band_pass = response(tpt::<f64>(FilterType::BandPass, cutoff, 2.0));
+        let name = format!("StateVariableTPT band-pass, {} Hz", cutoff);
+        depth(&name, 0, band_pass.magnitude_db[0], -120.0);
+        depth(&name, NYQUIST, band_pass.magnitude_db[NYQUIST], -120.0);
+    }
+
+    let fir_low_pass = response::<f32>(Box::new(FirLowPassFilter::new(SAMPLE_RATE as f32)));
+    depth(
+        "FirLowPass",
+        NYQUIST,
+        fir_low_pass.magnitude_db[NYQUIST],
+        -120.0,
+    );
+    let fir_high_pass = response::<f32>(Box::new(FirHighPassFilter::new(SAMPLE_RATE as f32)));
+    depth("FirHighPass", 0, fir_high_pass.magnitude_db[0], -120.0);
+}
+
+/// At the cutoff the second-order low- and high-pass gain is Q, and so is the peak of the
+/// (unnormalized) band-pass.
+#[test]
+fn gain_at_resonance_is_q() {
+    for cutoff in [200.0, 1000.0, 5000.0] {
+        for q in [2.0f32, 5.0, 10.0] {
+            let hz = cutoff as usize;
+            let expected = 20.0 * q.log10();
+
+            for (name, filter) in low_passes::<f64>(cutoff, q) {
+                assert_db(
+                    &name,
+                    hz,
+                    response(filter).magnitude_db[hz],
+                    expected,
+                    0.001,
+                );
+            }
+            for filter_type in [FilterType::HighPass, FilterType::BandPass] {
+                let name = format!("StateVariableTPT {:?}, {} Hz, Q {}", filter_type, cutoff, q);
+                let magnitude = response(tpt::<f64>(filter_type, cutoff, q)).magnitude_db;
+                assert_db(&name, hz, magnitude[hz], expected, 0.001);
+            }
+
+            let band_pass = response(tpt::<f64>(FilterType::BandPass, cutoff, q)).magnitude_db;
+            let peak = (0..=NYQUIST)
+                .max_by(|a, b| band_pass[*a].total_cmp(&band_pass[*b]))
+                .unwrap();
+            assert_eq!(peak, hz, "band-pass peak at {} Hz, Q {}", cutoff, q);
+        }
+    }
+}
+
+fn impulse<T: Sample>(filter: &mut dyn Filter<T>, len: usize) -> Vec<f64> {
+    (0..len)
+        .map(|n| {
+            let x = if n == 0 { T::one() } else { T::zero() };
+            filter.render(x).as_f64()
+        })
+        .collect()
+}
+
+fn check_equivalent<T: Sample>(tolerance: f64) {
+    for cutoff in CUTOFFS {
+        for q in [0.5, BUTTERWORTH_Q, 2.0, 10.0] {
+            let mut filters = low_passes::<T>(cutoff, q);
+            let (reference_name, reference) = &mut filters[0];
+            let expected = impulse(reference.as_mut(), 4096);
+            let reference_name = reference_name.clone();
+
+            for (name, filter) in filters.iter_mut().skip(1) {
+                let actual = impulse(filter.as_mut(), 4096);
+                let error = actual
+                    .iter()
+                    .zip(expected.iter())
+                    .fold(0.0f64, |max, (a, e)| max.max((a - e).abs()));
+                assert!(
+                    error <= tolerance,
+                    "{} differs from {} by {:e}",
+                    name,
+                    reference_name,
+                    error
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn biquad_and_state_variable_filters_match() {
+    check_equivalent::<f32>(1e-4);
+    check_equivalent::<f64>(1e-10);
+}
+
+/// Both run transposed direct form II, so they agree to the bit.
+#[test]
+fn sos_filter_matches_biquad() {
+    let mut biquad = BiQuadFilter::<f64>::with_topology(
+        SAMPLE_RATE as f32,
+        BiQuadTopology::TransposedDirectFormII,
+    );
+    biquad.update_coefficients(3000.0, 3.0);
+    let Coefficients::BiQuad(section) = biquad.coefficients() else {
+        unreachable!()
+    };
+    let mut sos = SosFilter::new(vec![section]);
+    assert_eq!(
+        impulse(&mut sos, 2048),
+        impulse(&mut biquad, 2048).as_slice()
+    );
+
+    let identity = BiQuadCoefficients {
+        b0: 1.0,
+        b1: 0.0,
+        b2: 0.0,
+        a1: 0.0,
+        a2: 0.0,
+    };
+    let mut cascade = SosFilter::<f64>::new(vec![section, identity]);
+    let mut biquad = BiQuadFilter::<f64>::with_topology(
+        SAMPLE_RATE as f32,
+        BiQuadTopology::TransposedDirectFormII,
+    );
+    biquad.update_coefficients(3000.0, 3.0);
+    assert_eq!(impulse(&mut cascade, 2048), impulse(&mut biquad, 2048));
+}
+
+/// With 31-bit samples and 32-bit coefficients the fixed-point filters are close enough to their
+/// float references for the analytical values to hold.
+#[test]
+fn fixed_point_filters_are_3_db_down_at_cutoff() {
+    let params = FixedPointParams {
+        format: FixedFormat::Q31,
+        coefficient_bits: 32,
+    };
+    for cutoff in [1000.0, 5000.0, 15000.0] {
+        let hz = cutoff as usize;
+        let filters: [(&str, Box<dyn Filter<f64>>); 2] = [
+            (
+                "FixedBiQuad",
+                Box::new(FixedBiQuadFilter::new(SAMPLE_RATE as f32, params)),
+            ),
+            (
+                "FixedStateVariable",
+                Box::new(FixedStateVariableFilter::new(SAMPLE_RATE as f32, params)),
+            ),
+        ];
+        for (name, mut filter) in filters {
+            filter.update_coefficients(cutoff, BUTTERWORTH_Q);
+            let magnitude = response(filter).magnitude_db;
+            let name = format!("{}, {} Hz", name, cutoff);
+            assert_db(&name, hz, magnitude[hz], MINUS_3_DB, 0.01);
+            assert_db(&name, 0, magnitude[0], 0.0, 0.01);
+        }
+    }
+}
+
+/// The state variable filter's states reach about Q times the input. A step near full scale
+/// overshoots past it, and only the output may clip: the rest of the response matches the float
+/// filter with its output clamped.
+#[test]
+fn fixed_state_variable_clips_only_its_output() {
+    let params = FixedPointParams {
+        format: FixedFormat::Q31,
+        coefficient_bits: 32,
+    };
+    let mut fixed: Box<dyn Filter<f64>> =
+        Box::new(FixedStateVariableFilter::new(SAMPLE_RATE as f32, params));
+    let mut float: Box<dyn Filter<f64>> = Box::new(StateVariableFilter::new(SAMPLE_RATE as f32));
+    fixed.update_coefficients(1000.0, 8.0);
+    float.update_coefficients(1000.0, 8.0);
+
+    let mut peak = 0.0f64;
+    for n in 0..4096 {
+        let reference = float.render(0.9);
+        let actual = fixed.render(0.9);
+        peak = peak.max(reference.abs());
+        let expected = reference.clamp(-1.0, 1.0);
+        assert!(
+            (actual - expected).abs() < 1e-6,
+            "sample {}: {}, expected {}",
+            n,
+            actual,
+            expected
+        );
+    }
+    assert!(peak > 1.5, "the step only peaks at {}", peak);
+}
+
+/// Like the float filters, new fixed-point filters run with their design's initial coefficients.
+#[test]
+fn new_fixed_point_filters_match_their_float_designs() {
+    let params = FixedPointParams::default();
+    let fixed: Box<dyn Filter<f64>> = Box::new(FixedBiQuadFilter::new(SAMPLE_RATE as f32, params));
+    let float: Box<dyn Filter<f64>> = Box::new(BiQuadFilter::new(SAMPLE_RATE as f32));
+    assert_eq!(fixed.coefficients(), float.coefficients());
+
+    let fixed: Box<dyn Filter<f64>> =
+        Box::new(FixedStateVariableFilter::new(SAMPLE_RATE as f32, params));
+    let float: Box<dyn Filter<f64>> = Box::new(StateVariableFilter::new(SAMPLE_RATE as f32));
+    assert_eq!(fixed.coefficients(), float.coefficients());
+}
@@ -0,0 +1,92 @@
+//! Settings restored into the app (from a saved session, a preset or by re-linking channels) have
+//! to reach the audio thread, not only the controls.
+
+use std::sync::mpsc::channel;
+
+use filters::app::AudioCommand;
+use filters::audio::AudioEngine;
+use filters::{
+    AudioFilterApp, BiQuadTopology, FilterParams, FixedFormat, FixedPointParams, SelectedFilter,
+    StereoMode,
+};
+
+/// Sends everything the app would after restoring a session and applies it like the audio
+/// thread does.
+fn synced_engine(app: &mut AudioFilterApp) -> AudioEngine {
+    let (events_tx, _events_rx) = channel();
+    let mut engine = AudioEngine::new(FilterParams::default(), app.vol, events_tx);
+    let (audio_tx, audio_rx) = channel::<AudioCommand>();
+    app.audio_tx = Some(audio_tx);
+    app.sync_audio();
+    while let Ok(cmd) = audio_rx.try_recv() {
+        engine.handle_command(cmd);
+    }
+    engine
+}
+
+fn biquad(topology: BiQuadTopology) -> FilterParams {
+    FilterParams {
+        filter: SelectedFilter::BiQuad,
+        cutoff_hz: 500.0,
+        resonance_q: 2.0,
+        topology,
+        ..FilterParams::default()
+    }
+}
+
+#[test]
+fn restored_topology_reaches_the_audio_thread() {
+    let mut app = AudioFilterApp::new();
+    app.channel_params = [biquad(BiQuadTopology::Lattice); 2];
+    app.channels_linked = true;
+    let engine = synced_engine(&mut app);
+    for channel in 0..2 {
+        assert_eq!(engine.params(channel), Some(app.channel_params[0]));
+    }
+
+    let mut app = AudioFilterApp::new();
+    app.channel_params = [
+        biquad(BiQuadTopology::TransposedDirectFormII),
+        biquad(BiQuadTopology::DirectFormII),
+    ];
+    app.channels_linked = false;
+    app.stereo_mode = StereoMode::LeftRight;
+    let engine = synced_engine(&mut app);
+    for channel in 0..2 {
+        assert_eq!(engine.params(channel), Some(app.channel_params[channel]));
+    }
+}
+
+fn fixed(filter: SelectedFilter, format: FixedFormat, coefficient_bits: u32) -> FilterParams {
+    FilterParams {
+        filter,
+        cutoff_hz: 2000.0,
+        fixed: FixedPointParams {
+            format,
+            coefficient_bits,
+        },
+        ..FilterParams::default()
+    }
+}
+
+#[test]
+fn restored_fixed_point_settings_reach_the_audio_thread() {
+    let mut app = AudioFilterApp::new();
+    app.channel_params = [fixed(SelectedFilter::FixedBiQuad, FixedFormat::Q31, 24); 2];
+    app.channels_linked = true;
+    let engine = synced_engine(&mut app);
+    for channel in 0..2 {
+        assert_eq!(engine.params(channel), Some(app.channel_params[0]));
+    }
+
+    let mut app = AudioFilterApp::new();
+    app.channel_params = [
+        fixed(SelectedFilter::FixedBiQuad, FixedFormat::Q31, 20),
+        fixed(SelectedFilter::FixedStateVariable, FixedFormat::Q15, 12),
+    ];
+    app.channels_linked = false;
+    let engine = synced_engine(&mut app);
+    for channel in 0..2 {
+        assert_eq!(engine.params(channel), Some(app.channel_params[channel]));
+    }
+}
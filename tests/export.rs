@@ -0,0 +1,99 @@
+//! Exported coefficients have to carry the precision they are declared with, and the header has
+//! to describe the design they come from.
+
+use filters::export::{export_coefficients, ExportFormat, ExportOptions, Precision};
+use filters::registry::{CUTOFF, RESONANCE};
+use filters::{FilterParams, SelectedFilter};
+
+fn exported_values(params: &FilterParams, precision: Precision) -> Vec<f64> {
+    let options = ExportOptions {
+        format: ExportFormat::Csv,
+        precision,
+        digits: 17,
+        ..ExportOptions::default()
+    };
+    export_coefficients(params, None, 48000, &options)
+        .lines()
+        .filter(|line| !line.starts_with('#') && !line.starts_with("name"))
+        .map(|line| line.split(',').nth(1).unwrap().parse().unwrap())
+        .collect()
+}
+
+/// A double export is designed in `f64`, not widened from the `f32` design.
+#[test]
+fn double_export_keeps_f64_digits() {
+    for filter in [SelectedFilter::BiQuad, SelectedFilter::StateVariableTPT] {
+        let params = FilterParams {
+            filter,
+            cutoff_hz: 1234.5,
+            resonance_q: 3.3,
+            ..FilterParams::default()
+        };
+        let single = exported_values(&params, Precision::Single);
+        let double = exported_values(&params, Precision::Double);
+        assert_eq!(single.len(), double.len());
+
+        for (&s, &d) in single.iter().zip(&double) {
+            assert_eq!(s, s as f32 as f64, "{:?}: single export not an f32", filter);
+            assert!(
+                (s - d).abs() <= 1e-6 * d.abs().max(1.0),
+                "{:?}: {} vs {}",
+                filter,
+                s,
+                d
+            );
+        }
+        assert!(
+            double.iter().any(|&d| d != d as f32 as f64),
+            "{:?}: double export {:?} is only f32 precise",
+            filter,
+            double
+        );
+    }
+}
+
+fn csv_header(params: &FilterParams, sample_rate: u32) -> String {
+    let options = ExportOptions {
+        format: ExportFormat::Csv,
+        ..ExportOptions::default()
+    };
+    let csv = export_coefficients(params, None, sample_rate, &options);
+    csv.lines().next().unwrap().to_string()
+}
+
+/// The header names the clamped cutoff the coefficients were designed at, and only the parameters
+/// the filter has.
+#[test]
+fn description_matches_the_design() {
+    let params = FilterParams {
+        filter: SelectedFilter::BiQuad,
+        cutoff_hz: 30000.0,
+        resonance_q: 2.0,
+        ..FilterParams::default()
+    };
+    let header = csv_header(&params, 44100);
+    assert!(
+        header.contains(&format!(
+            "{} {},",
+            CUTOFF.label(),
+            params.cutoff_at(44100.0)
+        )),
+        "{}",
+        header
+    );
+    assert!(
+        header.contains(&format!("{} 2,", RESONANCE.label())),
+        "{}",
+        header
+    );
+    assert!(!header.contains("30000"), "{}", header);
+
+    for filter in [SelectedFilter::FirLowPass, SelectedFilter::Imported] {
+        let header = csv_header(&FilterParams { filter, ..params }, 44100);
+        assert!(
+            !header.contains(CUTOFF.name) && !header.contains(RESONANCE.name),
+            "{}",
+            header
+        );
+    }
+}
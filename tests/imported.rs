@@ -0,0 +1,101 @@
+//! Coefficient files with a header row: rows shorter than the header must be rejected, not read
+//! past their end. Values are kept at the precision they were written in, and the app's own CSV
+//! export reads back.
+
+use filters::export::{export_coefficients, ExportFormat, ExportOptions, Precision};
+use filters::imported::ImportError;
+use filters::{BiQuadCoefficients, Coefficients, FilterParams, ImportedDesign, SelectedFilter};
+
+#[test]
+fn header_with_taps_is_read() {
+    let design = ImportedDesign::parse("index,tap\n0,0.5\n1,0.25\n").unwrap();
+    assert_eq!(design, ImportedDesign::Fir(vec![0.5, 0.25]));
+}
+
+#[test]
+fn tap_rows_shorter_than_header_are_rejected() {
+    assert!(matches!(
+        ImportedDesign::parse("index,tap\n0.5\n0.25\n"),
+        Err(ImportError::MissingColumns {
+            line: 2,
+            columns: 1,
+            header: 2
+        })
+    ));
+}
+
+#[test]
+fn section_rows_shorter_than_header_are_rejected() {
+    assert!(matches!(
+        ImportedDesign::parse("b0,b1,b2,a1,a2\n1 0 0\n"),
+        Err(ImportError::MissingColumns {
+            line: 2,
+            columns: 3,
+            header: 5
+        })
+    ));
+}
+
+/// Double-precision filters get the file's values, not values rounded through `f32`.
+#[test]
+fn values_keep_double_precision() {
+    let section = BiQuadCoefficients {
+        b0: 0.1,
+        b1: 0.2,
+        b2: 0.1,
+        a1: -1.9999,
+        a2: 0.99991,
+    };
+    let design = ImportedDesign::parse("0.2 0.4 0.2 2 -3.9998 1.99982\n").unwrap();
+    assert_eq!(design, ImportedDesign::Sos(vec![section]));
+    assert_eq!(
+        design.create_filter::<f64>().coefficients(),
+        Coefficients::Cascade(vec![section])
+    );
+
+    let design = ImportedDesign::parse("0.1\n0.7\n").unwrap();
+    assert_eq!(
+        design.create_filter::<f64>().coefficients(),
+        Coefficients::Fir(vec![0.1, 0.7])
+    );
+}
+
+fn csv_export(filter: SelectedFilter) -> (FilterParams, String) {
+    let params = FilterParams {
+        filter,
+        cutoff_hz: 2500.0,
+        resonance_q: 4.0,
+        ..FilterParams::default()
+    };
+    let options = ExportOptions {
+        format: ExportFormat::Csv,
+        precision: Precision::Double,
+        digits: 17,
+        ..ExportOptions::default()
+    };
+    let csv = export_coefficients(&params, None, 48000, &options);
+    (params, csv)
+}
+
+#[test]
+fn biquad_csv_export_reads_back() {
+    let (params, csv) = csv_export(SelectedFilter::BiQuad);
+    let Coefficients::BiQuad(section) =
+        params.create_filter_as::<f64>(48000.0, None).coefficients()
+    else {
+        panic!("a BiQuad has biquad coefficients");
+    };
+    assert_eq!(
+        ImportedDesign::parse(&csv).unwrap(),
+        ImportedDesign::Sos(vec![section])
+    );
+}
+
+#[test]
+fn state_variable_csv_export_is_rejected() {
+    let (_, csv) = csv_export(SelectedFilter::StateVariable);
+    assert!(matches!(
+        ImportedDesign::parse(&csv),
+        Err(ImportError::MissingValue { name: "b0" })
+    ));
+}
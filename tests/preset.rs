@@ -0,0 +1,109 @@
+//! Preset files have to survive a round trip, keep loading after fields are added, and refuse
+//! versions this build can't read.
+
+use filters::preset::{PresetFileError, PRESET_FILE_VERSION};
+use filters::{
+    BiQuadTopology, FilterParams, FixedFormat, FixedPointParams, Preset, PresetFile,
+    SelectedFilter, StereoMode,
+};
+
+fn preset() -> Preset {
+    Preset {
+        name: "Side high-pass".to_string(),
+        channel_params: [
+            FilterParams {
+                filter: SelectedFilter::BiQuad,
+                cutoff_hz: 440.0,
+                resonance_q: 2.5,
+                topology: BiQuadTopology::Lattice,
+                ..FilterParams::default()
+            },
+            FilterParams {
+                filter: SelectedFilter::FixedStateVariable,
+                cutoff_hz: 250.0,
+                resonance_q: 0.5,
+                fixed: FixedPointParams {
+                    format: FixedFormat::Q31,
+                    coefficient_bits: 20,
+                },
+                ..FilterParams::default()
+            },
+        ],
+        channels_linked: false,
+        stereo_mode: StereoMode::MidSide,
+    }
+}
+
+#[test]
+fn preset_file_round_trips() {
+    let file = PresetFile::new(preset(), 44100);
+    let json = file.to_json().unwrap();
+    assert_eq!(PresetFile::from_json(&json).unwrap(), file);
+    assert_eq!(file.version, PRESET_FILE_VERSION);
+}
+
+/// A file written before `topology` and `fixed` existed, with some other fields left out too.
+#[test]
+fn missing_fields_take_defaults() {
+    let json = r#"{
+        "version": 1,
+        "sample_rate": 48000,
+        "preset": {
+            "name": "Side high-pass",
+            "channel_params": [
+                { "filter": "StateVariable", "cutoff_hz": 1000.0, "resonance_q": 0.707 },
+                { "filter": "FirHighPass", "cutoff_hz": 250.0 }
+            ],
+            "stereo_mode": "MidSide"
+        }
+    }"#;
+    let file = PresetFile::from_json(json).unwrap();
+    let [left, right] = file.preset.channel_params;
+    assert_eq!(left.filter, SelectedFilter::StateVariable);
+    assert_eq!(left.topology, BiQuadTopology::default());
+    assert_eq!(left.fixed, FixedPointParams::default());
+    assert_eq!(right.filter, SelectedFilter::FirHighPass);
+    assert_eq!(right.cutoff_hz, 250.0);
+    assert_eq!(right.resonance_q, FilterParams::default().resonance_q);
+    assert!(file.preset.channels_linked);
+    assert_eq!(file.preset.stereo_mode, StereoMode::MidSide);
+
+    let json = r#"{ "version": 1, "sample_rate": 48000, "preset": { "name": "Empty" } }"#;
+    let file = PresetFile::from_json(json).unwrap();
+    assert_eq!(file.preset.channel_params, [FilterParams::default(); 2]);
+}
+
+#[test]
+fn unreadable_versions_are_rejected() {
+    let mut value = serde_json::to_value(PresetFile::new(preset(), 48000)).unwrap();
+
+    value["version"] = (PRESET_FILE_VERSION + 1).into();
+    assert!(matches!(
+        PresetFile::from_json(&value.to_string()),
+        Err(PresetFileError::UnsupportedVersion(v)) if v == u64::from(PRESET_FILE_VERSION) + 1
+    ));
+
+    // Would be read as version 1 if truncated to 32 bits.
+    value["version"] = (u64::from(u32::MAX) + 2).into();
+    assert!(matches!(
+        PresetFile::from_json(&value.to_string()),
+        Err(PresetFileError::UnsupportedVersion(4294967297))
+    ));
+
+    value["version"] = 0.into();
+    assert!(matches!(
+        PresetFile::from_json(&value.to_string()),
+        Err(PresetFileError::UnsupportedVersion(0))
+    ));
+
+    value.as_object_mut().unwrap().remove("version");
+    assert!(matches!(
+        PresetFile::from_json(&value.to_string()),
+        Err(PresetFileError::MissingVersion)
+    ));
+
+    assert!(matches!(
+        PresetFile::from_json(r#"{ "version": 1, "preset": "#),
+        Err(PresetFileError::Json(_))
+    ));
+}
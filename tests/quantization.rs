@@ -0,0 +1,54 @@
+//! Quantization analysis has to report less deviation as the coefficient word gets longer.
+
+use filters::quantization::QuantizationAnalysis;
+use filters::{FilterParams, SelectedFilter};
+
+const SAMPLE_RATE: usize = 48000;
+
+fn max_deviations(params: &FilterParams) -> Vec<(u32, f32)> {
+    let coefficients = params
+        .create_filter_as::<f64>(SAMPLE_RATE as f32, None)
+        .coefficients();
+    (8..=32)
+        .step_by(4)
+        .map(|bits| {
+            let analysis = QuantizationAnalysis::new(&coefficients, bits, SAMPLE_RATE);
+            (bits, analysis.max_deviation_db)
+        })
+        .collect()
+}
+
+/// Deviation near the response's zeros (the low-pass at Nyquist) used to dominate, so longer
+/// words could report more deviation than shorter ones.
+#[test]
+fn deviation_shrinks_with_more_bits() {
+    for filter in [
+        SelectedFilter::BiQuad,
+        SelectedFilter::StateVariable,
+        SelectedFilter::StateVariableTPT,
+        SelectedFilter::FirLowPass,
+        SelectedFilter::FirHighPass,
+    ] {
+        let params = FilterParams {
+            filter,
+            cutoff_hz: 1000.0,
+            resonance_q: 2.0,
+            ..FilterParams::default()
+        };
+        let deviations = max_deviations(&params);
+        for pair in deviations.windows(2) {
+            let ((fewer, coarse), (more, fine)) = (pair[0], pair[1]);
+            assert!(
+                fine <= coarse.max(1e-4),
+                "{:?}: {} dB at {} bits but {} dB at {} bits",
+                filter,
+                coarse,
+                fewer,
+                fine,
+                more
+            );
+        }
+        let (_, finest) = deviations[deviations.len() - 1];
+        assert!(finest < 1e-3, "{:?}: {} dB at 32 bits", filter, finest);
+    }
+}
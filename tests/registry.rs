@@ -0,0 +1,103 @@
+//! The registry has to describe every filter type truthfully: the UI only shows the parameters
+//! it lists, so a filter must respond to those and ignore the rest. (Topologies share their
+//! coefficients, so `uses_topology` can't be checked this way.)
+
+use filters::registry::{ParamDescriptor, ParamScale};
+use filters::{FilterParams, SelectedFilter, FILTERS};
+
+const SAMPLE_RATE: f32 = 48000.0;
+
+/// Every parameter some filter lists, once each.
+fn descriptors() -> Vec<ParamDescriptor> {
+    let mut descriptors: Vec<ParamDescriptor> = Vec::new();
+    for descriptor in FILTERS.iter().flat_map(|info| info.params) {
+        if !descriptors.iter().any(|known| known.id == descriptor.id) {
+            descriptors.push(*descriptor);
+        }
+    }
+    descriptors
+}
+
+#[test]
+fn every_filter_type_is_registered_once() {
+    let registered: Vec<SelectedFilter> = FILTERS.iter().map(|info| info.filter).collect();
+    assert_eq!(SelectedFilter::ALL.to_vec(), registered);
+
+    for (index, info) in FILTERS.iter().enumerate() {
+        for other in &FILTERS[..index] {
+            assert_ne!(
+                other.filter, info.filter,
+                "{} is registered twice",
+                info.name
+            );
+            assert_ne!(other.name, info.name, "two filters are named {}", info.name);
+        }
+        assert_eq!(info.filter.info().filter, info.filter, "{}", info.name);
+        if let Some(reference) = info.float_reference {
+            assert!(reference.info().float_reference.is_none(), "{}", info.name);
+        }
+    }
+}
+
+#[test]
+fn descriptors_are_consistent() {
+    let defaults = FilterParams::default();
+    for descriptor in descriptors() {
+        if descriptor.scale == ParamScale::Logarithmic {
+            assert!(
+                descriptor.min > 0.0,
+                "{} is shown on a log scale",
+                descriptor.name
+            );
+        }
+        assert!(
+            descriptor.min <= descriptor.default && descriptor.default <= descriptor.max,
+            "{} defaults outside its range",
+            descriptor.name
+        );
+        assert_eq!(
+            defaults.param(descriptor.id),
+            descriptor.default,
+            "{}",
+            descriptor.name
+        );
+    }
+}
+
+/// Whether changing `change` gives `filter` different coefficients.
+fn responds_to(filter: SelectedFilter, change: impl Fn(&mut FilterParams)) -> bool {
+    let params = FilterParams {
+        filter,
+        ..FilterParams::default()
+    };
+    let mut changed = params;
+    change(&mut changed);
+    let before = params
+        .create_filter_as::<f64>(SAMPLE_RATE, None)
+        .coefficients();
+    let after = changed
+        .create_filter_as::<f64>(SAMPLE_RATE, None)
+        .coefficients();
+    before != after
+}
+
+#[test]
+fn filters_respond_to_the_parameters_they_list() {
+    for info in FILTERS {
+        for descriptor in descriptors() {
+            let listed = info.params.iter().any(|param| param.id == descriptor.id);
+            let responds = responds_to(info.filter, |params| {
+                params.set_param(descriptor.id, descriptor.max)
+            });
+            assert_eq!(listed, responds, "{} and {}", info.name, descriptor.name);
+        }
+
+        let responds = responds_to(info.filter, |params| params.fixed.coefficient_bits = 10);
+        assert_eq!(
+            info.float_reference.is_some(),
+            responds,
+            "{} and fixed-point settings",
+            info.name
+        );
+    }
+}
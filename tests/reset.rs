@@ -0,0 +1,149 @@
+//! `Filter::reset` must clear the state but keep the coefficients: after a reset a filter has to
+//! behave exactly like a freshly built one with the same parameters.
+
+use filters::{
+    BiQuadTopology, Filter, FilterParams, FixedFormat, FixedPointParams, ImportedDesign,
+    MultiChannelFilter, Sample, SelectedFilter,
+};
+
+const SAMPLE_RATE: f32 = 48000.0;
+
+fn designs() -> Vec<ImportedDesign> {
+    vec![
+        ImportedDesign::parse("0.2 0.4 0.2 1 -0.4 0.2\n1 -1 0 1 -0.5 0").unwrap(),
+        ImportedDesign::parse("0.1 0.2 0.4 0.2 0.1").unwrap(),
+    ]
+}
+
+/// Every filter kind, biquad topology and fixed-point format, tuned to a resonant setting so
+/// leftover state is audible for a while.
+fn all_params() -> Vec<FilterParams> {
+    let base = FilterParams {
+        cutoff_hz: 2000.0,
+        resonance_q: 4.0,
+        ..FilterParams::default()
+    };
+    let mut params: Vec<FilterParams> = SelectedFilter::ALL
+        .iter()
+        .map(|&filter| FilterParams { filter, ..base })
+        .collect();
+    for topology in [
+        BiQuadTopology::DirectFormII,
+        BiQuadTopology::TransposedDirectFormII,
+        BiQuadTopology::Lattice,
+    ] {
+        params.push(FilterParams {
+            filter: SelectedFilter::BiQuad,
+            topology,
+            ..base
+        });
+    }
+    for filter in [
+        SelectedFilter::FixedBiQuad,
+        SelectedFilter::FixedStateVariable,
+    ] {
+        params.push(FilterParams {
+            filter,
+            fixed: FixedPointParams {
+                format: FixedFormat::Q31,
+                coefficient_bits: 24,
+            },
+            ..base
+        });
+    }
+    params
+}
+
+/// A deterministic, non-trivial input.
+fn signal<T: Sample>(len: usize) -> Vec<T> {
+    (0..len)
+        .map(|n| T::cast(0.5 * (n as f64 * 0.37).sin() + 0.3 * (n as f64 * 0.05).cos()))
+        .collect()
+}
+
+fn impulse_response<T: Sample>(filter: &mut dyn Filter<T>, len: usize) -> Vec<T> {
+    (0..len)
+        .map(|n| filter.render(if n == 0 { T::one() } else { T::zero() }))
+        .collect()
+}
+
+fn check_reset<T: Sample>(params: &FilterParams, imported: Option<&ImportedDesign>) {
+    let mut fresh = params.create_filter_as::<T>(SAMPLE_RATE, imported);
+    let mut used = params.create_filter_as::<T>(SAMPLE_RATE, imported);
+
+    for x in signal::<T>(1000) {
+        used.render(x);
+    }
+    used.reset();
+
+    assert_eq!(
+        used.coefficients(),
+        fresh.coefficients(),
+        "{:?} lost its coefficients on reset",
+        params
+    );
+    assert_eq!(
+        impulse_response(used.as_mut(), 256),
+        impulse_response(fresh.as_mut(), 256),
+        "{:?} kept state across reset",
+        params
+    );
+}
+
+#[test]
+fn reset_clears_state_of_every_filter() {
+    for params in all_params() {
+        check_reset::<f32>(&params, None);
+        check_reset::<f64>(&params, None);
+    }
+}
+
+#[test]
+fn reset_clears_state_of_imported_filters() {
+    let params = FilterParams {
+        filter: SelectedFilter::Imported,
+        ..FilterParams::default()
+    };
+    for design in designs() {
+        check_reset::<f32>(&params, Some(&design));
+        check_reset::<f64>(&params, Some(&design));
+    }
+}
+
+#[test]
+fn reset_twice_is_the_same_as_once() {
+    for params in all_params() {
+        let mut filter = params.create_filter(SAMPLE_RATE);
+        for x in signal::<f32>(100) {
+            filter.render(x);
+        }
+        filter.reset();
+        let once = impulse_response(filter.as_mut(), 64);
+        filter.reset();
+        filter.reset();
+        assert_eq!(impulse_response(filter.as_mut(), 64), once, "{:?}", params);
+    }
+}
+
+#[test]
+fn multichannel_reset_clears_every_channel() {
+    for params in all_params() {
+        let mut fresh = MultiChannelFilter::new(SAMPLE_RATE, 2, params);
+        let mut used = MultiChannelFilter::new(SAMPLE_RATE, 2, params);
+
+        for x in signal::<f32>(500) {
+            used.render_frame(&mut [x, -x]);
+        }
+        used.reset();
+
+        for n in 0..128 {
+            let x = if n == 0 { 1.0 } else { 0.0 };
+            let mut expected = [x, 0.5 * x];
+            let mut actual = expected;
+            fresh.render_frame(&mut expected);
+            used.render_frame(&mut actual);
+            assert_eq!(actual, expected, "{:?}, sample {}", params, n);
+        }
+        assert_eq!(used.params(0), Some(params));
+    }
+}
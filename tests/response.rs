@@ -0,0 +1,83 @@
+//! Response exports have to carry the analysis values unchanged: the CSV at its frequencies, and
+//! the SVG as a well-formed document with one curve per response.
+
+use quick_xml::events::Event;
+use quick_xml::Reader;
+
+use filters::response::{response_csv, response_svg, ResponseQuantity};
+use filters::{BiQuadFilter, Filter, FilterResponse, StateVariableFilter};
+
+const SAMPLE_RATE: usize = 48000;
+const BUTTERWORTH_Q: f32 = std::f32::consts::FRAC_1_SQRT_2;
+
+fn responses() -> Vec<(String, FilterResponse)> {
+    let mut biquad: Box<dyn Filter<f64>> = Box::new(BiQuadFilter::new(SAMPLE_RATE as f32));
+    biquad.update_coefficients(1000.0, BUTTERWORTH_Q);
+    let mut svf: Box<dyn Filter<f64>> = Box::new(StateVariableFilter::new(SAMPLE_RATE as f32));
+    svf.update_coefficients(5000.0, 2.0);
+    vec![
+        (
+            "BiQuad".to_string(),
+            FilterResponse::compute(biquad, SAMPLE_RATE),
+        ),
+        (
+            "SVF <Q & 2>".to_string(),
+            FilterResponse::compute(svf, SAMPLE_RATE),
+        ),
+    ]
+}
+
+/// The RBJ low-pass with a Butterworth Q is 3 dB down at its cutoff and flat at DC.
+#[test]
+fn magnitude_csv_has_the_cutoff_3_db_down() {
+    let csv = response_csv(&responses(), ResponseQuantity::Magnitude, SAMPLE_RATE);
+    let mut rows = csv.lines().filter(|line| !line.starts_with('#'));
+    assert_eq!(rows.next(), Some("frequency_hz,BiQuad,SVF <Q & 2>"));
+
+    let magnitude_at = |hz: usize| -> f64 {
+        let row = csv
+            .lines()
+            .find(|line| line.starts_with(&format!("{},", hz)))
+            .unwrap();
+        row.split(',').nth(1).unwrap().parse().unwrap()
+    };
+    assert!(
+        (magnitude_at(1000) + 3.0103).abs() < 0.01,
+        "{}",
+        magnitude_at(1000)
+    );
+    assert!(magnitude_at(0).abs() < 0.01, "{}", magnitude_at(0));
+    assert_eq!(rows.count(), SAMPLE_RATE / 2 + 1);
+}
+
+#[test]
+fn svg_is_well_formed_with_one_curve_per_response() {
+    let responses = responses();
+    for quantity in ResponseQuantity::ALL {
+        for log_frequency in [false, true] {
+            let svg = response_svg(&responses, quantity, log_frequency);
+            let mut reader = Reader::from_str(&svg);
+            let (mut curves, mut legend) = (0, Vec::new());
+            loop {
+                match reader.read_event() {
+                    Ok(Event::Empty(element)) if element.name().as_ref() == b"polyline" => {
+                        curves += 1;
+                    }
+                    Ok(Event::Text(text)) => legend.push(text.unescape().unwrap().into_owned()),
+                    Ok(Event::Eof) => break,
+                    Ok(_) => {}
+                    Err(err) => panic!("{:?}: {} in\n{}", quantity, err, svg),
+                }
+            }
+            assert_eq!(curves, responses.len(), "{:?}", quantity);
+            for (name, _) in &responses {
+                assert!(
+                    legend.contains(name),
+                    "{:?}: no legend for {}",
+                    quantity,
+                    name
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,38 @@
+//! Looping a clip has to stay in bounds whatever the ratio between clip and device sample rate.
+
+use std::sync::Arc;
+
+use filters::{AudioClip, SelectedSource, SourcePlayer};
+
+fn write_clip(name: &str, sample_rate: u32, samples: &[f32]) -> AudioClip {
+    let path = std::env::temp_dir().join(format!("{}-{}.wav", name, std::process::id()));
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate,
+        bits_per_sample: 32,
+        sample_format: hound::SampleFormat::Float,
+    };
+    let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+    for &sample in samples {
+        writer.write_sample(sample).unwrap();
+    }
+    writer.finalize().unwrap();
+    let clip = AudioClip::load_wav(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    clip
+}
+
+/// A clip shorter than one resampling step used to index past its end after the first frame.
+#[test]
+fn clip_shorter_than_one_step_wraps() {
+    let clip = write_clip("short-clip", 48000, &[0.25, -0.25]);
+    let mut player = SourcePlayer::new(8000.0, 1);
+    player.set_source(SelectedSource::File);
+    player.set_clip(Some(Arc::new(clip)));
+
+    let mut frame = [0.0f32; 1];
+    for _ in 0..100 {
+        player.next_frame(&mut frame);
+        assert!(frame[0].abs() <= 0.25, "sample {} out of range", frame[0]);
+    }
+}
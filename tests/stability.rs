@@ -0,0 +1,366 @@
+//! Randomized stability checks over the parameter ranges the UI allows: cutoff from 20 Hz to just
+//! below Nyquist, Q from 0.1 to 30, and common sample rates. Draws come from a seeded `NoiseGen`,
+//! so failures reproduce; the failing settings are in the panic message.
+
+use std::sync::Arc;
+
+use filters::registry::CUTOFF;
+use filters::{
+    BiQuadTopology, Filter, FilterParams, FilterType, FixedFormat, FixedPointParams,
+    ImportedDesign, MultiChannelFilter, NoiseGen, Sample, SelectedFilter, StateVariableFilter,
+    StateVariableTPTFilter,
+};
+
+const SAMPLE_RATES: [f32; 5] = [22050.0, 44100.0, 48000.0, 96000.0, 192000.0];
+const MIN_CUTOFF: f32 = 20.0;
+/// Fraction of the sample rate the cutoff is drawn up to.
+const MAX_CUTOFF_RATIO: f32 = 0.49;
+const MIN_Q: f32 = 0.1;
+const MAX_Q: f32 = 30.0;
+const CASES: usize = 40;
+
+/// Draws parameters with a log-uniform distribution, like the UI's logarithmic sliders.
+struct Draw {
+    noise: NoiseGen,
+}
+
+impl Draw {
+    fn new(seed: u32) -> Self {
+        Self {
+            noise: NoiseGen::with_seed(seed),
+        }
+    }
+
+    /// Uniform in [0, 1].
+    fn unit(&mut self) -> f32 {
+        (self.noise.next_value() + 1.0) * 0.5
+    }
+
+    fn log_uniform(&mut self, min: f32, max: f32) -> f32 {
+        (min.ln() + self.unit() * (max.ln() - min.ln())).exp()
+    }
+
+    fn sample_rate(&mut self) -> f32 {
+        let index = (self.unit() * SAMPLE_RATES.len() as f32) as usize;
+        SAMPLE_RATES[index.min(SAMPLE_RATES.len() - 1)]
+    }
+
+    fn cutoff(&mut self, sample_rate: f32) -> f32 {
+        self.log_uniform(MIN_CUTOFF, sample_rate * MAX_CUTOFF_RATIO)
+    }
+
+    fn q(&mut self) -> f32 {
+        self.log_uniform(MIN_Q, MAX_Q)
+    }
+}
+
+/// Every filter kind and structure, with parameters filled in per case.
+fn structures() -> Vec<FilterParams> {
+    let mut params: Vec<FilterParams> = SelectedFilter::ALL
+        .iter()
+        .map(|&filter| FilterParams {
+            filter,
+            ..FilterParams::default()
+        })
+        .collect();
+    for topology in [
+        BiQuadTopology::DirectFormII,
+        BiQuadTopology::TransposedDirectFormII,
+        BiQuadTopology::Lattice,
+    ] {
+        params.push(FilterParams {
+            filter: SelectedFilter::BiQuad,
+            topology,
+            ..FilterParams::default()
+        });
+    }
+    for filter in [
+        SelectedFilter::FixedBiQuad,
+        SelectedFilter::FixedStateVariable,
+    ] {
+        params.push(FilterParams {
+            filter,
+            fixed: FixedPointParams {
+                format: FixedFormat::Q31,
+                coefficient_bits: 24,
+            },
+            ..FilterParams::default()
+        });
+    }
+    params
+}
+
+fn design() -> ImportedDesign {
+    // scipy's butter(4, 0.2, output='sos'): a 4th order Butterworth low-pass at a tenth of the
+    // sample rate.
+    ImportedDesign::parse(
+        "0.00482434 0.00964869 0.00482434 1 -1.04859958 0.29614036\n\
+         1 2 1 1 -1.32091343 0.63273879",
+    )
+    .unwrap()
+}
+
+fn is_fixed_point(params: &FilterParams) -> bool {
+    params.filter.float_reference().is_some()
+}
+
+/// Sum of |h[n]|, the largest gain the filter can have for any input. Rendering stops once the
+/// impulse response has decayed; a response that doesn't decay within `max_len` is unstable.
+fn l1_norm<T: Sample>(filter: &mut dyn Filter<T>, max_len: usize, context: &str) -> f64 {
+    let block = 1024;
+    let mut total = 0.0;
+    let mut n = 0;
+    loop {
+        let mut block_sum = 0.0;
+        for _ in 0..block {
+            let x = if n == 0 { T::one() } else { T::zero() };
+            let y = filter.render(x).as_f64();
+            assert!(
+                y.is_finite(),
+                "{}: impulse response not finite at {}",
+                context,
+                n
+            );
+            block_sum += y.abs();
+            n += 1;
+        }
+        total += block_sum;
+        if block_sum <= total * 1e-9 {
+            return total;
+        }
+        assert!(
+            n < max_len,
+            "{}: impulse response still at {:e} per block after {} samples",
+            context,
+            block_sum,
+            n
+        );
+    }
+}
+
+fn check_bounded<T: Sample>(params: &FilterParams, sample_rate: f32, imported: &ImportedDesign) {
+    let context = format!(
+        "{:?} at {} Hz, {}",
+        params,
+        sample_rate,
+        std::any::type_name::<T>()
+    );
+    let mut filter = params.create_filter_as::<T>(sample_rate, Some(imported));
+
+    // The slowest pole's time constant is about 2Q / sin(w0) samples for resonant settings and
+    // 1 / (Q sin(w0)) for overdamped ones. Twenty of them is plenty to decay.
+    let w0 = 2.0 * std::f64::consts::PI * (params.cutoff_hz / sample_rate) as f64;
+    let q = params.resonance_q as f64;
+    let time_constant = (2.0 * q).max(1.0 / q) / w0.sin();
+    let max_len = (20.0 * time_constant) as usize + 4096;
+
+    // Saturating fixed-point filters can't exceed full scale whatever their poles.
+    let bound = if is_fixed_point(params) {
+        1.0
+    } else {
+        l1_norm(filter.as_mut(), max_len, &context) * (1.0 + 1e-3)
+    };
+    filter.reset();
+
+    let mut noise = NoiseGen::with_seed(params.cutoff_hz.to_bits());
+    for n in 0..8192 {
+        let x = T::cast(noise.next_value() as f64);
+        let y = filter.render(x).as_f64();
+        assert!(
+            y.is_finite() && y.abs() <= bound,
+            "{}: output {} at sample {} exceeds the bound {}",
+            context,
+            y,
+            n,
+            bound
+        );
+    }
+}
+
+#[test]
+fn filters_are_bounded_across_parameter_space() {
+    let imported = design();
+    let mut draw = Draw::new(1234);
+    for structure in structures() {
+        for _ in 0..CASES {
+            let sample_rate = draw.sample_rate();
+            let params = FilterParams {
+                cutoff_hz: draw.cutoff(sample_rate),
+                resonance_q: draw.q(),
+                ..structure
+            };
+            check_bounded::<f32>(&params, sample_rate, &imported);
+            check_bounded::<f64>(&params, sample_rate, &imported);
+        }
+    }
+}
+
+/// The corners of the parameter space, where coefficients are most extreme.
+#[test]
+fn filters_are_bounded_at_parameter_extremes() {
+    let imported = design();
+    for structure in structures() {
+        for sample_rate in SAMPLE_RATES {
+            for cutoff_hz in [MIN_CUTOFF, sample_rate * MAX_CUTOFF_RATIO] {
+                for resonance_q in [MIN_Q, MAX_Q] {
+                    let params = FilterParams {
+                        cutoff_hz,
+                        resonance_q,
+                        ..structure
+                    };
+                    check_bounded::<f32>(&params, sample_rate, &imported);
+                    check_bounded::<f64>(&params, sample_rate, &imported);
+                }
+            }
+        }
+    }
+}
+
+/// The slider allows cutoffs above Nyquist at low sample rates. Filters have to clamp them, both
+/// when built and when retuned, instead of going unstable and tripping the NaN watchdog.
+#[test]
+fn cutoff_above_nyquist_is_clamped() {
+    let sample_rate = 22050.0;
+    for structure in structures() {
+        let mut filters = MultiChannelFilter::new(sample_rate, 2, structure);
+        filters.set_imported(Some(Arc::new(design())));
+        filters.set_params(
+            1,
+            FilterParams {
+                cutoff_hz: CUTOFF.max,
+                ..structure
+            },
+        );
+        let mut rebuilt = MultiChannelFilter::new(
+            sample_rate,
+            2,
+            FilterParams {
+                cutoff_hz: CUTOFF.max,
+                ..structure
+            },
+        );
+        rebuilt.set_imported(Some(Arc::new(design())));
+
+        let mut noise = NoiseGen::new();
+        for n in 0..8192 {
+            for filters in [&mut filters, &mut rebuilt] {
+                let mut frame = [noise.next_value(); 2];
+                filters.render_frame(&mut frame);
+                assert!(
+                    frame
+                        .iter()
+                        .all(|y| y.is_finite() && y.abs() <= 4.0 * MAX_Q),
+                    "{:?}: output {:?} at sample {}",
+                    structure,
+                    frame,
+                    n
+                );
+            }
+        }
+        assert_eq!(
+            filters.recoveries() + rebuilt.recoveries(),
+            0,
+            "{:?}",
+            structure
+        );
+    }
+}
+
+/// The zero-delay-feedback filters whose parameters may change every sample.
+fn modulatable<T: Sample>(sample_rate: f32) -> Vec<(&'static str, Box<dyn Filter<T>>)> {
+    vec![
+        (
+            "StateVariable",
+            Box::new(StateVariableFilter::new(sample_rate)),
+        ),
+        (
+            "StateVariableTPT low-pass",
+            Box::new(StateVariableTPTFilter::with_filter_type(
+                sample_rate,
+                FilterType::LowPass,
+            )),
+        ),
+        (
+            "StateVariableTPT band-pass",
+            Box::new(StateVariableTPTFilter::with_filter_type(
+                sample_rate,
+                FilterType::BandPass,
+            )),
+        ),
+        (
+            "StateVariableTPT high-pass",
+            Box::new(StateVariableTPTFilter::with_filter_type(
+                sample_rate,
+                FilterType::HighPass,
+            )),
+        ),
+    ]
+}
+
+/// Runs noise through filters whose parameters are set on every sample by `modulation`, which
+/// gets the sample index and returns cutoff and Q.
+fn check_modulated<T: Sample>(
+    scenario: &str,
+    sample_rate: f32,
+    mut modulation: impl FnMut(usize) -> (f32, f32),
+) {
+    // The gain of a filter held at any setting in range is at most about MAX_Q; an unstable
+    // filter grows exponentially and crosses this within a few hundred samples.
+    let bound = 4.0 * MAX_Q as f64;
+    let settings: Vec<(f32, f32)> = (0..sample_rate as usize / 2).map(&mut modulation).collect();
+
+    for (name, mut filter) in modulatable::<T>(sample_rate) {
+        let mut noise = NoiseGen::new();
+        for (n, &(cutoff, q)) in settings.iter().enumerate() {
+            filter.update_coefficients(cutoff, q);
+            let y = filter.render(T::cast(noise.next_value() as f64)).as_f64();
+            assert!(
+                y.is_finite() && y.abs() <= bound,
+                "{}, {} ({}) at {} Hz: output {} at sample {} with cutoff {} Hz, Q {}",
+                name,
+                scenario,
+                std::any::type_name::<T>(),
+                sample_rate,
+                y,
+                n,
+                cutoff,
+                q
+            );
+        }
+    }
+}
+
+fn modulation_scenarios<T: Sample>() {
+    for sample_rate in SAMPLE_RATES {
+        let max_cutoff = sample_rate * MAX_CUTOFF_RATIO;
+
+        let mut draw = Draw::new(sample_rate as u32);
+        check_modulated::<T>("random settings every sample", sample_rate, |_| {
+            (draw.cutoff(sample_rate), draw.q())
+        });
+
+        check_modulated::<T>("cutoff jumping between extremes", sample_rate, |n| {
+            let cutoff = if n % 2 == 0 { MIN_CUTOFF } else { max_cutoff };
+            (cutoff, MAX_Q)
+        });
+
+        // An audio-rate LFO sweeping the whole range, at maximum resonance.
+        check_modulated::<T>("1 kHz exponential sweep", sample_rate, |n| {
+            let phase = (2.0 * std::f32::consts::PI * 1000.0 * n as f32 / sample_rate).sin();
+            let cutoff =
+                (MIN_CUTOFF.ln() + (phase + 1.0) * 0.5 * (max_cutoff.ln() - MIN_CUTOFF.ln())).exp();
+            (cutoff, MAX_Q)
+        });
+
+        check_modulated::<T>("Q jumping between extremes", sample_rate, |n| {
+            let q = if n % 3 == 0 { MIN_Q } else { MAX_Q };
+            (1000.0, q)
+        });
+    }
+}
+
+#[test]
+fn zero_delay_feedback_filters_survive_fast_modulation() {
+    modulation_scenarios::<f32>();
+    modulation_scenarios::<f64>();
+}